@@ -0,0 +1,303 @@
+//! Authentication for the admin API under `/api`.
+//!
+//! Admins present a bearer token read at startup from `ADMIN_TOKEN`, or from
+//! the file named by `ADMIN_TOKEN_FILE`. An optional second token, from
+//! `ADMIN_READONLY_TOKEN` / `ADMIN_READONLY_TOKEN_FILE`, may only read: it
+//! sees the config with secrets redacted and cannot change anything. Without
+//! any token configured nobody is admin: everyone gets read-only access, so
+//! the dashboard works but nothing can be changed through the API.
+//!
+//! Clients that present too many wrong tokens get their further wrong
+//! tokens refused with `429` for a while, so tokens can't be guessed at
+//! speed. The right token always works, so guessers sharing an address with
+//! the admin, or behind the same proxy, can't lock the admin out.
+
+use crate::config::Config;
+use crate::error;
+use crate::state::AppState;
+use anyhow::{bail, Context};
+use axum::extract::connect_info::ConnectInfo;
+use axum::extract::{Request, State};
+use axum::http::{header, Extensions, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Shown in place of secrets to read-only users.
+pub const REDACTED: &str = "[redacted]";
+
+/// Wrong tokens a client may present within [`FAILURE_WINDOW`] before its
+/// wrong tokens are throttled.
+const MAX_FAILURES: usize = 5;
+const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    ReadOnly,
+}
+
+/// Why a request to the admin API was refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Denied {
+    /// No token, or a wrong one.
+    Unauthorized,
+    /// Too many wrong tokens lately; try again after the wait.
+    Throttled(Duration),
+}
+
+impl IntoResponse for Denied {
+    fn into_response(self) -> Response {
+        match self {
+            Denied::Unauthorized => error::json_error(
+                StatusCode::UNAUTHORIZED,
+                "invalid_request_error",
+                "admin_auth_required",
+                "A valid admin token is required",
+                &[],
+            ),
+            Denied::Throttled(wait) => {
+                let mut response = error::json_error(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "rate_limit_error",
+                    "too_many_attempts",
+                    "Too many invalid admin tokens; try again later",
+                    &[],
+                );
+                let seconds = wait.as_secs_f64().ceil().max(1.0) as u64;
+                response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+                response
+            }
+        }
+    }
+}
+
+/// Recent wrong tokens per client address. Clients whose address is unknown
+/// share one entry.
+#[derive(Clone, Default)]
+struct Failures(Arc<Mutex<HashMap<Option<IpAddr>, VecDeque<Instant>>>>);
+
+impl Failures {
+    /// How long `client` must wait before trying again, if it is throttled.
+    fn throttled(&self, client: Option<IpAddr>) -> Option<Duration> {
+        let now = Instant::now();
+        let mut failures = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let recent = failures.get_mut(&client)?;
+        while recent.front().is_some_and(|at| now.duration_since(*at) >= FAILURE_WINDOW) {
+            recent.pop_front();
+        }
+        if recent.len() < MAX_FAILURES {
+            return None;
+        }
+        Some(FAILURE_WINDOW.saturating_sub(now.duration_since(recent[recent.len() - MAX_FAILURES])))
+    }
+
+    fn record(&self, client: Option<IpAddr>) {
+        let now = Instant::now();
+        let mut failures = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget clients that have gone quiet, so the map can't grow without bound
+        failures.retain(|_, recent| recent.back().is_some_and(|at| now.duration_since(*at) < FAILURE_WINDOW));
+        failures.entry(client).or_default().push_back(now);
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct AdminAuth {
+    admin_token: Option<String>,
+    read_only_token: Option<String>,
+    failures: Failures,
+}
+
+impl AdminAuth {
+    pub fn new(admin_token: Option<String>, read_only_token: Option<String>) -> Self {
+        let non_empty = |t: Option<String>| t.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        Self {
+            admin_token: non_empty(admin_token),
+            read_only_token: non_empty(read_only_token),
+            failures: Failures::default(),
+        }
+    }
+
+    /// Tokens from the environment: `<VAR>` itself, or the file `<VAR>_FILE`.
+    /// An empty variable counts as unset. Fails on an unreadable token file,
+    /// and on a read-only token without an admin token, which would leave
+    /// nobody able to change anything.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let token = |var: &str| -> anyhow::Result<Option<String>> {
+            if let Some(value) = std::env::var(var).ok().filter(|v| !v.trim().is_empty()) {
+                return Ok(Some(value));
+            }
+            match std::env::var(format!("{}_FILE", var)).ok().filter(|p| !p.trim().is_empty()) {
+                Some(path) => fs::read_to_string(&path)
+                    .map(Some)
+                    .with_context(|| format!("failed to read {}_FILE {}", var, path)),
+                None => Ok(None),
+            }
+        };
+        let auth = Self::new(token("ADMIN_TOKEN")?, token("ADMIN_READONLY_TOKEN")?);
+        if auth.admin_token.is_none() && auth.read_only_token.is_some() {
+            bail!("ADMIN_READONLY_TOKEN is set without ADMIN_TOKEN; set ADMIN_TOKEN too");
+        }
+        Ok(auth)
+    }
+
+    /// Whether the admin API needs a token.
+    pub fn required(&self) -> bool {
+        self.admin_token.is_some() || self.read_only_token.is_some()
+    }
+
+    /// The role a token grants, if any.
+    pub fn role(&self, token: &str) -> Option<Role> {
+        let matches = |expected: &Option<String>| expected.as_deref().is_some_and(|e| constant_time_eq(e, token));
+        if matches(&self.admin_token) {
+            Some(Role::Admin)
+        } else if matches(&self.read_only_token) {
+            Some(Role::ReadOnly)
+        } else {
+            None
+        }
+    }
+
+    /// The role `token` grants `client`. Everyone may only read when no token
+    /// is configured. A wrong token counts against the client, and once it
+    /// has too many its wrong tokens are throttled.
+    pub fn check(&self, token: Option<&str>, client: Option<IpAddr>) -> Result<Role, Denied> {
+        if !self.required() {
+            return Ok(Role::ReadOnly);
+        }
+        let token = token.ok_or(Denied::Unauthorized)?;
+        if let Some(role) = self.role(token.trim()) {
+            return Ok(role);
+        }
+        if let Some(wait) = self.failures.throttled(client) {
+            return Err(Denied::Throttled(wait));
+        }
+        self.failures.record(client);
+        Err(Denied::Unauthorized)
+    }
+
+    /// [`AdminAuth::check`] for a request's bearer token.
+    pub fn role_of(&self, headers: &HeaderMap, client: Option<IpAddr>) -> Result<Role, Denied> {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        self.check(token, client)
+    }
+}
+
+/// The address a request came from, when the server records it.
+pub fn client_ip(extensions: &Extensions) -> Option<IpAddr> {
+    extensions.get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip())
+}
+
+/// Compare without stopping at the first difference, so response times
+/// don't reveal how much of a guessed token is right.
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Middleware for admin routes: reads need any role, everything else needs
+/// `Admin`. The role is passed on to handlers as a request extension.
+pub async fn require_role(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let client = client_ip(request.extensions());
+    let role = match state.admin.role_of(request.headers(), client) {
+        Ok(role) => role,
+        Err(denied) => {
+            tracing::warn!(method = %request.method(), path = %request.uri().path(), client = ?client, "Admin request without a valid token");
+            return denied.into_response();
+        }
+    };
+    let read = matches!(*request.method(), Method::GET | Method::HEAD);
+    if role == Role::ReadOnly && !read {
+        tracing::warn!(method = %request.method(), path = %request.uri().path(), "Read-only access used for a change");
+        let message = if state.admin.required() {
+            "This token may only read"
+        } else {
+            "The admin API is read-only until ADMIN_TOKEN is set"
+        };
+        return error::json_error(StatusCode::FORBIDDEN, "permission_error", "read_only", message, &[]);
+    }
+    request.extensions_mut().insert(role);
+    next.run(request).await
+}
+
+/// Replace every key and credential in the config with [`REDACTED`].
+pub fn redact_secrets(config: &mut Config) {
+    for provider in &mut config.providers {
+        if provider.api_key.is_some() {
+            provider.api_key = Some(REDACTED.to_string());
+        }
+        for key in &mut provider.api_keys {
+            key.key = REDACTED.to_string();
+        }
+        if let Some(ref mut aws) = provider.aws {
+            aws.secret_access_key = REDACTED.to_string();
+            if aws.session_token.is_some() {
+                aws.session_token = Some(REDACTED.to_string());
+            }
+        }
+    }
+    for key in &mut config.virtual_keys {
+        key.key = REDACTED.to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_roles() {
+        let auth = AdminAuth::new(Some("admin-secret\n".to_string()), Some("viewer".to_string()));
+        assert!(auth.required());
+        assert_eq!(auth.role_of(&bearer("admin-secret"), None), Ok(Role::Admin));
+        assert_eq!(auth.role_of(&bearer("viewer"), None), Ok(Role::ReadOnly));
+        assert_eq!(auth.role_of(&bearer("admin-secreT"), None), Err(Denied::Unauthorized));
+        assert_eq!(auth.role_of(&HeaderMap::new(), None), Err(Denied::Unauthorized));
+
+        // With no token configured nobody gets to change anything
+        let unset = AdminAuth::new(None, Some("  ".to_string()));
+        assert!(!unset.required());
+        assert_eq!(unset.role_of(&HeaderMap::new(), None), Ok(Role::ReadOnly));
+        assert_eq!(unset.check(Some("anything"), None), Ok(Role::ReadOnly));
+    }
+
+    #[test]
+    fn test_wrong_tokens_throttled_per_client() {
+        let auth = AdminAuth::new(Some("admin-secret".to_string()), None);
+        let guesser = Some(IpAddr::from([10, 0, 0, 1]));
+        for _ in 0..MAX_FAILURES {
+            assert_eq!(auth.check(Some("guess"), guesser), Err(Denied::Unauthorized));
+        }
+        // Further guesses are throttled, but the right token still works
+        assert!(matches!(auth.check(Some("guess"), guesser), Err(Denied::Throttled(_))));
+        assert_eq!(auth.check(Some("admin-secret"), guesser), Ok(Role::Admin));
+        // Other clients are unaffected, and a missing token isn't a guess
+        let other = Some(IpAddr::from([10, 0, 0, 2]));
+        assert_eq!(auth.check(None, other), Err(Denied::Unauthorized));
+        assert_eq!(auth.check(Some("admin-secret"), other), Ok(Role::Admin));
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let mut config = Config::default();
+        config.providers[0].api_key = Some("sk-live".to_string());
+        config.providers[1].api_key = None;
+        redact_secrets(&mut config);
+        assert_eq!(config.providers[0].api_key.as_deref(), Some(REDACTED));
+        assert_eq!(config.providers[1].api_key, None);
+    }
+}
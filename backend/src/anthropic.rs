@@ -0,0 +1,656 @@
+//! Anthropic Messages API translation helpers.
+//!
+//! Converts Anthropic SSE events (`message_start`, `content_block_*`,
+//! `message_delta`, `message_stop`) into OpenAI `chat.completion.chunk` events,
+//! and converts native Messages API requests and responses to and from the
+//! OpenAI chat format for clients calling `/v1/messages`.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Map an Anthropic `stop_reason` to an OpenAI `finish_reason`.
+pub fn finish_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "max_tokens" => "length",
+        "tool_use" => "tool_calls",
+        "refusal" => "content_filter",
+        _ => "stop",
+    }
+}
+
+/// Map an OpenAI `finish_reason` to an Anthropic `stop_reason`.
+pub fn stop_reason(finish_reason: &str) -> &'static str {
+    match finish_reason {
+        "length" => "max_tokens",
+        "tool_calls" | "function_call" => "tool_use",
+        "content_filter" => "refusal",
+        _ => "end_turn",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Requests: Anthropic Messages -> OpenAI chat
+// ---------------------------------------------------------------------------
+
+/// Flatten a string or an array of text blocks into plain text.
+fn text_of(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => String::new(),
+    }
+}
+
+/// Convert an Anthropic image block into an OpenAI `image_url` part.
+fn image_part(block: &Value) -> Option<Value> {
+    let source = block.get("source")?;
+    let url = match source.get("type").and_then(|t| t.as_str()) {
+        Some("base64") => format!(
+            "data:{};base64,{}",
+            source.get("media_type").and_then(|v| v.as_str()).unwrap_or("image/png"),
+            source.get("data").and_then(|v| v.as_str()).unwrap_or(""),
+        ),
+        Some("url") => source.get("url")?.as_str()?.to_string(),
+        _ => return None,
+    };
+    Some(json!({"type": "image_url", "image_url": {"url": url}}))
+}
+
+/// Convert one Anthropic message into one or more OpenAI messages.
+/// `tool_result` blocks become separate `tool` role messages.
+fn convert_message(message: &Value, out: &mut Vec<Value>) {
+    let role = message.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+    let content = message.get("content").cloned().unwrap_or(Value::Null);
+    let Value::Array(blocks) = content else {
+        out.push(json!({"role": role, "content": content}));
+        return;
+    };
+
+    if role == "assistant" {
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for block in &blocks {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("text") => text.push_str(block.get("text").and_then(|t| t.as_str()).unwrap_or("")),
+                Some("tool_use") => tool_calls.push(json!({
+                    "id": block.get("id").cloned().unwrap_or(Value::Null),
+                    "type": "function",
+                    "function": {
+                        "name": block.get("name").cloned().unwrap_or(Value::Null),
+                        "arguments": block.get("input").map(|i| i.to_string()).unwrap_or_else(|| "{}".to_string()),
+                    },
+                })),
+                _ => {}
+            }
+        }
+        let mut msg = json!({"role": "assistant", "content": if text.is_empty() { Value::Null } else { Value::String(text) }});
+        if !tool_calls.is_empty() {
+            msg["tool_calls"] = Value::Array(tool_calls);
+        }
+        out.push(msg);
+        return;
+    }
+
+    let mut parts = Vec::new();
+    for block in &blocks {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => parts.push(json!({"type": "text", "text": block.get("text").cloned().unwrap_or(Value::Null)})),
+            Some("image") => parts.extend(image_part(block)),
+            Some("tool_result") => out.push(json!({
+                "role": "tool",
+                "tool_call_id": block.get("tool_use_id").cloned().unwrap_or(Value::Null),
+                "content": text_of(block.get("content").unwrap_or(&Value::Null)),
+            })),
+            _ => {}
+        }
+    }
+    if !parts.is_empty() {
+        out.push(json!({"role": role, "content": parts}));
+    }
+}
+
+/// Convert an Anthropic Messages request body into OpenAI chat `messages` and
+/// the remaining top-level parameters.
+pub fn request_to_openai(body: &Value) -> (Vec<Value>, HashMap<String, Value>) {
+    let mut messages = Vec::new();
+    if let Some(system) = body.get("system") {
+        let text = text_of(system);
+        if !text.is_empty() {
+            messages.push(json!({"role": "system", "content": text}));
+        }
+    }
+    for message in body.get("messages").and_then(|m| m.as_array()).into_iter().flatten() {
+        convert_message(message, &mut messages);
+    }
+
+    let mut extra = HashMap::new();
+    for key in ["max_tokens", "temperature", "top_p", "stream"] {
+        if let Some(v) = body.get(key) {
+            extra.insert(key.to_string(), v.clone());
+        }
+    }
+    if let Some(stop) = body.get("stop_sequences") {
+        extra.insert("stop".to_string(), stop.clone());
+    }
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .map(|t| json!({
+                "type": "function",
+                "function": {
+                    "name": t.get("name").cloned().unwrap_or(Value::Null),
+                    "description": t.get("description").cloned().unwrap_or(Value::Null),
+                    "parameters": t.get("input_schema").cloned().unwrap_or_else(|| json!({"type": "object"})),
+                },
+            }))
+            .collect();
+        extra.insert("tools".to_string(), Value::Array(tools));
+    }
+    if let Some(choice) = body.get("tool_choice") {
+        let mapped = match choice.get("type").and_then(|t| t.as_str()) {
+            Some("any") => json!("required"),
+            Some("none") => json!("none"),
+            Some("tool") => json!({"type": "function", "function": {"name": choice.get("name").cloned().unwrap_or(Value::Null)}}),
+            _ => json!("auto"),
+        };
+        extra.insert("tool_choice".to_string(), mapped);
+    }
+    if let Some(user) = body.pointer("/metadata/user_id") {
+        extra.insert("user".to_string(), user.clone());
+    }
+    (messages, extra)
+}
+
+// ---------------------------------------------------------------------------
+// Responses: OpenAI chat -> Anthropic Messages
+// ---------------------------------------------------------------------------
+
+/// Convert a buffered OpenAI chat completion into an Anthropic Messages response.
+pub fn response_from_openai(resp: &Value, model: &str) -> Value {
+    let choice = &resp["choices"][0];
+    let message = &choice["message"];
+    let mut content = Vec::new();
+    if let Some(text) = message.get("content").and_then(|c| c.as_str()).filter(|t| !t.is_empty()) {
+        content.push(json!({"type": "text", "text": text}));
+    }
+    for call in message.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+        let arguments = call.pointer("/function/arguments").and_then(|a| a.as_str()).unwrap_or("{}");
+        content.push(json!({
+            "type": "tool_use",
+            "id": call.get("id").cloned().unwrap_or(Value::Null),
+            "name": call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+            "input": serde_json::from_str::<Value>(arguments).unwrap_or_else(|_| json!({})),
+        }));
+    }
+    json!({
+        "id": resp.get("id").cloned().unwrap_or_else(|| json!(format!("msg_{}", uuid::Uuid::new_v4().simple()))),
+        "type": "message",
+        "role": "assistant",
+        "model": resp.get("model").and_then(|m| m.as_str()).unwrap_or(model),
+        "content": content,
+        "stop_reason": stop_reason(choice.get("finish_reason").and_then(|f| f.as_str()).unwrap_or("stop")),
+        "stop_sequence": null,
+        "usage": {
+            "input_tokens": resp.pointer("/usage/prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+            "output_tokens": resp.pointer("/usage/completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+        },
+    })
+}
+
+/// Token usage reported by an Anthropic stream or response.
+#[derive(Default)]
+pub struct Usage {
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+impl Usage {
+    /// Record the `usage` object from an event or response, if present.
+    pub fn read(&mut self, usage: &Value) {
+        if let Some(t) = usage.get("input_tokens").and_then(|v| v.as_u64()) {
+            self.input_tokens = Some(t);
+        }
+        if let Some(t) = usage.get("output_tokens").and_then(|v| v.as_u64()) {
+            self.output_tokens = Some(t);
+        }
+    }
+
+    /// Record usage from the JSON payload of one native Anthropic stream event.
+    pub fn observe(&mut self, data: &str) {
+        let Ok(event) = serde_json::from_str::<Value>(data) else { return };
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => self.read(&event["message"]["usage"]),
+            Some("message_delta") => self.read(&event["usage"]),
+            _ => {}
+        }
+    }
+}
+
+/// Translates one Anthropic stream into OpenAI chunk events, event by event.
+pub struct ChunkTranslator {
+    id: String,
+    model: String,
+    created: i64,
+    usage: Usage,
+    /// Anthropic content block index → OpenAI tool call index.
+    tool_indices: HashMap<u64, usize>,
+}
+
+impl ChunkTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+            model: model.to_string(),
+            created: chrono::Utc::now().timestamp(),
+            usage: Usage::default(),
+            tool_indices: HashMap::new(),
+        }
+    }
+
+    /// Translate the JSON payload of one Anthropic `data:` line into zero or
+    /// more OpenAI chunk payloads.
+    pub fn translate(&mut self, data: &str) -> Vec<String> {
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            return Vec::new();
+        };
+
+        match event.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+            "message_start" => {
+                let message = &event["message"];
+                if let Some(id) = message.get("id").and_then(|v| v.as_str()) {
+                    self.id = id.to_string();
+                }
+                if let Some(model) = message.get("model").and_then(|v| v.as_str()) {
+                    self.model = model.to_string();
+                }
+                self.usage.read(&message["usage"]);
+                vec![self.chunk(json!({"role": "assistant", "content": ""}), None)]
+            }
+            "content_block_start" => {
+                let block = &event["content_block"];
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("tool_use") => {
+                        let block_index = event.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+                        let tool_index = self.tool_indices.len();
+                        self.tool_indices.insert(block_index, tool_index);
+                        vec![self.chunk(json!({
+                            "tool_calls": [{
+                                "index": tool_index,
+                                "id": block.get("id").cloned().unwrap_or(Value::Null),
+                                "type": "function",
+                                "function": {
+                                    "name": block.get("name").cloned().unwrap_or(Value::Null),
+                                    "arguments": "",
+                                },
+                            }]
+                        }), None)]
+                    }
+                    Some("text") => match block.get("text").and_then(|v| v.as_str()) {
+                        Some(text) if !text.is_empty() => vec![self.chunk(json!({"content": text}), None)],
+                        _ => Vec::new(),
+                    },
+                    _ => Vec::new(),
+                }
+            }
+            "content_block_delta" => {
+                let delta = &event["delta"];
+                match delta.get("type").and_then(|t| t.as_str()) {
+                    Some("text_delta") => {
+                        let text = delta.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                        vec![self.chunk(json!({"content": text}), None)]
+                    }
+                    Some("input_json_delta") => {
+                        let block_index = event.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+                        let Some(&tool_index) = self.tool_indices.get(&block_index) else {
+                            return Vec::new();
+                        };
+                        let partial = delta.get("partial_json").and_then(|v| v.as_str()).unwrap_or("");
+                        vec![self.chunk(json!({
+                            "tool_calls": [{
+                                "index": tool_index,
+                                "function": {"arguments": partial},
+                            }]
+                        }), None)]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            "message_delta" => {
+                self.usage.read(&event["usage"]);
+                let reason = event["delta"]
+                    .get("stop_reason")
+                    .and_then(|v| v.as_str())
+                    .map(finish_reason);
+                vec![self.chunk(json!({}), reason)]
+            }
+            "message_stop" => {
+                let prompt = self.usage.input_tokens.unwrap_or(0);
+                let completion = self.usage.output_tokens.unwrap_or(0);
+                let usage = json!({
+                    "id": self.id,
+                    "object": "chat.completion.chunk",
+                    "created": self.created,
+                    "model": self.model,
+                    "choices": [],
+                    "usage": {
+                        "prompt_tokens": prompt,
+                        "completion_tokens": completion,
+                        "total_tokens": prompt + completion,
+                    },
+                });
+                vec![usage.to_string(), "[DONE]".to_string()]
+            }
+            "error" => vec![json!({"error": event.get("error").cloned().unwrap_or(Value::Null)}).to_string()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> String {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason,
+            }],
+        })
+        .to_string()
+    }
+}
+
+/// Translates an OpenAI chunk stream into Anthropic Messages stream events.
+pub struct EventTranslator {
+    model: String,
+    started: bool,
+    finished: bool,
+    /// Index and kind ("text" / "tool_use") of the open content block.
+    open_block: Option<(usize, &'static str)>,
+    next_block: usize,
+    /// OpenAI tool call index → Anthropic content block index.
+    tool_blocks: HashMap<u64, usize>,
+    stop_reason: Option<&'static str>,
+    usage: Usage,
+}
+
+impl EventTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            started: false,
+            finished: false,
+            open_block: None,
+            next_block: 0,
+            tool_blocks: HashMap::new(),
+            stop_reason: None,
+            usage: Usage::default(),
+        }
+    }
+
+    /// Translate the payload of one OpenAI `data:` line into Anthropic SSE frames.
+    pub fn translate(&mut self, data: &str) -> Vec<String> {
+        if data == "[DONE]" {
+            return self.finish();
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            return Vec::new();
+        };
+        let mut frames = Vec::new();
+
+        if !self.started {
+            self.started = true;
+            let id = chunk.get("id").and_then(|v| v.as_str()).unwrap_or("msg");
+            if let Some(model) = chunk.get("model").and_then(|v| v.as_str()) {
+                self.model = model.to_string();
+            }
+            frames.push(event_frame("message_start", &json!({
+                "type": "message_start",
+                "message": {
+                    "id": id,
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [],
+                    "model": self.model,
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {"input_tokens": 0, "output_tokens": 0},
+                },
+            })));
+        }
+
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object()) {
+            if let Some(t) = usage.get("prompt_tokens").and_then(|v| v.as_u64()) {
+                self.usage.input_tokens = Some(t);
+            }
+            if let Some(t) = usage.get("completion_tokens").and_then(|v| v.as_u64()) {
+                self.usage.output_tokens = Some(t);
+            }
+        }
+
+        let choice = &chunk["choices"][0];
+        let delta = &choice["delta"];
+        if let Some(text) = delta.get("content").and_then(|c| c.as_str()).filter(|t| !t.is_empty()) {
+            let index = self.ensure_block("text", &json!({"type": "text", "text": ""}), &mut frames);
+            frames.push(event_frame("content_block_delta", &json!({
+                "type": "content_block_delta",
+                "index": index,
+                "delta": {"type": "text_delta", "text": text},
+            })));
+        }
+        for call in delta.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+            let tool_index = call.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+            if call.get("id").is_some_and(|id| !id.is_null()) || !self.tool_blocks.contains_key(&tool_index) {
+                self.close_block(&mut frames);
+                let index = self.ensure_block("tool_use", &json!({
+                    "type": "tool_use",
+                    "id": call.get("id").cloned().unwrap_or(Value::Null),
+                    "name": call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+                    "input": {},
+                }), &mut frames);
+                self.tool_blocks.insert(tool_index, index);
+            }
+            let index = self.tool_blocks[&tool_index];
+            if let Some(args) = call.pointer("/function/arguments").and_then(|a| a.as_str()).filter(|a| !a.is_empty()) {
+                frames.push(event_frame("content_block_delta", &json!({
+                    "type": "content_block_delta",
+                    "index": index,
+                    "delta": {"type": "input_json_delta", "partial_json": args},
+                })));
+            }
+        }
+        if let Some(reason) = choice.get("finish_reason").and_then(|f| f.as_str()) {
+            self.stop_reason = Some(stop_reason(reason));
+        }
+        frames
+    }
+
+    /// Close the message. Safe to call more than once; only the first call emits events.
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.finished || !self.started {
+            return Vec::new();
+        }
+        self.finished = true;
+        let mut frames = Vec::new();
+        self.close_block(&mut frames);
+        frames.push(event_frame("message_delta", &json!({
+            "type": "message_delta",
+            "delta": {"stop_reason": self.stop_reason.unwrap_or("end_turn"), "stop_sequence": null},
+            "usage": {
+                "input_tokens": self.usage.input_tokens.unwrap_or(0),
+                "output_tokens": self.usage.output_tokens.unwrap_or(0),
+            },
+        })));
+        frames.push(event_frame("message_stop", &json!({"type": "message_stop"})));
+        frames
+    }
+
+    /// Return the index of the open block of `kind`, starting a new one if needed.
+    fn ensure_block(&mut self, kind: &'static str, block: &Value, frames: &mut Vec<String>) -> usize {
+        if let Some((index, _)) = self.open_block.filter(|&(_, open_kind)| open_kind == kind && kind == "text") {
+            return index;
+        }
+        self.close_block(frames);
+        let index = self.next_block;
+        self.next_block += 1;
+        self.open_block = Some((index, kind));
+        frames.push(event_frame("content_block_start", &json!({
+            "type": "content_block_start",
+            "index": index,
+            "content_block": block,
+        })));
+        index
+    }
+
+    fn close_block(&mut self, frames: &mut Vec<String>) {
+        if let Some((index, _)) = self.open_block.take() {
+            frames.push(event_frame("content_block_stop", &json!({"type": "content_block_stop", "index": index})));
+        }
+    }
+}
+
+fn event_frame(event: &str, value: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", event, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(data: &str) -> Value {
+        serde_json::from_str(data).unwrap()
+    }
+
+    #[test]
+    fn test_text_stream_translation() {
+        let mut t = ChunkTranslator::new("claude-test");
+        let start = t.translate(r#"{"type":"message_start","message":{"id":"msg_1","model":"claude-test","usage":{"input_tokens":25,"output_tokens":1}}}"#);
+        assert_eq!(payload(&start[0])["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(payload(&start[0])["id"], "msg_1");
+
+        assert!(t.translate(r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#).is_empty());
+        let delta = t.translate(r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#);
+        assert_eq!(payload(&delta[0])["choices"][0]["delta"]["content"], "Hi");
+        assert_eq!(payload(&delta[0])["object"], "chat.completion.chunk");
+
+        let end = t.translate(r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":7}}"#);
+        assert_eq!(payload(&end[0])["choices"][0]["finish_reason"], "stop");
+
+        let stop = t.translate(r#"{"type":"message_stop"}"#);
+        assert_eq!(stop.len(), 2);
+        assert_eq!(payload(&stop[0])["usage"]["prompt_tokens"], 25);
+        assert_eq!(payload(&stop[0])["usage"]["completion_tokens"], 7);
+        assert_eq!(stop[1], "[DONE]");
+    }
+
+    #[test]
+    fn test_tool_use_stream_translation() {
+        let mut t = ChunkTranslator::new("claude-test");
+        let start = t.translate(r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"get_weather","input":{}}}"#);
+        let call = &payload(&start[0])["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(call["index"], 0);
+        assert_eq!(call["id"], "toolu_1");
+        assert_eq!(call["function"]["name"], "get_weather");
+
+        let args = t.translate(r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"city\":"}}"#);
+        assert_eq!(payload(&args[0])["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"], "{\"city\":");
+
+        let end = t.translate(r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":12}}"#);
+        assert_eq!(payload(&end[0])["choices"][0]["finish_reason"], "tool_calls");
+    }
+
+    #[test]
+    fn test_ping_is_dropped() {
+        let mut t = ChunkTranslator::new("claude-test");
+        assert!(t.translate(r#"{"type":"ping"}"#).is_empty());
+    }
+
+    #[test]
+    fn test_request_to_openai() {
+        let body = json!({
+            "model": "claude-test",
+            "max_tokens": 256,
+            "system": [{"type": "text", "text": "Be brief."}],
+            "stop_sequences": ["END"],
+            "tools": [{"name": "lookup", "description": "Find", "input_schema": {"type": "object"}}],
+            "tool_choice": {"type": "any"},
+            "messages": [
+                {"role": "user", "content": "Hi"},
+                {"role": "assistant", "content": [
+                    {"type": "text", "text": "Checking."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "lookup", "input": {"q": "x"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": [{"type": "text", "text": "found"}]},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+                ]}
+            ]
+        });
+        let (messages, extra) = request_to_openai(&body);
+        assert_eq!(messages[0], json!({"role": "system", "content": "Be brief."}));
+        assert_eq!(messages[1]["content"], "Hi");
+        assert_eq!(messages[2]["content"], "Checking.");
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["arguments"], "{\"q\":\"x\"}");
+        assert_eq!(messages[3], json!({"role": "tool", "tool_call_id": "toolu_1", "content": "found"}));
+        assert_eq!(messages[4]["content"][0]["image_url"]["url"], "data:image/png;base64,AAAA");
+        assert_eq!(extra["max_tokens"], 256);
+        assert_eq!(extra["stop"], json!(["END"]));
+        assert_eq!(extra["tool_choice"], "required");
+        assert_eq!(extra["tools"][0]["function"]["parameters"], json!({"type": "object"}));
+    }
+
+    #[test]
+    fn test_response_from_openai() {
+        let resp = json!({
+            "id": "chatcmpl-1",
+            "model": "gpt-test",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "Sure.",
+                    "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "lookup", "arguments": "{\"q\":1}"}}]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 4, "total_tokens": 14}
+        });
+        let msg = response_from_openai(&resp, "gpt-test");
+        assert_eq!(msg["type"], "message");
+        assert_eq!(msg["content"][0], json!({"type": "text", "text": "Sure."}));
+        assert_eq!(msg["content"][1]["input"], json!({"q": 1}));
+        assert_eq!(msg["stop_reason"], "tool_use");
+        assert_eq!(msg["usage"], json!({"input_tokens": 10, "output_tokens": 4}));
+    }
+
+    #[test]
+    fn test_event_translator() {
+        let mut t = EventTranslator::new("gpt-test");
+        let mut frames = Vec::new();
+        frames.extend(t.translate(r#"{"id":"c1","model":"gpt-test","choices":[{"index":0,"delta":{"role":"assistant","content":"Hi"},"finish_reason":null}]}"#));
+        frames.extend(t.translate(r#"{"id":"c1","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"lookup","arguments":""}}]},"finish_reason":null}]}"#));
+        frames.extend(t.translate(r#"{"id":"c1","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{}"}}]},"finish_reason":"tool_calls"}]}"#));
+        frames.extend(t.translate(r#"{"id":"c1","choices":[],"usage":{"prompt_tokens":5,"completion_tokens":3}}"#));
+        frames.extend(t.translate("[DONE]"));
+
+        let events: Vec<&str> = frames
+            .iter()
+            .map(|f| f.lines().next().unwrap().trim_start_matches("event: "))
+            .collect();
+        assert_eq!(events, vec![
+            "message_start",
+            "content_block_start", "content_block_delta",
+            "content_block_stop", "content_block_start", "content_block_delta",
+            "content_block_stop", "message_delta", "message_stop",
+        ]);
+        let delta: Value = serde_json::from_str(frames[7].lines().nth(1).unwrap().trim_start_matches("data: ")).unwrap();
+        assert_eq!(delta["delta"]["stop_reason"], "tool_use");
+        assert_eq!(delta["usage"]["output_tokens"], 3);
+        assert!(t.finish().is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+//! Load balancing across equivalent candidates.
+//!
+//! Providers serving a model at the same tier and cost are interchangeable
+//! (typically several accounts with one vendor), but the router's sort always
+//! puts the same one first. A profile's `load_balancing` strategy reorders
+//! each such group so traffic, and with it per-account rate limits, is spread
+//! across all of them.
+
+use crate::config::Provider;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// How long a provider failure counts for `least_errors`.
+const ERROR_WINDOW: Duration = Duration::from_secs(300);
+
+/// How equivalent candidates are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadBalancing {
+    /// Keep the router's order: higher priority first.
+    #[default]
+    None,
+    /// Random order, each provider picked first in proportion to its `weight`.
+    WeightedRandom,
+    /// Take turns, one request at a time.
+    RoundRobin,
+    /// Fewest requests currently in flight first.
+    LeastInFlight,
+    /// Fewest failures in the last five minutes first.
+    LeastErrors,
+}
+
+#[derive(Default)]
+struct Counters {
+    in_flight: HashMap<String, usize>,
+    errors: HashMap<String, VecDeque<Instant>>,
+}
+
+/// Live per-provider load, shared across requests.
+#[derive(Clone, Default)]
+pub struct LoadTracker {
+    counters: Arc<Mutex<Counters>>,
+    turns: Turns,
+}
+
+impl LoadTracker {
+    fn lock(&self) -> MutexGuard<'_, Counters> {
+        self.counters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count a request as in flight on a provider until the guard is dropped.
+    pub fn start(&self, provider_id: &str) -> InFlight {
+        *self.lock().in_flight.entry(provider_id.to_string()).or_default() += 1;
+        InFlight {
+            tracker: self.clone(),
+            provider_id: provider_id.to_string(),
+        }
+    }
+
+    /// Note a failure caused by the provider.
+    pub fn record_error(&self, provider_id: &str) {
+        let now = Instant::now();
+        let mut counters = self.lock();
+        let errors = counters.errors.entry(provider_id.to_string()).or_default();
+        errors.push_back(now);
+        while errors.front().is_some_and(|at| now.duration_since(*at) > ERROR_WINDOW) {
+            errors.pop_front();
+        }
+    }
+
+    /// The current load, for routing one request.
+    pub fn snapshot(&self) -> Load {
+        let now = Instant::now();
+        let counters = self.lock();
+        Load {
+            in_flight: counters.in_flight.clone(),
+            recent_errors: counters
+                .errors
+                .iter()
+                .map(|(id, errors)| (id.clone(), errors.iter().filter(|at| now.duration_since(**at) <= ERROR_WINDOW).count()))
+                .collect(),
+            turns: self.turns.clone(),
+        }
+    }
+}
+
+/// A request in flight on a provider; see [`LoadTracker::start`].
+pub struct InFlight {
+    tracker: LoadTracker,
+    provider_id: String,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut counters = self.tracker.lock();
+        if let Some(count) = counters.in_flight.get_mut(&self.provider_id) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+/// Round-robin positions, one rotation per model and group of providers, so
+/// traffic to one group doesn't skip turns in another.
+#[derive(Debug, Clone, Default)]
+pub struct Turns(Arc<Mutex<HashMap<String, u64>>>);
+
+impl Turns {
+    /// Take the next turn in the rotation of `group` for `model`.
+    fn next(&self, model: &str, group: &[Provider]) -> u64 {
+        let mut ids: Vec<&str> = group.iter().map(|p| p.id.as_str()).collect();
+        ids.sort_unstable();
+        let key = format!("{}:{}", model, ids.join(","));
+        let mut turns = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let turn = turns.entry(key).or_default();
+        let current = *turn;
+        *turn += 1;
+        current
+    }
+}
+
+/// Provider load as seen by one request.
+#[derive(Debug, Clone, Default)]
+pub struct Load {
+    pub in_flight: HashMap<String, usize>,
+    pub recent_errors: HashMap<String, usize>,
+    /// Shared round-robin rotations.
+    pub turns: Turns,
+}
+
+/// Reorder a group of equivalent candidates for `model` according to `strategy`.
+pub fn balance(group: &mut [Provider], model: &str, strategy: LoadBalancing, load: &Load) {
+    if group.len() < 2 {
+        return;
+    }
+    match strategy {
+        LoadBalancing::None => {}
+        LoadBalancing::WeightedRandom => {
+            // Weighted shuffle: sort by u^(1/w) for a uniform u, largest first
+            let mut keyed: Vec<(f64, Provider)> = group
+                .iter()
+                .map(|p| {
+                    let weight = p.weight.unwrap_or(1) as f64;
+                    let key = if weight > 0.0 { fastrand::f64().powf(1.0 / weight) } else { -1.0 };
+                    (key, p.clone())
+                })
+                .collect();
+            keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+            for (slot, (_, provider)) in group.iter_mut().zip(keyed) {
+                *slot = provider;
+            }
+        }
+        LoadBalancing::RoundRobin => {
+            let turn = load.turns.next(model, group);
+            let len = group.len();
+            group.rotate_left((turn % len as u64) as usize);
+        }
+        LoadBalancing::LeastInFlight => {
+            group.sort_by_key(|p| load.in_flight.get(&p.id).copied().unwrap_or(0));
+        }
+        LoadBalancing::LeastErrors => {
+            group.sort_by_key(|p| load.recent_errors.get(&p.id).copied().unwrap_or(0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn group(ids: &[&str]) -> Vec<Provider> {
+        let template = Config::default().providers[0].clone();
+        ids.iter()
+            .map(|id| Provider { id: id.to_string(), ..template.clone() })
+            .collect()
+    }
+
+    fn ids(group: &[Provider]) -> Vec<&str> {
+        group.iter().map(|p| p.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let tracker = LoadTracker::default();
+        let firsts: Vec<String> = (0..4)
+            .map(|_| {
+                // Traffic to another group or model doesn't advance this rotation
+                balance(&mut group(&["x", "y"]), "m", LoadBalancing::RoundRobin, &tracker.snapshot());
+                balance(&mut group(&["a", "b", "c"]), "other", LoadBalancing::RoundRobin, &tracker.snapshot());
+                let mut providers = group(&["a", "b", "c"]);
+                balance(&mut providers, "m", LoadBalancing::RoundRobin, &tracker.snapshot());
+                providers[0].id.clone()
+            })
+            .collect();
+        assert_eq!(firsts, vec!["a", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_least_in_flight_and_errors() {
+        let tracker = LoadTracker::default();
+        let busy = tracker.start("a");
+        let _also_busy = tracker.start("a");
+        let _one = tracker.start("b");
+        let mut providers = group(&["a", "b", "c"]);
+        balance(&mut providers, "m", LoadBalancing::LeastInFlight, &tracker.snapshot());
+        assert_eq!(ids(&providers), vec!["c", "b", "a"]);
+
+        drop(busy);
+        assert_eq!(tracker.snapshot().in_flight["a"], 1);
+
+        tracker.record_error("c");
+        tracker.record_error("c");
+        tracker.record_error("a");
+        let mut providers = group(&["a", "b", "c"]);
+        balance(&mut providers, "m", LoadBalancing::LeastErrors, &tracker.snapshot());
+        assert_eq!(ids(&providers), vec!["b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_weighted_random() {
+        let load = Load::default();
+        let mut first_counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..2000 {
+            let mut providers = group(&["a", "b", "off"]);
+            providers[0].weight = Some(3);
+            providers[2].weight = Some(0);
+            balance(&mut providers, "m", LoadBalancing::WeightedRandom, &load);
+            assert_eq!(providers[2].id, "off");
+            *first_counts.entry(providers[0].id.clone()).or_default() += 1;
+        }
+        // a should come first about 75% of the time
+        let a = first_counts["a"];
+        assert!((1300..1700).contains(&a), "a first {} times", a);
+    }
+}
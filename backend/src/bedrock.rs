@@ -0,0 +1,574 @@
+//! AWS Bedrock `Converse` / `ConverseStream` adapter.
+//!
+//! Requests are converted from the OpenAI chat shape and signed with SigV4.
+//! `ConverseStream` replies use the binary `application/vnd.amazon.eventstream`
+//! framing, which is decoded here and translated into OpenAI chunks.
+
+use crate::config::Provider;
+use crate::sigv4;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+const SERVICE: &str = "bedrock";
+
+pub fn base_url(provider: &Provider, region: &str) -> String {
+    match provider.endpoint.as_deref() {
+        Some(endpoint) if !endpoint.is_empty() => endpoint.trim_end_matches('/').to_string(),
+        _ => format!("https://bedrock-runtime.{}.amazonaws.com", region),
+    }
+}
+
+/// Converse URL for `model_id`, using the model's configured Bedrock id when set.
+pub fn url(provider: &Provider, region: &str, model_id: &str, streaming: bool) -> String {
+    let bedrock_id = provider
+        .models
+        .iter()
+        .find(|m| m.id == model_id)
+        .and_then(|m| m.deployment.as_deref())
+        .unwrap_or(model_id);
+    let action = if streaming { "converse-stream" } else { "converse" };
+    format!("{}/model/{}/{}", base_url(provider, region), sigv4::uri_encode(bedrock_id), action)
+}
+
+/// SigV4 headers for a Bedrock request. Fails if the provider has no AWS credentials.
+pub fn sign(provider: &Provider, url: &str, body: &[u8]) -> Result<Vec<(&'static str, String)>, String> {
+    let aws = provider.aws.as_ref().ok_or("no AWS credentials configured")?;
+    let url = reqwest::Url::parse(url).map_err(|e| format!("invalid Bedrock URL {}: {}", url, e))?;
+    let credentials = sigv4::Credentials {
+        access_key_id: &aws.access_key_id,
+        secret_access_key: &aws.secret_access_key,
+        session_token: aws.session_token.as_deref(),
+    };
+    Ok(sigv4::sign("POST", &url, body, &credentials, &aws.region, SERVICE, chrono::Utc::now()))
+}
+
+/// Map a Converse `stopReason` to an OpenAI `finish_reason`.
+pub fn finish_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "max_tokens" => "length",
+        "tool_use" => "tool_calls",
+        "guardrail_intervened" | "content_filtered" => "content_filter",
+        _ => "stop",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Requests: OpenAI chat -> Converse
+// ---------------------------------------------------------------------------
+
+fn text_of(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Converse only accepts inline image bytes, so remote image URLs are dropped.
+fn image_block(part: &Value) -> Option<Value> {
+    let url = part.pointer("/image_url/url")?.as_str()?;
+    let (mime_type, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+    let format = mime_type.strip_prefix("image/").unwrap_or("png");
+    Some(json!({"image": {"format": format, "source": {"bytes": data}}}))
+}
+
+fn user_blocks(content: &Value) -> Vec<Value> {
+    match content {
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| match p.get("type").and_then(|t| t.as_str()) {
+                Some("text") => Some(json!({"text": p.get("text").cloned().unwrap_or(Value::Null)})),
+                Some("image_url") => image_block(p),
+                _ => None,
+            })
+            .collect(),
+        Value::Null => Vec::new(),
+        other => vec![json!({"text": text_of(other)})],
+    }
+}
+
+/// Append a message, merging into the previous one when the role repeats
+/// (Converse requires alternating user/assistant turns).
+fn push_message(messages: &mut Vec<Value>, role: &str, content: Vec<Value>) {
+    if content.is_empty() {
+        return;
+    }
+    let same_role = messages.last_mut().filter(|last| last["role"] == role);
+    if let Some(existing) = same_role.and_then(|last| last["content"].as_array_mut()) {
+        existing.extend(content);
+        return;
+    }
+    messages.push(json!({"role": role, "content": content}));
+}
+
+/// Convert OpenAI chat messages and parameters into a Converse request body.
+pub fn request_from_openai(messages: &[Value], extra: &HashMap<String, Value>) -> Value {
+    let mut system = Vec::new();
+    let mut converse: Vec<Value> = Vec::new();
+
+    for msg in messages {
+        let content = msg.get("content").cloned().unwrap_or(Value::Null);
+        match msg.get("role").and_then(|r| r.as_str()).unwrap_or("user") {
+            "system" | "developer" => system.push(json!({"text": text_of(&content)})),
+            "assistant" => {
+                let mut blocks = Vec::new();
+                let text = text_of(&content);
+                if !text.is_empty() {
+                    blocks.push(json!({"text": text}));
+                }
+                for call in msg.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+                    let input = call
+                        .pointer("/function/arguments")
+                        .and_then(|a| a.as_str())
+                        .and_then(|a| serde_json::from_str::<Value>(a).ok())
+                        .unwrap_or_else(|| json!({}));
+                    blocks.push(json!({"toolUse": {
+                        "toolUseId": call.get("id").cloned().unwrap_or(Value::Null),
+                        "name": call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+                        "input": input,
+                    }}));
+                }
+                push_message(&mut converse, "assistant", blocks);
+            }
+            "tool" => {
+                let text = text_of(&content);
+                let result = match serde_json::from_str::<Value>(&text) {
+                    Ok(v @ Value::Object(_)) => json!({"json": v}),
+                    _ => json!({"text": text}),
+                };
+                push_message(&mut converse, "user", vec![json!({"toolResult": {
+                    "toolUseId": msg.get("tool_call_id").cloned().unwrap_or(Value::Null),
+                    "content": [result],
+                }})]);
+            }
+            _ => push_message(&mut converse, "user", user_blocks(&content)),
+        }
+    }
+
+    let mut body = Map::new();
+    body.insert("messages".to_string(), Value::Array(converse));
+    if !system.is_empty() {
+        body.insert("system".to_string(), Value::Array(system));
+    }
+
+    let mut inference = Map::new();
+    for (from, to) in [
+        ("max_tokens", "maxTokens"),
+        ("max_completion_tokens", "maxTokens"),
+        ("temperature", "temperature"),
+        ("top_p", "topP"),
+    ] {
+        if let Some(v) = extra.get(from).filter(|v| !v.is_null()) {
+            inference.insert(to.to_string(), v.clone());
+        }
+    }
+    match extra.get("stop") {
+        Some(Value::String(s)) => {
+            inference.insert("stopSequences".to_string(), json!([s]));
+        }
+        Some(stop @ Value::Array(_)) => {
+            inference.insert("stopSequences".to_string(), stop.clone());
+        }
+        _ => {}
+    }
+    if !inference.is_empty() {
+        body.insert("inferenceConfig".to_string(), Value::Object(inference));
+    }
+
+    if let Some(tools) = extra.get("tools").and_then(|t| t.as_array()) {
+        let specs: Vec<Value> = tools
+            .iter()
+            .filter_map(|t| t.get("function"))
+            .map(|f| {
+                let mut spec = json!({
+                    "name": f.get("name").cloned().unwrap_or(Value::Null),
+                    "inputSchema": {"json": f.get("parameters").cloned().unwrap_or_else(|| json!({"type": "object"}))},
+                });
+                if let Some(desc) = f.get("description").filter(|d| !d.is_null()) {
+                    spec["description"] = desc.clone();
+                }
+                json!({"toolSpec": spec})
+            })
+            .collect();
+        if !specs.is_empty() {
+            let mut tool_config = json!({"tools": specs});
+            // Converse has no "none"; the tools stay declared so tool history remains valid
+            match extra.get("tool_choice") {
+                Some(Value::String(s)) if s == "required" => tool_config["toolChoice"] = json!({"any": {}}),
+                Some(Value::String(s)) if s == "auto" => tool_config["toolChoice"] = json!({"auto": {}}),
+                Some(choice @ Value::Object(_)) => {
+                    if let Some(name) = choice.pointer("/function/name") {
+                        tool_config["toolChoice"] = json!({"tool": {"name": name}});
+                    }
+                }
+                _ => {}
+            }
+            body.insert("toolConfig".to_string(), tool_config);
+        }
+    }
+
+    Value::Object(body)
+}
+
+// ---------------------------------------------------------------------------
+// Responses: Converse -> OpenAI chat
+// ---------------------------------------------------------------------------
+
+/// Token counts from a Converse `usage` object.
+pub fn usage_tokens(usage: &Value) -> (Option<u64>, Option<u64>) {
+    (
+        usage.get("inputTokens").and_then(|v| v.as_u64()),
+        usage.get("outputTokens").and_then(|v| v.as_u64()),
+    )
+}
+
+/// Convert a buffered Converse response into an OpenAI chat completion.
+pub fn response_to_openai(resp: &Value, model: &str) -> Value {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for block in resp.pointer("/output/message/content").and_then(|c| c.as_array()).into_iter().flatten() {
+        if let Some(t) = block.get("text").and_then(|t| t.as_str()) {
+            text.push_str(t);
+        }
+        if let Some(tool_use) = block.get("toolUse") {
+            tool_calls.push(json!({
+                "id": tool_use.get("toolUseId").cloned().unwrap_or(Value::Null),
+                "type": "function",
+                "function": {
+                    "name": tool_use.get("name").cloned().unwrap_or(Value::Null),
+                    "arguments": tool_use.get("input").map(|i| i.to_string()).unwrap_or_else(|| "{}".to_string()),
+                },
+            }));
+        }
+    }
+
+    let mut message = json!({
+        "role": "assistant",
+        "content": if text.is_empty() && !tool_calls.is_empty() { Value::Null } else { Value::String(text) },
+    });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+    let (input, output) = usage_tokens(resp.get("usage").unwrap_or(&Value::Null));
+    let (input, output) = (input.unwrap_or(0), output.unwrap_or(0));
+    json!({
+        "id": format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": message,
+            "finish_reason": finish_reason(resp.get("stopReason").and_then(|s| s.as_str()).unwrap_or("end_turn")),
+        }],
+        "usage": {
+            "prompt_tokens": input,
+            "completion_tokens": output,
+            "total_tokens": input + output,
+        },
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Streaming: ConverseStream -> OpenAI chunks
+// ---------------------------------------------------------------------------
+
+/// Splits an `application/vnd.amazon.eventstream` body into events.
+///
+/// Each frame is `total_len:u32 headers_len:u32 prelude_crc:u32 headers
+/// payload message_crc:u32`. CRCs are not checked; the transport is TLS.
+#[derive(Default)]
+pub struct EventStreamDecoder {
+    pending: Vec<u8>,
+}
+
+/// One decoded event: its `:event-type` (or `:exception-type`) and JSON payload.
+pub struct Event {
+    pub event_type: String,
+    pub is_exception: bool,
+    pub payload: Value,
+}
+
+impl EventStreamDecoder {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Event> {
+        self.pending.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while self.pending.len() >= 12 {
+            let total_len = u32::from_be_bytes(self.pending[0..4].try_into().unwrap()) as usize;
+            let headers_len = u32::from_be_bytes(self.pending[4..8].try_into().unwrap()) as usize;
+            if total_len < 16 + headers_len {
+                // Corrupt framing; nothing after this point can be trusted
+                self.pending.clear();
+                break;
+            }
+            if self.pending.len() < total_len {
+                break;
+            }
+            let frame: Vec<u8> = self.pending.drain(..total_len).collect();
+            let headers = parse_headers(&frame[12..12 + headers_len]);
+            let payload = serde_json::from_slice(&frame[12 + headers_len..total_len - 4]).unwrap_or(Value::Null);
+            let is_exception = headers.get(":message-type").map(|t| t != "event").unwrap_or(false);
+            let event_type = headers
+                .get(":event-type")
+                .or_else(|| headers.get(":exception-type"))
+                .cloned()
+                .unwrap_or_default();
+            events.push(Event { event_type, is_exception, payload });
+        }
+        events
+    }
+}
+
+/// Parse event-stream headers, keeping only string-valued ones.
+fn parse_headers(mut bytes: &[u8]) -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+    while let Some((&name_len, rest)) = bytes.split_first() {
+        let name_len = name_len as usize;
+        if rest.len() < name_len + 1 {
+            break;
+        }
+        let name = String::from_utf8_lossy(&rest[..name_len]).to_string();
+        let value_type = rest[name_len];
+        let rest = &rest[name_len + 1..];
+        // Value sizes by type: 0/1 bool, 2 byte, 3 short, 4 int, 5 long,
+        // 6 bytes and 7 string (u16 length prefix), 8 timestamp, 9 uuid
+        let (value, consumed) = match value_type {
+            0 | 1 => (None, 0),
+            2 => (None, 1),
+            3 => (None, 2),
+            4 => (None, 4),
+            5 | 8 => (None, 8),
+            9 => (None, 16),
+            6 | 7 if rest.len() >= 2 => {
+                let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+                if rest.len() < 2 + len {
+                    break;
+                }
+                let value = (value_type == 7).then(|| String::from_utf8_lossy(&rest[2..2 + len]).to_string());
+                (value, 2 + len)
+            }
+            _ => break,
+        };
+        if rest.len() < consumed {
+            break;
+        }
+        if let Some(value) = value {
+            headers.insert(name, value);
+        }
+        bytes = &rest[consumed..];
+    }
+    headers
+}
+
+/// Translates `ConverseStream` events into OpenAI chunk payloads.
+pub struct ChunkTranslator {
+    id: String,
+    model: String,
+    created: i64,
+    finished: bool,
+    /// contentBlockIndex -> OpenAI tool call index
+    tool_indices: HashMap<u64, usize>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+impl ChunkTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+            model: model.to_string(),
+            created: chrono::Utc::now().timestamp(),
+            finished: false,
+            tool_indices: HashMap::new(),
+            input_tokens: None,
+            output_tokens: None,
+        }
+    }
+
+    /// Translate one stream event into zero or more OpenAI chunk payloads.
+    pub fn translate(&mut self, event: &Event) -> Vec<String> {
+        if event.is_exception {
+            tracing::warn!(
+                "Bedrock stream exception {}: {}",
+                event.event_type,
+                event.payload.get("message").and_then(|m| m.as_str()).unwrap_or_default()
+            );
+            return Vec::new();
+        }
+        let payload = &event.payload;
+        let block = payload.get("contentBlockIndex").and_then(|i| i.as_u64()).unwrap_or(0);
+        match event.event_type.as_str() {
+            "messageStart" => vec![self.chunk(json!({"role": "assistant", "content": ""}), None)],
+            "contentBlockStart" => match payload.pointer("/start/toolUse") {
+                Some(tool_use) => {
+                    let index = self.tool_indices.len();
+                    self.tool_indices.insert(block, index);
+                    vec![self.chunk(json!({"tool_calls": [{
+                        "index": index,
+                        "id": tool_use.get("toolUseId").cloned().unwrap_or(Value::Null),
+                        "type": "function",
+                        "function": {"name": tool_use.get("name").cloned().unwrap_or(Value::Null), "arguments": ""},
+                    }]}), None)]
+                }
+                None => Vec::new(),
+            },
+            "contentBlockDelta" => {
+                let delta = payload.get("delta").unwrap_or(&Value::Null);
+                if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
+                    vec![self.chunk(json!({"content": text}), None)]
+                } else if let Some(input) = delta.pointer("/toolUse/input").and_then(|i| i.as_str()) {
+                    let index = self.tool_indices.get(&block).copied().unwrap_or(0);
+                    vec![self.chunk(json!({"tool_calls": [{"index": index, "function": {"arguments": input}}]}), None)]
+                } else {
+                    Vec::new()
+                }
+            }
+            "messageStop" => {
+                let reason = payload.get("stopReason").and_then(|s| s.as_str()).unwrap_or("end_turn");
+                vec![self.chunk(json!({}), Some(finish_reason(reason)))]
+            }
+            "metadata" => {
+                let (input, output) = usage_tokens(payload.get("usage").unwrap_or(&Value::Null));
+                self.input_tokens = input.or(self.input_tokens);
+                self.output_tokens = output.or(self.output_tokens);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Emit the trailing usage chunk and `[DONE]`.
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.finished = true;
+        let prompt = self.input_tokens.unwrap_or(0);
+        let completion = self.output_tokens.unwrap_or(0);
+        vec![
+            json!({
+                "id": self.id,
+                "object": "chat.completion.chunk",
+                "created": self.created,
+                "model": self.model,
+                "choices": [],
+                "usage": {
+                    "prompt_tokens": prompt,
+                    "completion_tokens": completion,
+                    "total_tokens": prompt + completion,
+                },
+            })
+            .to_string(),
+            "[DONE]".to_string(),
+        ]
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> String {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason,
+            }],
+        })
+        .to_string()
+    }
+}
+
+/// Encode one event-stream frame. Used by tests to stand in for Bedrock.
+pub fn encode_event(event_type: &str, payload: &Value) -> Vec<u8> {
+    let mut headers = Vec::new();
+    for (name, value) in [(":event-type", event_type), (":content-type", "application/json"), (":message-type", "event")] {
+        headers.push(name.len() as u8);
+        headers.extend_from_slice(name.as_bytes());
+        headers.push(7);
+        headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        headers.extend_from_slice(value.as_bytes());
+    }
+    let payload = payload.to_string().into_bytes();
+    let total_len = 12 + headers.len() + payload.len() + 4;
+
+    let mut frame = Vec::with_capacity(total_len);
+    frame.extend_from_slice(&(total_len as u32).to_be_bytes());
+    frame.extend_from_slice(&(headers.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&[0; 4]); // prelude CRC, not checked
+    frame.extend_from_slice(&headers);
+    frame.extend_from_slice(&payload);
+    frame.extend_from_slice(&[0; 4]); // message CRC, not checked
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_from_openai() {
+        let messages = vec![
+            json!({"role": "system", "content": "Be brief."}),
+            json!({"role": "user", "content": "Weather?"}),
+            json!({"role": "assistant", "content": null, "tool_calls": [
+                {"id": "t1", "type": "function", "function": {"name": "weather", "arguments": "{\"city\":\"Oslo\"}"}}
+            ]}),
+            json!({"role": "tool", "tool_call_id": "t1", "content": "{\"temp\":3}"}),
+            json!({"role": "user", "content": "Thanks"}),
+        ];
+        let extra = HashMap::from([
+            ("max_tokens".to_string(), json!(50)),
+            ("tools".to_string(), json!([{"type": "function", "function": {"name": "weather", "parameters": {"type": "object"}}}])),
+            ("tool_choice".to_string(), json!("required")),
+        ]);
+
+        let body = request_from_openai(&messages, &extra);
+        assert_eq!(body["system"], json!([{"text": "Be brief."}]));
+        assert_eq!(body["messages"][1]["content"][0]["toolUse"]["input"], json!({"city": "Oslo"}));
+        // The tool result and the following user text share one user turn
+        assert_eq!(body["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(body["messages"][2]["content"][0]["toolResult"]["content"][0]["json"], json!({"temp": 3}));
+        assert_eq!(body["messages"][2]["content"][1]["text"], "Thanks");
+        assert_eq!(body["inferenceConfig"]["maxTokens"], 50);
+        assert_eq!(body["toolConfig"]["toolChoice"], json!({"any": {}}));
+    }
+
+    #[test]
+    fn test_response_to_openai() {
+        let resp = json!({
+            "output": {"message": {"role": "assistant", "content": [
+                {"text": "Checking."},
+                {"toolUse": {"toolUseId": "t1", "name": "weather", "input": {"city": "Oslo"}}}
+            ]}},
+            "stopReason": "tool_use",
+            "usage": {"inputTokens": 20, "outputTokens": 8, "totalTokens": 28}
+        });
+        let out = response_to_openai(&resp, "claude-test");
+        assert_eq!(out["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(out["choices"][0]["message"]["tool_calls"][0]["id"], "t1");
+        assert_eq!(out["usage"]["prompt_tokens"], 20);
+    }
+
+    #[test]
+    fn test_event_stream_split_across_chunks() {
+        let mut bytes = encode_event("contentBlockDelta", &json!({"contentBlockIndex": 0, "delta": {"text": "Hi"}}));
+        bytes.extend(encode_event("metadata", &json!({"usage": {"inputTokens": 4, "outputTokens": 1}})));
+
+        let mut decoder = EventStreamDecoder::default();
+        let mut translator = ChunkTranslator::new("claude-test");
+        let (head, tail) = bytes.split_at(10);
+        assert!(decoder.push(head).is_empty());
+        let events = decoder.push(tail);
+        assert_eq!(events.len(), 2);
+
+        let out: Vec<String> = events.iter().flat_map(|e| translator.translate(e)).collect();
+        let chunk: Value = serde_json::from_str(&out[0]).unwrap();
+        assert_eq!(chunk["choices"][0]["delta"]["content"], "Hi");
+        assert_eq!(translator.input_tokens, Some(4));
+    }
+}
@@ -0,0 +1,358 @@
+//! Circuit breakers per provider and model.
+//!
+//! A circuit opens after `failure_threshold` consecutive failures, or when the
+//! error rate over the last `window_seconds` reaches `error_rate_threshold`.
+//! Open circuits are tried last by the router and skipped outright until
+//! `cooldown_seconds` have passed; then one request at a time is let through
+//! as a probe (half-open). A successful probe closes the circuit, a failed one
+//! opens it again.
+
+use crate::error::ErrorClass;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BreakerConfig {
+    pub enabled: bool,
+    /// Consecutive failures that open the circuit.
+    pub failure_threshold: u32,
+    /// Error rate (0-1) over the window that opens the circuit.
+    pub error_rate_threshold: f64,
+    /// Length of the error-rate window.
+    pub window_seconds: u64,
+    /// Requests needed in the window before the error rate is considered.
+    pub min_requests: u32,
+    /// How long an open circuit rejects requests before a probe is let through.
+    pub cooldown_seconds: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: 5,
+            error_rate_threshold: 0.5,
+            window_seconds: 60,
+            min_requests: 10,
+            cooldown_seconds: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// What an attempt says about a provider's health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The provider answered, even if it rejected the request.
+    Healthy,
+    /// The provider is down, overloaded or refusing us.
+    Failed,
+    /// The provider was never reached.
+    Unknown,
+}
+
+impl Outcome {
+    pub fn from_class(class: ErrorClass) -> Self {
+        match class {
+            ErrorClass::RateLimited
+            | ErrorClass::Auth
+            | ErrorClass::ServerError
+            | ErrorClass::Timeout
+            | ErrorClass::Connection => Self::Failed,
+            ErrorClass::ContextOverflow | ErrorClass::ContentFiltered | ErrorClass::InvalidRequest => Self::Healthy,
+            ErrorClass::Other => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    /// Outcomes in the error-rate window: (when, failed).
+    window: VecDeque<(Instant, bool)>,
+    opened_at: Option<Instant>,
+    /// When the current half-open probe was let through.
+    probe_started: Option<Instant>,
+}
+
+impl Breaker {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            window: VecDeque::new(),
+            opened_at: None,
+            probe_started: None,
+        }
+    }
+
+    fn prune(&mut self, config: &BreakerConfig, now: Instant) {
+        let window = Duration::from_secs(config.window_seconds);
+        while self.window.front().is_some_and(|(at, _)| now.duration_since(*at) > window) {
+            self.window.pop_front();
+        }
+    }
+
+    fn error_rate(&self) -> f64 {
+        if self.window.is_empty() {
+            return 0.0;
+        }
+        self.window.iter().filter(|(_, failed)| *failed).count() as f64 / self.window.len() as f64
+    }
+
+    fn cooled_down(&self, config: &BreakerConfig, now: Instant) -> bool {
+        self.opened_at
+            .is_none_or(|at| now.duration_since(at) >= Duration::from_secs(config.cooldown_seconds))
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.state = CircuitState::Open;
+        self.opened_at = Some(now);
+        self.probe_started = None;
+    }
+
+    fn close(&mut self) {
+        self.state = CircuitState::Closed;
+        self.consecutive_failures = 0;
+        self.window.clear();
+        self.opened_at = None;
+        self.probe_started = None;
+    }
+}
+
+/// Breaker state for one provider and model, as shown in `/api/stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitStatus {
+    pub provider_id: String,
+    pub model: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub error_rate: f64,
+    pub window_requests: usize,
+    /// Seconds until an open circuit lets a probe through.
+    pub retry_in_seconds: Option<u64>,
+}
+
+type Key = (String, String);
+
+/// All breakers, keyed by provider id and model.
+#[derive(Clone, Default)]
+pub struct CircuitBreakers {
+    breakers: Arc<Mutex<HashMap<Key, Breaker>>>,
+}
+
+impl CircuitBreakers {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Key, Breaker>> {
+        self.breakers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether a request may be sent. Lets a single probe through once an
+    /// open circuit has cooled down.
+    pub fn allow(&self, provider_id: &str, model: &str, config: &BreakerConfig) -> bool {
+        if !config.enabled {
+            return true;
+        }
+        let mut breakers = self.lock();
+        let Some(breaker) = breakers.get_mut(&(provider_id.to_string(), model.to_string())) else {
+            return true;
+        };
+        let now = Instant::now();
+        match breaker.state {
+            CircuitState::Closed => true,
+            CircuitState::Open if breaker.cooled_down(config, now) => {
+                tracing::info!(provider = %provider_id, model = %model, "Circuit half-open, sending probe");
+                breaker.state = CircuitState::HalfOpen;
+                breaker.probe_started = Some(now);
+                true
+            }
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => {
+                // A probe that never reported back (e.g. the client went away)
+                // is given up on after another cooldown
+                let stale = breaker.probe_started.is_none_or(|at| {
+                    now.duration_since(at) >= Duration::from_secs(config.cooldown_seconds)
+                });
+                if stale {
+                    breaker.probe_started = Some(now);
+                }
+                stale
+            }
+        }
+    }
+
+    /// Record the outcome of an attempt.
+    pub fn record(&self, provider_id: &str, model: &str, config: &BreakerConfig, outcome: Outcome) {
+        if !config.enabled {
+            return;
+        }
+        let mut breakers = self.lock();
+        let breaker = breakers
+            .entry((provider_id.to_string(), model.to_string()))
+            .or_insert_with(Breaker::new);
+        let now = Instant::now();
+        match (breaker.state, outcome) {
+            (_, Outcome::Unknown) => breaker.probe_started = None,
+            (CircuitState::HalfOpen, Outcome::Healthy) => {
+                tracing::info!(provider = %provider_id, model = %model, "Probe succeeded, circuit closed");
+                breaker.close();
+            }
+            (CircuitState::HalfOpen, Outcome::Failed) => {
+                tracing::warn!(provider = %provider_id, model = %model, "Probe failed, circuit reopened");
+                breaker.open(now);
+            }
+            // Stragglers that were sent before the circuit opened
+            (CircuitState::Open, _) => {}
+            (CircuitState::Closed, outcome) => {
+                let failed = outcome == Outcome::Failed;
+                breaker.consecutive_failures = if failed { breaker.consecutive_failures + 1 } else { 0 };
+                breaker.window.push_back((now, failed));
+                breaker.prune(config, now);
+                let rate_tripped = breaker.window.len() >= config.min_requests as usize
+                    && breaker.error_rate() >= config.error_rate_threshold;
+                if failed && (breaker.consecutive_failures >= config.failure_threshold || rate_tripped) {
+                    tracing::warn!(
+                        provider = %provider_id,
+                        model = %model,
+                        consecutive_failures = breaker.consecutive_failures,
+                        error_rate = format!("{:.2}", breaker.error_rate()),
+                        "Circuit opened"
+                    );
+                    breaker.open(now);
+                }
+            }
+        }
+    }
+
+    /// Provider/model pairs whose circuit is not closed.
+    pub fn open_circuits(&self) -> OpenCircuits {
+        OpenCircuits(
+            self.lock()
+                .iter()
+                .filter(|(_, b)| b.state != CircuitState::Closed)
+                .map(|(key, _)| key.clone())
+                .collect(),
+        )
+    }
+
+    /// Current state of every breaker that has seen traffic.
+    pub fn status(&self, config: &BreakerConfig) -> Vec<CircuitStatus> {
+        let now = Instant::now();
+        let mut breakers = self.lock();
+        let mut status: Vec<CircuitStatus> = breakers
+            .iter_mut()
+            .map(|((provider_id, model), breaker)| {
+                breaker.prune(config, now);
+                let retry_in_seconds = match (breaker.state, breaker.opened_at) {
+                    (CircuitState::Open, Some(at)) => Some(
+                        Duration::from_secs(config.cooldown_seconds)
+                            .saturating_sub(now.duration_since(at))
+                            .as_secs(),
+                    ),
+                    _ => None,
+                };
+                CircuitStatus {
+                    provider_id: provider_id.clone(),
+                    model: model.clone(),
+                    state: breaker.state,
+                    consecutive_failures: breaker.consecutive_failures,
+                    error_rate: (breaker.error_rate() * 100.0).round() / 100.0,
+                    window_requests: breaker.window.len(),
+                    retry_in_seconds,
+                }
+            })
+            .collect();
+        status.sort_by(|a, b| (&a.provider_id, &a.model).cmp(&(&b.provider_id, &b.model)));
+        status
+    }
+}
+
+/// Snapshot of the circuits the router should try last.
+#[derive(Debug, Clone, Default)]
+pub struct OpenCircuits(HashSet<Key>);
+
+impl OpenCircuits {
+    pub fn contains(&self, provider_id: &str, model: &str) -> bool {
+        self.0.contains(&(provider_id.to_string(), model.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BreakerConfig {
+        BreakerConfig { failure_threshold: 3, cooldown_seconds: 0, ..BreakerConfig::default() }
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breakers = CircuitBreakers::default();
+        let config = BreakerConfig { cooldown_seconds: 60, ..config() };
+        for _ in 0..2 {
+            breakers.record("p", "m", &config, Outcome::Failed);
+        }
+        assert!(breakers.allow("p", "m", &config));
+        // A healthy answer resets the streak
+        breakers.record("p", "m", &config, Outcome::Healthy);
+        for _ in 0..3 {
+            breakers.record("p", "m", &config, Outcome::Failed);
+        }
+        assert!(!breakers.allow("p", "m", &config));
+        assert!(breakers.open_circuits().contains("p", "m"));
+        assert!(!breakers.open_circuits().contains("p", "other-model"));
+        assert_eq!(breakers.status(&config)[0].state, CircuitState::Open);
+    }
+
+    #[test]
+    fn test_opens_on_error_rate() {
+        let breakers = CircuitBreakers::default();
+        let config = BreakerConfig { failure_threshold: 100, min_requests: 4, cooldown_seconds: 60, ..config() };
+        for outcome in [Outcome::Failed, Outcome::Healthy, Outcome::Failed] {
+            breakers.record("p", "m", &config, outcome);
+        }
+        assert!(breakers.allow("p", "m", &config));
+        breakers.record("p", "m", &config, Outcome::Failed);
+        assert!(!breakers.allow("p", "m", &config));
+    }
+
+    #[test]
+    fn test_half_open_probe() {
+        let breakers = CircuitBreakers::default();
+        let config = BreakerConfig { failure_threshold: 1, ..config() };
+        breakers.record("p", "m", &config, Outcome::Failed);
+
+        // Cooldown of zero: the next request is the probe, and only one at a time
+        assert!(breakers.allow("p", "m", &config));
+        assert_eq!(breakers.status(&config)[0].state, CircuitState::HalfOpen);
+        let slow = BreakerConfig { cooldown_seconds: 60, ..config.clone() };
+        assert!(!breakers.allow("p", "m", &slow));
+
+        breakers.record("p", "m", &config, Outcome::Failed);
+        assert_eq!(breakers.status(&config)[0].state, CircuitState::Open);
+
+        assert!(breakers.allow("p", "m", &config));
+        breakers.record("p", "m", &config, Outcome::Healthy);
+        assert_eq!(breakers.status(&config)[0].state, CircuitState::Closed);
+        assert!(breakers.open_circuits().0.is_empty());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let breakers = CircuitBreakers::default();
+        let config = BreakerConfig { enabled: false, failure_threshold: 1, ..config() };
+        breakers.record("p", "m", &config, Outcome::Failed);
+        assert!(breakers.allow("p", "m", &config));
+        assert!(breakers.status(&config).is_empty());
+    }
+}
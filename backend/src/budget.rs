@@ -0,0 +1,457 @@
+//! Spend budgets per period, at global, provider, profile and virtual-key
+//! scope.
+//!
+//! Spend is the `estimated_cost` of completed requests, summed per scope and
+//! UTC day, and in total, in a ledger that is saved next to the config file,
+//! so budgets survive restarts. A period's spend is the sum of its days: today, the
+//! ISO week so far, or the calendar month so far. Once a budget is used up
+//! its action applies: requests are rejected, routed to free and cheap
+//! providers only, or just logged.
+
+use crate::config::{Provider, Tier};
+use crate::error;
+use crate::state::RequestLog;
+use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use axum::response::Response;
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Days of history kept in the ledger; enough for any month so far.
+const RETAINED_DAYS: u64 = 40;
+/// Share of a budget at which a warning is logged, unless configured.
+const DEFAULT_WARN_AT: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetScope {
+    /// All requests.
+    Global,
+    /// Requests served by the provider with this `id`.
+    Provider,
+    /// Requests routed through the profile with this `id` (its name).
+    Profile,
+    /// Requests made with the virtual key with this `id`.
+    VirtualKey,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    Daily,
+    /// Monday to Sunday.
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    /// First day of the period containing `today`.
+    fn start(self, today: NaiveDate) -> NaiveDate {
+        match self {
+            BudgetPeriod::Daily => today,
+            BudgetPeriod::Weekly => today - Days::new(today.weekday().num_days_from_monday() as u64),
+            BudgetPeriod::Monthly => today.with_day(1).unwrap_or(today),
+        }
+    }
+}
+
+/// What happens once a budget is used up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetAction {
+    /// Refuse requests; for a provider budget, stop routing to the provider.
+    #[default]
+    Reject,
+    /// Route only to `Free` and `Cheap` tier providers.
+    Downgrade,
+    /// Keep serving and log a warning.
+    Warn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Budget {
+    pub scope: BudgetScope,
+    /// Provider id, profile name or virtual key id; unused for `global`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub period: BudgetPeriod,
+    /// Spend allowed per period, in dollars.
+    pub limit: f64,
+    #[serde(default)]
+    pub action: BudgetAction,
+    /// Share of the limit at which a warning is logged (default 0.8).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_at: Option<f64>,
+}
+
+impl Budget {
+    /// Ledger key of the budget's scope.
+    fn key(&self) -> String {
+        scope_key(self.scope, self.id.as_deref().unwrap_or_default())
+    }
+
+    fn describe(&self) -> String {
+        let period = match self.period {
+            BudgetPeriod::Daily => "daily",
+            BudgetPeriod::Weekly => "weekly",
+            BudgetPeriod::Monthly => "monthly",
+        };
+        let scope = match self.scope {
+            BudgetScope::Global => return format!("{} global budget", period),
+            BudgetScope::Provider => "provider",
+            BudgetScope::Profile => "profile",
+            BudgetScope::VirtualKey => "virtual key",
+        };
+        format!("{} {} budget for '{}'", period, scope, self.id.as_deref().unwrap_or_default())
+    }
+}
+
+fn scope_key(scope: BudgetScope, id: &str) -> String {
+    match scope {
+        BudgetScope::Global => "global".to_string(),
+        BudgetScope::Provider => format!("provider:{}", id),
+        BudgetScope::Profile => format!("profile:{}", id),
+        BudgetScope::VirtualKey => format!("virtual_key:{}", id),
+    }
+}
+
+/// Ledger keys a completed request counts against.
+pub fn scopes(log: &RequestLog) -> Vec<String> {
+    let mut scopes = vec![scope_key(BudgetScope::Global, "")];
+    if let Some(ref id) = log.provider_id {
+        scopes.push(scope_key(BudgetScope::Provider, id));
+    }
+    if let Some(ref profile) = log.profile {
+        scopes.push(scope_key(BudgetScope::Profile, profile));
+    }
+    if let Some(ref key) = log.virtual_key {
+        scopes.push(scope_key(BudgetScope::VirtualKey, key));
+    }
+    scopes
+}
+
+/// Spend per scope key.
+#[derive(Default, Serialize, Deserialize)]
+struct Ledger {
+    /// By UTC day, for the last [`RETAINED_DAYS`].
+    days: HashMap<String, BTreeMap<NaiveDate, f64>>,
+    /// All time, for caps that never reset.
+    #[serde(default)]
+    totals: HashMap<String, f64>,
+}
+
+fn period_spend(ledger: &Ledger, key: &str, period: BudgetPeriod, today: NaiveDate) -> f64 {
+    ledger
+        .days
+        .get(key)
+        .map(|days| days.range(period.start(today)..=today).map(|(_, spend)| spend).sum())
+        .unwrap_or(0.0)
+}
+
+/// Spend of every scope, optionally saved to a file after each update.
+#[derive(Clone, Default)]
+pub struct SpendLedger {
+    ledger: Arc<Mutex<Ledger>>,
+    path: Option<PathBuf>,
+    /// Held while saving, so saves land in order.
+    saving: Arc<Mutex<()>>,
+    /// Set while a save is waiting to run; updates meanwhile join it.
+    save_queued: Arc<AtomicBool>,
+}
+
+impl SpendLedger {
+    /// A ledger saved at `path`, starting from its contents if it exists.
+    /// Fails if the file exists but cannot be read, rather than starting
+    /// over and lifting every budget.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let ledger = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("spend ledger {} is corrupt; fix or remove it", path.display()))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Ledger::default(),
+            Err(e) => return Err(e).with_context(|| format!("cannot read spend ledger {}", path.display())),
+        };
+        Ok(Self {
+            ledger: Arc::new(Mutex::new(ledger)),
+            path: Some(path),
+            ..Self::default()
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Ledger> {
+        self.ledger.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add a request's cost to its scopes, warning about budgets it takes
+    /// past their warning share or their limit.
+    pub fn record(&self, budgets: &[Budget], scopes: &[String], cost: f64) {
+        self.record_on(budgets, scopes, cost, Utc::now().date_naive());
+    }
+
+    fn record_on(&self, budgets: &[Budget], scopes: &[String], cost: f64, today: NaiveDate) {
+        let mut ledger = self.lock();
+        let affected: Vec<&Budget> = budgets.iter().filter(|b| scopes.contains(&b.key())).collect();
+        let before: Vec<f64> = affected.iter().map(|b| period_spend(&ledger, &b.key(), b.period, today)).collect();
+
+        let oldest = today - Days::new(RETAINED_DAYS);
+        for scope in scopes {
+            let days = ledger.days.entry(scope.clone()).or_default();
+            *days.entry(today).or_default() += cost;
+            days.retain(|day, _| *day >= oldest);
+            *ledger.totals.entry(scope.clone()).or_default() += cost;
+        }
+
+        for (budget, before) in affected.iter().zip(before) {
+            let after = period_spend(&ledger, &budget.key(), budget.period, today);
+            let warn_at = budget.limit * budget.warn_at.unwrap_or(DEFAULT_WARN_AT);
+            if before < budget.limit && after >= budget.limit {
+                tracing::warn!(
+                    budget = %budget.describe(),
+                    spend = after,
+                    limit = budget.limit,
+                    action = ?budget.action,
+                    "Budget exhausted"
+                );
+            } else if before < warn_at && after >= warn_at && after < budget.limit {
+                tracing::warn!(budget = %budget.describe(), spend = after, limit = budget.limit, "Budget nearly used up");
+            }
+        }
+
+        drop(ledger);
+        self.queue_save();
+    }
+
+    /// Save on a blocking thread, off the async runtime.
+    fn queue_save(&self) {
+        if self.path.is_none() || self.save_queued.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let ledger = self.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(move || ledger.save())),
+            Err(_) => ledger.save(),
+        }
+    }
+
+    /// Write to a temporary file and rename it into place, so a crash never
+    /// leaves a truncated ledger behind.
+    fn save(&self) {
+        let Some(ref path) = self.path else { return };
+        let _saving = self.saving.lock().unwrap_or_else(|e| e.into_inner());
+        self.save_queued.store(false, Ordering::Release);
+        let content = serde_json::to_string(&*self.lock()).unwrap_or_default();
+        let tmp = path.with_extension("json.tmp");
+        if let Err(e) = fs::write(&tmp, content).and_then(|()| fs::rename(&tmp, path)) {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to save spend ledger");
+        }
+    }
+
+    /// All-time spend of one scope.
+    pub fn total(&self, scope: BudgetScope, id: &str) -> f64 {
+        self.lock().totals.get(&scope_key(scope, id)).copied().unwrap_or(0.0)
+    }
+
+    /// Current spend of every budget.
+    pub fn status(&self, budgets: &[Budget]) -> Vec<BudgetStatus> {
+        self.status_on(budgets, Utc::now().date_naive())
+    }
+
+    fn status_on(&self, budgets: &[Budget], today: NaiveDate) -> Vec<BudgetStatus> {
+        let ledger = self.lock();
+        budgets
+            .iter()
+            .map(|budget| {
+                let spend = period_spend(&ledger, &budget.key(), budget.period, today);
+                BudgetStatus {
+                    budget: budget.clone(),
+                    spend,
+                    remaining: (budget.limit - spend).max(0.0),
+                    exceeded: spend >= budget.limit,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A budget's spend in the current period, as shown in `/api/stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    #[serde(flatten)]
+    pub budget: Budget,
+    pub spend: f64,
+    pub remaining: f64,
+    pub exceeded: bool,
+}
+
+/// What the budgets allow for one request.
+#[derive(Debug, Default)]
+pub struct Verdict {
+    /// A used-up budget that rejects the request outright.
+    pub rejected: Option<BudgetStatus>,
+    /// Only free and cheap providers may serve the request.
+    pub downgrade: bool,
+    /// Providers whose own budget is used up.
+    pub blocked_providers: HashSet<String>,
+}
+
+impl Verdict {
+    /// Work out the verdict for a request through `profile` with `virtual_key`.
+    pub fn check(budgets: &[Budget], ledger: &SpendLedger, profile: Option<&str>, virtual_key: Option<&str>) -> Self {
+        let mut verdict = Verdict::default();
+        for status in ledger.status(budgets).into_iter().filter(|s| s.exceeded) {
+            let budget = &status.budget;
+            let id = budget.id.as_deref();
+            let applies = match budget.scope {
+                BudgetScope::Global | BudgetScope::Provider => true,
+                BudgetScope::Profile => profile.is_some() && id == profile,
+                BudgetScope::VirtualKey => virtual_key.is_some() && id == virtual_key,
+            };
+            if !applies {
+                continue;
+            }
+            match (budget.scope, budget.action) {
+                (_, BudgetAction::Warn) => {
+                    tracing::debug!(budget = %budget.describe(), spend = status.spend, "Serving over budget");
+                }
+                (BudgetScope::Provider, action) => {
+                    if let Some(id) = id {
+                        tracing::info!(budget = %budget.describe(), ?action, "Provider over budget");
+                        verdict.blocked_providers.insert(id.to_string());
+                    }
+                }
+                (_, BudgetAction::Downgrade) => {
+                    tracing::info!(budget = %budget.describe(), "Over budget, routing to free and cheap providers only");
+                    verdict.downgrade = true;
+                }
+                (_, BudgetAction::Reject) => {
+                    tracing::warn!(budget = %budget.describe(), spend = status.spend, "Over budget, rejecting request");
+                    verdict.rejected.get_or_insert(status);
+                }
+            }
+        }
+        verdict
+    }
+
+    /// Whether a provider may serve the request.
+    pub fn allows(&self, provider: &Provider) -> bool {
+        !self.blocked_providers.contains(&provider.id)
+            && (!self.downgrade || matches!(provider.tier, Tier::Free | Tier::Cheap))
+    }
+}
+
+/// 429 for a request refused by a used-up budget.
+pub fn exceeded(status: &BudgetStatus) -> Response {
+    error::json_error(
+        StatusCode::TOO_MANY_REQUESTS,
+        "insufficient_quota",
+        "budget_exceeded",
+        &format!(
+            "The {} of ${:.2} is used up (spent ${:.4})",
+            status.budget.describe(),
+            status.budget.limit,
+            status.spend
+        ),
+        &[],
+    )
+}
+
+/// 429 once budgets rule out every provider for a model.
+pub fn no_provider(model: &str) -> Response {
+    error::json_error(
+        StatusCode::TOO_MANY_REQUESTS,
+        "insufficient_quota",
+        "budget_exceeded",
+        &format!("No provider within budget for model '{}'", model),
+        &[],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn budget(scope: BudgetScope, id: Option<&str>, period: BudgetPeriod, limit: f64, action: BudgetAction) -> Budget {
+        Budget { scope, id: id.map(str::to_string), period, limit, action, warn_at: None }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_period_windows() {
+        let ledger = SpendLedger::default();
+        let global = vec!["global".to_string()];
+        // 2026-10-12 is a Monday
+        ledger.record_on(&[], &global, 1.0, date(2026, 9, 30));
+        ledger.record_on(&[], &global, 2.0, date(2026, 10, 5));
+        ledger.record_on(&[], &global, 4.0, date(2026, 10, 12));
+        ledger.record_on(&[], &global, 8.0, date(2026, 10, 14));
+
+        let budgets = [BudgetPeriod::Daily, BudgetPeriod::Weekly, BudgetPeriod::Monthly]
+            .map(|period| budget(BudgetScope::Global, None, period, 10.0, BudgetAction::Reject));
+        let spend: Vec<f64> = ledger.status_on(&budgets, date(2026, 10, 14)).iter().map(|s| s.spend).collect();
+        assert_eq!(spend, vec![8.0, 12.0, 14.0]);
+        let status = ledger.status_on(&budgets, date(2026, 10, 14));
+        assert!(!status[0].exceeded && status[1].exceeded && status[2].exceeded);
+        assert_eq!(status[0].remaining, 2.0);
+    }
+
+    #[test]
+    fn test_ledger_persists() {
+        let path = std::env::temp_dir().join(format!("spend-{}.json", uuid::Uuid::new_v4()));
+        let ledger = SpendLedger::load(path.clone()).unwrap();
+        let mut log = RequestLog::new("m");
+        log.profile = Some("auto".to_string());
+        log.virtual_key = Some("alice".to_string());
+        log.provider_id = Some("openai".to_string());
+        ledger.record(&[], &scopes(&log), 0.5);
+
+        let budgets = vec![
+            budget(BudgetScope::Provider, Some("openai"), BudgetPeriod::Daily, 1.0, BudgetAction::Reject),
+            budget(BudgetScope::VirtualKey, Some("alice"), BudgetPeriod::Monthly, 1.0, BudgetAction::Reject),
+        ];
+        let reloaded = SpendLedger::load(path.clone()).unwrap();
+        assert!(reloaded.status(&budgets).iter().all(|s| s.spend == 0.5));
+        assert_eq!(reloaded.total(BudgetScope::VirtualKey, "alice"), 0.5);
+
+        // A damaged ledger stops startup instead of resetting every budget
+        fs::write(&path, "{\"days\": {\"glob").unwrap();
+        assert!(SpendLedger::load(path.clone()).is_err());
+        fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_verdict() {
+        let ledger = SpendLedger::default();
+        let budgets = vec![
+            budget(BudgetScope::Profile, Some("premium"), BudgetPeriod::Daily, 1.0, BudgetAction::Downgrade),
+            budget(BudgetScope::Provider, Some("pricey"), BudgetPeriod::Daily, 1.0, BudgetAction::Reject),
+            budget(BudgetScope::VirtualKey, Some("bob"), BudgetPeriod::Daily, 1.0, BudgetAction::Reject),
+            budget(BudgetScope::Global, None, BudgetPeriod::Daily, 1.0, BudgetAction::Warn),
+        ];
+        let scopes = ["global", "profile:premium", "provider:pricey", "virtual_key:bob"].map(str::to_string);
+        ledger.record(&budgets, &scopes, 2.0);
+
+        let verdict = Verdict::check(&budgets, &ledger, Some("premium"), Some("alice"));
+        assert!(verdict.rejected.is_none());
+        assert!(verdict.downgrade);
+        let mut provider = Config::default().providers[0].clone();
+        provider.tier = Tier::Subscription;
+        assert!(!verdict.allows(&provider));
+        provider.tier = Tier::Cheap;
+        assert!(verdict.allows(&provider));
+        provider.id = "pricey".to_string();
+        assert!(!verdict.allows(&provider));
+
+        let verdict = Verdict::check(&budgets, &ledger, Some("auto"), Some("bob"));
+        assert!(!verdict.downgrade);
+        assert_eq!(verdict.rejected.map(|s| s.budget.scope), Some(BudgetScope::VirtualKey));
+    }
+}
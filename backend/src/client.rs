@@ -0,0 +1,121 @@
+//! Shared HTTP clients for provider requests.
+//!
+//! A `reqwest::Client` owns a connection pool, so reusing one across requests
+//! keeps connections, HTTP/2 sessions and TLS sessions alive between turns.
+//! Providers with the same connection settings share a client.
+
+use crate::config::Provider;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long an idle pooled connection is kept open.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// The provider settings that need a client of their own.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientKey {
+    pub connect_ms: u64,
+    pub proxy: Option<String>,
+    pub ca_cert: Option<String>,
+}
+
+impl ClientKey {
+    pub fn for_provider(provider: &Provider) -> Self {
+        Self {
+            connect_ms: provider.timeouts.clone().unwrap_or_default().connect_ms,
+            proxy: provider.proxy.clone(),
+            ca_cert: provider.ca_cert.clone(),
+        }
+    }
+
+    fn build(&self) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_millis(self.connect_ms))
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(TCP_KEEPALIVE);
+        if let Some(ref proxy) = self.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| format!("invalid proxy '{}': {}", proxy, e))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(ref path) = self.ca_cert {
+            let pem = std::fs::read(path).map_err(|e| format!("cannot read CA certificate '{}': {}", path, e))?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("invalid CA certificate '{}': {}", path, e))?;
+            builder = builder.tls_certs_merge(certs);
+        }
+        builder.build().map_err(|e| format!("cannot build HTTP client: {}", e))
+    }
+}
+
+/// Clients keyed by connection settings, built on first use.
+#[derive(Clone, Default)]
+pub struct ClientPool {
+    clients: Arc<Mutex<HashMap<ClientKey, reqwest::Client>>>,
+}
+
+impl ClientPool {
+    /// The client for a provider's settings. Fails if the provider's proxy or
+    /// CA certificate is unusable.
+    pub fn get(&self, provider: &Provider) -> Result<reqwest::Client, String> {
+        let key = ClientKey::for_provider(provider);
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+        let client = key.build()?;
+        tracing::debug!(provider = %provider.name, proxy = ?key.proxy, "Created HTTP client");
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// Drop all clients, e.g. after the provider settings changed.
+    pub fn clear(&self) {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_clients_shared_by_settings() {
+        let config = Config::default();
+        let mut providers = config.providers.clone();
+        let pool = ClientPool::default();
+
+        pool.get(&providers[0]).unwrap();
+        pool.get(&providers[1]).unwrap();
+        assert_eq!(pool.len(), 1);
+
+        providers[1].proxy = Some("http://127.0.0.1:3128".to_string());
+        pool.get(&providers[1]).unwrap();
+        assert_eq!(pool.len(), 2);
+
+        pool.clear();
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_bad_settings_rejected() {
+        let mut provider = Config::default().providers[0].clone();
+        provider.ca_cert = Some("/nonexistent/ca.pem".to_string());
+        let err = ClientPool::default().get(&provider).unwrap_err();
+        assert!(err.contains("cannot read CA certificate"));
+
+        provider.ca_cert = None;
+        provider.proxy = Some("not a url".to_string());
+        assert!(ClientPool::default().get(&provider).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Legacy text completions (`/v1/completions`) on top of chat completions.
+//!
+//! The prompt becomes a single user message; chat responses and chunks are
+//! turned back into `text_completion` objects.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// What the response conversion needs to know about the original request.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub prompt: String,
+    /// Prepend the prompt to the completion text (`echo`).
+    pub echo: bool,
+    /// Forward the usage-only chunk at the end of a stream, as requested via
+    /// `stream_options.include_usage`.
+    pub include_usage: bool,
+}
+
+/// Extract the prompt text. Only a single prompt is supported; a one-element
+/// array is treated like a plain string.
+pub fn prompt_text(prompt: &Value) -> Result<String, &'static str> {
+    match prompt {
+        Value::String(text) => Ok(text.clone()),
+        Value::Array(items) if items.len() == 1 => match &items[0] {
+            Value::String(text) => Ok(text.clone()),
+            _ => Err("Token prompts are not supported"),
+        },
+        Value::Array(items) if items.is_empty() => Ok(String::new()),
+        Value::Array(_) => Err("Only a single prompt is supported"),
+        Value::Null => Ok(String::new()),
+        _ => Err("prompt must be a string"),
+    }
+}
+
+/// Chat parameters for a completions request. `logprobs: <n>` becomes the chat
+/// `logprobs` / `top_logprobs` pair; `echo`, `suffix` and `best_of` have no
+/// chat equivalent and are handled here or dropped.
+pub fn request_params(extra: &HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut params: HashMap<String, Value> = extra
+        .iter()
+        .filter(|(k, v)| !v.is_null() && !matches!(k.as_str(), "echo" | "suffix" | "best_of" | "logprobs"))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    if let Some(n) = extra.get("logprobs").and_then(|v| v.as_u64()) {
+        params.insert("logprobs".to_string(), Value::Bool(true));
+        if n > 0 {
+            params.insert("top_logprobs".to_string(), json!(n.min(20)));
+        }
+    }
+    params
+}
+
+/// Convert chat `logprobs.content` into the legacy `{tokens, token_logprobs,
+/// top_logprobs, text_offset}` shape. Offsets start after `offset` bytes.
+fn logprobs_from_chat(logprobs: &Value, offset: usize) -> Value {
+    let Some(content) = logprobs.get("content").and_then(|c| c.as_array()) else {
+        return Value::Null;
+    };
+    let mut tokens = Vec::new();
+    let mut token_logprobs = Vec::new();
+    let mut top_logprobs = Vec::new();
+    let mut text_offset = Vec::new();
+    let mut position = offset;
+    for entry in content {
+        let token = entry.get("token").and_then(|t| t.as_str()).unwrap_or_default();
+        tokens.push(json!(token));
+        token_logprobs.push(entry.get("logprob").cloned().unwrap_or(Value::Null));
+        let top: serde_json::Map<String, Value> = entry
+            .get("top_logprobs")
+            .and_then(|t| t.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|t| Some((t.get("token")?.as_str()?.to_string(), t.get("logprob")?.clone())))
+            .collect();
+        top_logprobs.push(Value::Object(top));
+        text_offset.push(json!(position));
+        position += token.len();
+    }
+    json!({
+        "tokens": tokens,
+        "token_logprobs": token_logprobs,
+        "top_logprobs": top_logprobs,
+        "text_offset": text_offset,
+    })
+}
+
+fn completion_id() -> String {
+    format!("cmpl-{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Convert an OpenAI chat completion into a `text_completion` object. Every
+/// chat choice (`n`) becomes one completion choice.
+pub fn response_from_openai(resp: &Value, model: &str, options: &Options) -> Value {
+    let prefix = if options.echo { options.prompt.as_str() } else { "" };
+    let choices: Vec<Value> = resp
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, choice)| {
+            let content = choice.pointer("/message/content").and_then(|c| c.as_str()).unwrap_or_default();
+            json!({
+                "text": format!("{}{}", prefix, content),
+                "index": choice.get("index").cloned().unwrap_or_else(|| json!(i)),
+                "logprobs": logprobs_from_chat(choice.get("logprobs").unwrap_or(&Value::Null), prefix.len()),
+                "finish_reason": choice.get("finish_reason").cloned().unwrap_or(Value::Null),
+            })
+        })
+        .collect();
+    let mut out = json!({
+        "id": completion_id(),
+        "object": "text_completion",
+        "created": resp.get("created").cloned().unwrap_or_else(|| json!(chrono::Utc::now().timestamp())),
+        "model": resp.get("model").and_then(|m| m.as_str()).unwrap_or(model),
+        "choices": choices,
+    });
+    if let Some(usage) = resp.get("usage").filter(|u| u.is_object()) {
+        out["usage"] = usage.clone();
+    }
+    out
+}
+
+/// Translates an OpenAI chunk stream into `text_completion` chunks.
+pub struct ChunkTranslator {
+    id: String,
+    model: String,
+    created: i64,
+    options: Options,
+    /// Choice indices whose echoed prompt has been sent.
+    echoed: Vec<u64>,
+}
+
+impl ChunkTranslator {
+    pub fn new(model: &str, options: Options) -> Self {
+        Self {
+            id: completion_id(),
+            model: model.to_string(),
+            created: chrono::Utc::now().timestamp(),
+            options,
+            echoed: Vec::new(),
+        }
+    }
+
+    /// Translate the payload of one OpenAI `data:` line into completion SSE frames.
+    pub fn translate(&mut self, data: &str) -> Vec<String> {
+        if data == "[DONE]" {
+            return vec!["data: [DONE]\n\n".to_string()];
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            return Vec::new();
+        };
+        if let Some(model) = chunk.get("model").and_then(|m| m.as_str()) {
+            self.model = model.to_string();
+        }
+
+        let mut frames = Vec::new();
+        for choice in chunk.get("choices").and_then(|c| c.as_array()).into_iter().flatten() {
+            let index = choice.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+            let mut text = String::new();
+            if self.options.echo && !self.echoed.contains(&index) {
+                self.echoed.push(index);
+                text.push_str(&self.options.prompt);
+            }
+            text.push_str(choice.pointer("/delta/content").and_then(|c| c.as_str()).unwrap_or_default());
+            let finish_reason = choice.get("finish_reason").cloned().unwrap_or(Value::Null);
+            if text.is_empty() && finish_reason.is_null() {
+                continue;
+            }
+            frames.push(self.frame(json!([{
+                "text": text,
+                "index": index,
+                "logprobs": logprobs_from_chat(choice.get("logprobs").unwrap_or(&Value::Null), 0),
+                "finish_reason": finish_reason,
+            }]), None));
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object() && self.options.include_usage) {
+            frames.push(self.frame(json!([]), Some(usage.clone())));
+        }
+        frames
+    }
+
+    fn frame(&self, choices: Value, usage: Option<Value>) -> String {
+        let mut chunk = json!({
+            "id": self.id,
+            "object": "text_completion",
+            "created": self.created,
+            "model": self.model,
+            "choices": choices,
+        });
+        if let Some(usage) = usage {
+            chunk["usage"] = usage;
+        }
+        format!("data: {}\n\n", chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_text() {
+        assert_eq!(prompt_text(&json!("Once upon")), Ok("Once upon".to_string()));
+        assert_eq!(prompt_text(&json!(["Once upon"])), Ok("Once upon".to_string()));
+        assert!(prompt_text(&json!(["a", "b"])).is_err());
+        assert!(prompt_text(&json!([[1, 2, 3]])).is_err());
+    }
+
+    #[test]
+    fn test_request_params_logprobs() {
+        let extra: HashMap<String, Value> = [
+            ("logprobs".to_string(), json!(2)),
+            ("echo".to_string(), json!(true)),
+            ("max_tokens".to_string(), json!(16)),
+        ]
+        .into_iter()
+        .collect();
+        let params = request_params(&extra);
+        assert_eq!(params["logprobs"], true);
+        assert_eq!(params["top_logprobs"], 2);
+        assert_eq!(params["max_tokens"], 16);
+        assert!(!params.contains_key("echo"));
+    }
+
+    #[test]
+    fn test_response_with_echo_and_logprobs() {
+        let chat = json!({
+            "model": "gpt-test",
+            "created": 1700000000,
+            "choices": [
+                {"index": 0, "message": {"role": "assistant", "content": " a time"}, "finish_reason": "stop",
+                 "logprobs": {"content": [
+                    {"token": " a", "logprob": -0.1, "top_logprobs": [{"token": " a", "logprob": -0.1}]},
+                    {"token": " time", "logprob": -0.2, "top_logprobs": []}
+                 ]}},
+                {"index": 1, "message": {"role": "assistant", "content": " midnight"}, "finish_reason": "length"}
+            ],
+            "usage": {"prompt_tokens": 2, "completion_tokens": 2, "total_tokens": 4}
+        });
+        let options = Options { prompt: "Once upon".to_string(), echo: true, include_usage: false };
+        let out = response_from_openai(&chat, "gpt-test", &options);
+        assert_eq!(out["object"], "text_completion");
+        assert_eq!(out["choices"][0]["text"], "Once upon a time");
+        assert_eq!(out["choices"][0]["logprobs"]["tokens"], json!([" a", " time"]));
+        assert_eq!(out["choices"][0]["logprobs"]["text_offset"], json!([9, 11]));
+        assert_eq!(out["choices"][0]["logprobs"]["top_logprobs"][0][" a"], -0.1);
+        assert_eq!(out["choices"][1]["text"], "Once upon midnight");
+        assert_eq!(out["choices"][1]["logprobs"], Value::Null);
+        assert_eq!(out["usage"]["total_tokens"], 4);
+    }
+
+    #[test]
+    fn test_chunk_translator() {
+        let options = Options { prompt: "Say".to_string(), echo: true, include_usage: false };
+        let mut t = ChunkTranslator::new("gpt-test", options);
+        let first = t.translate(r#"{"choices":[{"index":0,"delta":{"role":"assistant","content":" hi"}}]}"#);
+        assert!(first[0].contains(r#""text":"Say hi""#));
+        let last = t.translate(r#"{"choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#);
+        assert!(last[0].contains(r#""finish_reason":"stop""#));
+        assert!(last[0].contains(r#""text":"""#));
+        assert!(t.translate(r#"{"choices":[],"usage":{"prompt_tokens":1,"completion_tokens":1}}"#).is_empty());
+        assert_eq!(t.translate("[DONE]"), vec!["data: [DONE]\n\n"]);
+    }
+}
@@ -1,7 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::balance::LoadBalancing;
+use crate::breaker::BreakerConfig;
+use crate::budget::Budget;
 use crate::cache::CacheConfig;
+use crate::error::ErrorPolicy;
+use crate::health::HealthConfig;
+use crate::keys::ApiKey;
+use crate::ratelimit::RateLimit;
+use crate::retry::RetryPolicy;
 use crate::scorer::ScorerConfig;
+use crate::timeout::Timeouts;
+use crate::virtual_keys::VirtualKey;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
@@ -27,6 +37,9 @@ pub enum ProviderType {
     DeepSeek,
     XAI,
     CustomOpenAI, // For generic OpenAI compatible
+    Ollama,       // Local Ollama / llama.cpp server; models are auto-discovered
+    AzureOpenAI,  // Azure OpenAI resource; models map to deployments
+    Bedrock,      // AWS Bedrock Converse API, signed with SigV4
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -46,6 +59,16 @@ pub struct Model {
     pub context_window: u32,
     pub supports_vision: bool,
     pub supports_function_calling: bool,
+    /// Upstream id for this model: the Azure OpenAI deployment name or the
+    /// Bedrock model id. Defaults to the model id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+    /// Embedding model, served through `/v1/embeddings` instead of chat.
+    #[serde(default)]
+    pub embedding: bool,
+    /// Requests/tokens per minute for this model on this provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,13 +77,51 @@ pub struct Provider {
     pub name: String,
     pub provider_type: ProviderType,
     pub api_key: Option<String>,
+    /// Several keys to rotate between instead of `api_key`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<ApiKey>,
     pub endpoint: Option<String>,
+    /// `api-version` query parameter for Azure OpenAI providers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    /// AWS credentials and region for Bedrock providers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws: Option<AwsConfig>,
+    /// Retries and backoff on this provider before falling back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    /// Connect, first-byte and total timeouts; defaults apply when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeouts: Option<Timeouts>,
+    /// Proxy URL for requests to this provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Path to a PEM file with extra root certificates to trust, for
+    /// endpoints behind a private CA.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+    /// Requests/tokens per minute across all of this provider's models.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// Share of traffic under weighted-random load balancing, relative to
+    /// the other equivalent providers (default 1; 0 takes no traffic first).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
     pub tier: Tier,
     pub enabled: bool,
     pub priority: u8, // Higher priority tries first within same tier
     pub models: Vec<Model>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwsConfig {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+    pub region: String,
+}
+
 /// Maps a complexity tier to a specific model for a routing profile.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelMapping {
@@ -80,6 +141,33 @@ pub struct RoutingProfile {
     /// Separate model mappings used when agentic mode is active (tool use, multi-step tasks).
     #[serde(default)]
     pub agentic_model_mapping: HashMap<String, ModelMapping>,
+    /// How candidates within the same tier are ordered.
+    #[serde(default)]
+    pub ordering: CandidateOrder,
+    /// Share of latency in the `weighted` score, from 0 (cost only) to 1
+    /// (latency only). Defaults to 0.5.
+    #[serde(default)]
+    pub latency_weight: Option<f64>,
+    /// How providers that tie on tier and cost share traffic.
+    #[serde(default)]
+    pub load_balancing: LoadBalancing,
+}
+
+/// Order of candidate providers within a tier.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CandidateOrder {
+    /// Cheapest expected request cost first, then priority. The cost covers
+    /// the estimated input and output tokens at the model's prices; input
+    /// price alone stands in when there is no request to estimate.
+    #[default]
+    Cost,
+    /// Lowest observed latency first. Providers without samples yet go
+    /// first, so they get measured.
+    Latency,
+    /// Lowest blend of relative cost and relative latency first; latency
+    /// counts as zero until a provider has samples.
+    Weighted,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,6 +185,21 @@ pub struct Config {
     /// Session persistence configuration.
     #[serde(default)]
     pub session: Option<SessionConfig>,
+    /// How provider failures are handled, per error class.
+    #[serde(default)]
+    pub error_policy: Option<ErrorPolicy>,
+    /// Circuit breakers per provider and model.
+    #[serde(default)]
+    pub circuit_breaker: Option<BreakerConfig>,
+    /// Background health checks of the providers.
+    #[serde(default)]
+    pub health_check: Option<HealthConfig>,
+    /// Keys clients must present; when empty the endpoints are open.
+    #[serde(default)]
+    pub virtual_keys: Vec<VirtualKey>,
+    /// Spend budgets per period and scope.
+    #[serde(default)]
+    pub budgets: Vec<Budget>,
 }
 
 impl Default for Config {
@@ -108,7 +211,16 @@ impl Default for Config {
                     name: "OpenAI".to_string(),
                     provider_type: ProviderType::OpenAI,
                     api_key: None,
+                    api_keys: Vec::new(),
                     endpoint: Some("https://api.openai.com/v1/chat/completions".to_string()),
+                    api_version: None,
+                    aws: None,
+                    retry: None,
+                    timeouts: None,
+                    proxy: None,
+                    ca_cert: None,
+                    rate_limit: None,
+                    weight: None,
                     tier: Tier::Subscription,
                     enabled: true,
                     priority: 1,
@@ -121,6 +233,9 @@ impl Default for Config {
                             context_window: 128000,
                             supports_vision: true,
                             supports_function_calling: true,
+                            deployment: None,
+                            embedding: false,
+                            rate_limit: None,
                         }
                     ],
                 },
@@ -129,7 +244,16 @@ impl Default for Config {
                     name: "Anthropic".to_string(),
                     provider_type: ProviderType::Anthropic,
                     api_key: None,
+                    api_keys: Vec::new(),
                     endpoint: Some("https://api.anthropic.com/v1/messages".to_string()),
+                    api_version: None,
+                    aws: None,
+                    retry: None,
+                    timeouts: None,
+                    proxy: None,
+                    ca_cert: None,
+                    rate_limit: None,
+                    weight: None,
                     tier: Tier::Subscription,
                     enabled: true,
                     priority: 1,
@@ -142,6 +266,9 @@ impl Default for Config {
                             context_window: 200000,
                             supports_vision: true,
                             supports_function_calling: true,
+                            deployment: None,
+                            embedding: false,
+                            rate_limit: None,
                         }
                     ],
                 },
@@ -150,7 +277,16 @@ impl Default for Config {
                     name: "DeepSeek".to_string(),
                     provider_type: ProviderType::DeepSeek,
                     api_key: None,
+                    api_keys: Vec::new(),
                     endpoint: Some("https://api.deepseek.com/chat/completions".to_string()),
+                    api_version: None,
+                    aws: None,
+                    retry: None,
+                    timeouts: None,
+                    proxy: None,
+                    ca_cert: None,
+                    rate_limit: None,
+                    weight: None,
                     tier: Tier::Cheap,
                     enabled: true,
                     priority: 1,
@@ -163,9 +299,66 @@ impl Default for Config {
                             context_window: 128000,
                             supports_vision: false,
                             supports_function_calling: true,
+                            deployment: None,
+                            embedding: false,
+                            rate_limit: None,
                         }
                     ],
                 },
+                Provider {
+                    id: "google".to_string(),
+                    name: "Google Gemini".to_string(),
+                    provider_type: ProviderType::Google,
+                    api_key: None,
+                    api_keys: Vec::new(),
+                    endpoint: Some("https://generativelanguage.googleapis.com/v1beta".to_string()),
+                    api_version: None,
+                    aws: None,
+                    retry: None,
+                    timeouts: None,
+                    proxy: None,
+                    ca_cert: None,
+                    rate_limit: None,
+                    weight: None,
+                    tier: Tier::Free,
+                    enabled: false,
+                    priority: 1,
+                    models: vec![
+                        Model {
+                            id: "google/gemini-2.5-flash".to_string(),
+                            name: "Gemini 2.5 Flash".to_string(),
+                            input_cost_per_1m: 0.0,
+                            output_cost_per_1m: 0.0,
+                            context_window: 1048576,
+                            supports_vision: true,
+                            supports_function_calling: true,
+                            deployment: None,
+                            embedding: false,
+                            rate_limit: None,
+                        }
+                    ],
+                },
+                Provider {
+                    id: "ollama".to_string(),
+                    name: "Ollama (local)".to_string(),
+                    provider_type: ProviderType::Ollama,
+                    api_key: None,
+                    api_keys: Vec::new(),
+                    endpoint: Some("http://localhost:11434".to_string()),
+                    api_version: None,
+                    aws: None,
+                    retry: None,
+                    timeouts: None,
+                    proxy: None,
+                    ca_cert: None,
+                    rate_limit: None,
+                    weight: None,
+                    tier: Tier::Free,
+                    enabled: false,
+                    priority: 1,
+                    // Filled in by model discovery once enabled
+                    models: vec![],
+                },
             ],
             profiles: vec![
                 RoutingProfile {
@@ -184,6 +377,9 @@ impl Default for Config {
                         ("complex".to_string(), ModelMapping { model_id: "claude-sonnet-4.6".to_string(), provider_id: "".to_string() }),
                         ("reasoning".to_string(), ModelMapping { model_id: "moonshot/kimi-k2.5".to_string(), provider_id: "".to_string() }),
                     ]),
+                    ordering: CandidateOrder::Cost,
+                    latency_weight: None,
+                    load_balancing: LoadBalancing::None,
                 },
                 RoutingProfile {
                     name: "eco".to_string(),
@@ -201,6 +397,9 @@ impl Default for Config {
                         ("complex".to_string(), ModelMapping { model_id: "claude-sonnet-4.6".to_string(), provider_id: "".to_string() }),
                         ("reasoning".to_string(), ModelMapping { model_id: "moonshot/kimi-k2.5".to_string(), provider_id: "".to_string() }),
                     ]),
+                    ordering: CandidateOrder::Cost,
+                    latency_weight: None,
+                    load_balancing: LoadBalancing::None,
                 },
                 RoutingProfile {
                     name: "premium".to_string(),
@@ -218,6 +417,9 @@ impl Default for Config {
                         ("complex".to_string(), ModelMapping { model_id: "claude-sonnet-4.6".to_string(), provider_id: "".to_string() }),
                         ("reasoning".to_string(), ModelMapping { model_id: "moonshot/kimi-k2.5".to_string(), provider_id: "".to_string() }),
                     ]),
+                    ordering: CandidateOrder::Cost,
+                    latency_weight: None,
+                    load_balancing: LoadBalancing::None,
                 },
             ],
             active_profile: "auto".to_string(),
@@ -225,6 +427,11 @@ impl Default for Config {
             cache: None,
             agentic_mode: false,
             session: None,
+            error_policy: None,
+            circuit_breaker: None,
+            health_check: None,
+            virtual_keys: Vec::new(),
+            budgets: Vec::new(),
         }
     }
 }
@@ -0,0 +1,172 @@
+//! Model auto-discovery for local servers (Ollama, llama.cpp, LM Studio).
+//!
+//! Local providers list their models instead of having them typed into the
+//! config. Discovered models are free and are merged into `Provider.models`
+//! without touching entries the user already configured.
+
+use crate::config::{Model, Provider, ProviderType};
+use crate::state::AppState;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const DEFAULT_LOCAL_BASE_URL: &str = "http://localhost:11434";
+
+/// Context window assumed for discovered models; the listings don't report one.
+const DEFAULT_CONTEXT_WINDOW: u32 = 8192;
+
+/// Base URL of a local server. Accepts a bare host or a full chat URL.
+pub fn base_url(provider: &Provider) -> String {
+    let endpoint = provider.endpoint.as_deref().unwrap_or(DEFAULT_LOCAL_BASE_URL).trim_end_matches('/');
+    let endpoint = endpoint.strip_suffix("/chat/completions").unwrap_or(endpoint);
+    endpoint.strip_suffix("/v1").unwrap_or(endpoint).to_string()
+}
+
+/// List the model ids served by a local provider. Tries Ollama's `/api/tags`
+/// first, then the OpenAI-style `/v1/models` used by llama.cpp and others.
+pub async fn list_models(client: &reqwest::Client, provider: &Provider) -> Result<Vec<String>, String> {
+    let base = base_url(provider);
+
+    let tags = fetch_json(client, &format!("{}/api/tags", base)).await;
+    if let Some(models) = tags.as_ref().ok().and_then(|body| body.get("models")).and_then(|m| m.as_array()) {
+        return Ok(models
+            .iter()
+            .filter_map(|m| m.get("name").or_else(|| m.get("model")).and_then(|n| n.as_str()))
+            .map(|s| s.to_string())
+            .collect());
+    }
+
+    let listing = fetch_json(client, &format!("{}/v1/models", base)).await;
+    match listing {
+        Ok(body) => match body.get("data").and_then(|d| d.as_array()) {
+            Some(models) => Ok(models
+                .iter()
+                .filter_map(|m| m.get("id").and_then(|id| id.as_str()))
+                .map(|s| s.to_string())
+                .collect()),
+            None => Err("model listing has no `data` array".to_string()),
+        },
+        Err(e) => Err(tags.err().map(|t| format!("{}; {}", t, e)).unwrap_or(e)),
+    }
+}
+
+async fn fetch_json(client: &reqwest::Client, url: &str) -> Result<Value, String> {
+    let response = client
+        .get(url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .map_err(|e| format!("GET {} failed: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("GET {} returned {}", url, response.status()));
+    }
+    response.json::<Value>().await.map_err(|e| format!("GET {} returned invalid JSON: {}", url, e))
+}
+
+/// Add discovered models that are not configured yet. Returns how many were added.
+pub fn merge_models(provider: &mut Provider, ids: &[String]) -> usize {
+    let mut added = 0;
+    for id in ids {
+        if provider.models.iter().any(|m| &m.id == id) {
+            continue;
+        }
+        provider.models.push(Model {
+            id: id.clone(),
+            name: id.clone(),
+            input_cost_per_1m: 0.0,
+            output_cost_per_1m: 0.0,
+            context_window: DEFAULT_CONTEXT_WINDOW,
+            supports_vision: false,
+            supports_function_calling: true,
+            deployment: None,
+            embedding: false,
+            rate_limit: None,
+        });
+        added += 1;
+    }
+    added
+}
+
+/// Discover models for every enabled local provider and merge them into the
+/// live config. Returns the discovered ids, or the error, per provider id.
+pub async fn refresh(state: &AppState) -> HashMap<String, Result<Vec<String>, String>> {
+    let providers: Vec<Provider> = state
+        .get_config()
+        .await
+        .providers
+        .into_iter()
+        .filter(|p| p.enabled && p.provider_type == ProviderType::Ollama)
+        .collect();
+
+    let mut results = HashMap::new();
+    for provider in providers {
+        // The provider's own client, so discovery goes through its proxy and CA
+        let result = match state.clients.get(&provider) {
+            Ok(client) => list_models(&client, &provider).await,
+            Err(e) => Err(e),
+        };
+        match &result {
+            Ok(ids) => {
+                let mut config = state.config.write().await;
+                if let Some(p) = config.providers.iter_mut().find(|p| p.id == provider.id) {
+                    let added = merge_models(p, ids);
+                    tracing::info!(provider = %provider.name, found = ids.len(), added, "Discovered local models");
+                }
+            }
+            Err(e) => tracing::warn!("Model discovery for provider {} failed: {}", provider.name, e),
+        }
+        results.insert(provider.id.clone(), result);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Tier;
+
+    fn local_provider(endpoint: Option<&str>) -> Provider {
+        Provider {
+            id: "local".to_string(),
+            name: "Local".to_string(),
+            provider_type: ProviderType::Ollama,
+            api_key: None,
+            api_keys: Vec::new(),
+            endpoint: endpoint.map(|e| e.to_string()),
+            api_version: None,
+            aws: None,
+            retry: None,
+            timeouts: None,
+            proxy: None,
+            ca_cert: None,
+            rate_limit: None,
+            weight: None,
+            tier: Tier::Free,
+            enabled: true,
+            priority: 1,
+            models: vec![],
+        }
+    }
+
+    #[test]
+    fn test_base_url_normalized() {
+        assert_eq!(base_url(&local_provider(None)), DEFAULT_LOCAL_BASE_URL);
+        assert_eq!(base_url(&local_provider(Some("http://box:8080/"))), "http://box:8080");
+        assert_eq!(base_url(&local_provider(Some("http://box:8080/v1"))), "http://box:8080");
+        assert_eq!(base_url(&local_provider(Some("http://box:8080/v1/chat/completions"))), "http://box:8080");
+    }
+
+    #[test]
+    fn test_merge_keeps_configured_models() {
+        let mut provider = local_provider(None);
+        merge_models(&mut provider, &["llama3.2".to_string()]);
+        provider.models[0].input_cost_per_1m = 0.5;
+
+        let added = merge_models(&mut provider, &["llama3.2".to_string(), "qwen3:8b".to_string()]);
+        assert_eq!(added, 1);
+        assert_eq!(provider.models.len(), 2);
+        assert_eq!(provider.models[0].input_cost_per_1m, 0.5);
+        assert_eq!(provider.models[1].id, "qwen3:8b");
+        assert_eq!(provider.models[1].output_cost_per_1m, 0.0);
+    }
+}
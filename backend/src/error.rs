@@ -0,0 +1,367 @@
+//! OpenAI-compatible error responses.
+//!
+//! Errors are returned as `{"error": {"message", "type", "code", "param"}}`.
+//! When providers were tried, `error.attempts` lists what each one answered.
+
+use axum::{
+    body::Bytes,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Longest upstream error text kept in an attempt summary.
+const MAX_MESSAGE_LEN: usize = 300;
+
+/// What kind of failure a provider attempt ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    RateLimited,
+    /// Bad or missing credentials, or no access to the model/billing.
+    Auth,
+    ContextOverflow,
+    ContentFiltered,
+    /// Any other malformed request (bad tool schema, unknown parameter, ...).
+    InvalidRequest,
+    ServerError,
+    Timeout,
+    Connection,
+    Other,
+}
+
+impl ErrorClass {
+    /// Classify an upstream error response.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let body = body.to_lowercase();
+        let mentions = |needles: &[&str]| needles.iter().any(|n| body.contains(n));
+        match status {
+            401..=403 => Self::Auth,
+            429 => Self::RateLimited,
+            408 => Self::Timeout,
+            413 => Self::ContextOverflow,
+            400..=499 if mentions(&[
+                "context_length_exceeded", "context length", "context window", "too many tokens",
+                "prompt is too long", "input is too long",
+            ]) => Self::ContextOverflow,
+            // Provider codes only: OpenAI/Azure error codes, and Gemini block
+            // reasons as quoted values so `safety_settings` errors don't match
+            400..=499 if mentions(&[
+                "content_filter", "content_policy_violation", "responsibleaipolicyviolation",
+                "\"safety\"", "\"prohibited_content\"", "\"blocklist\"",
+            ]) => Self::ContentFiltered,
+            400 | 422 => Self::InvalidRequest,
+            504 => Self::Timeout,
+            500..=599 => Self::ServerError,
+            _ => Self::Other,
+        }
+    }
+
+    /// Classify a failure to get any response at all.
+    pub fn from_transport(e: &reqwest::Error) -> Self {
+        if e.is_timeout() { Self::Timeout } else { Self::Connection }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RateLimited => "rate_limited",
+            Self::Auth => "auth",
+            Self::ContextOverflow => "context_overflow",
+            Self::ContentFiltered => "content_filtered",
+            Self::InvalidRequest => "invalid_request",
+            Self::ServerError => "server_error",
+            Self::Timeout => "timeout",
+            Self::Connection => "connection",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// What to do when an attempt fails with a given class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorAction {
+    /// Try the same provider again, within its retry policy's attempts.
+    Retry,
+    /// Move on to the next candidate.
+    Fallthrough,
+    /// Stop and return the failure to the client.
+    Abort,
+}
+
+/// Per-class actions for provider failures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ErrorPolicy {
+    pub rate_limited: ErrorAction,
+    pub auth: ErrorAction,
+    pub context_overflow: ErrorAction,
+    pub content_filtered: ErrorAction,
+    pub invalid_request: ErrorAction,
+    pub server_error: ErrorAction,
+    pub timeout: ErrorAction,
+    pub connection: ErrorAction,
+    pub other: ErrorAction,
+    /// Retries on the same provider before a `retry` class falls through,
+    /// for providers without their own retry policy.
+    pub max_retries: u32,
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self {
+        Self {
+            rate_limited: ErrorAction::Fallthrough,
+            auth: ErrorAction::Fallthrough,
+            context_overflow: ErrorAction::Abort,
+            content_filtered: ErrorAction::Abort,
+            invalid_request: ErrorAction::Abort,
+            server_error: ErrorAction::Fallthrough,
+            timeout: ErrorAction::Fallthrough,
+            connection: ErrorAction::Fallthrough,
+            other: ErrorAction::Fallthrough,
+            max_retries: 1,
+        }
+    }
+}
+
+impl ErrorPolicy {
+    pub fn action(&self, class: ErrorClass) -> ErrorAction {
+        match class {
+            ErrorClass::RateLimited => self.rate_limited,
+            ErrorClass::Auth => self.auth,
+            ErrorClass::ContextOverflow => self.context_overflow,
+            ErrorClass::ContentFiltered => self.content_filtered,
+            ErrorClass::InvalidRequest => self.invalid_request,
+            ErrorClass::ServerError => self.server_error,
+            ErrorClass::Timeout => self.timeout,
+            ErrorClass::Connection => self.connection,
+            ErrorClass::Other => self.other,
+        }
+    }
+}
+
+/// The outcome of one failed provider attempt.
+#[derive(Debug, Clone, Serialize)]
+pub struct Attempt {
+    pub provider: String,
+    /// Upstream HTTP status; None when no response was received.
+    pub status: Option<u16>,
+    pub class: ErrorClass,
+    pub message: String,
+}
+
+impl Attempt {
+    /// A provider that answered with an error status.
+    pub fn upstream(provider: &str, status: u16, body: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            status: Some(status),
+            class: ErrorClass::from_response(status, body),
+            message: upstream_message(body),
+        }
+    }
+
+    /// A provider that could not be reached.
+    pub fn transport(provider: &str, e: &reqwest::Error) -> Self {
+        Self::failed(provider, ErrorClass::from_transport(e), e.to_string())
+    }
+
+    /// A provider that could not be used for another reason.
+    pub fn failed(provider: &str, class: ErrorClass, message: impl Into<String>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            status: None,
+            class,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Attempt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{}: {} {}", self.provider, status, self.message),
+            None => write!(f, "{}: {}", self.provider, self.message),
+        }
+    }
+}
+
+/// An upstream's error reply, kept whole so it can be returned unchanged.
+#[derive(Debug)]
+pub struct UpstreamError {
+    pub status: StatusCode,
+    pub content_type: Option<String>,
+    pub body: Bytes,
+}
+
+impl IntoResponse for UpstreamError {
+    fn into_response(self) -> Response {
+        let content_type = self.content_type.unwrap_or_else(|| "application/json".to_string());
+        (self.status, [(header::CONTENT_TYPE, content_type)], self.body).into_response()
+    }
+}
+
+/// Why a provider attempt produced no usable reply.
+#[derive(Debug)]
+pub struct ProviderError {
+    pub attempt: Attempt,
+    /// The upstream's reply, when there was one.
+    pub response: Option<Box<UpstreamError>>,
+    /// How long the upstream asked us to wait before trying again.
+    pub retry_after: Option<Duration>,
+}
+
+impl From<Attempt> for ProviderError {
+    fn from(attempt: Attempt) -> Self {
+        Self { attempt, response: None, retry_after: None }
+    }
+}
+
+/// Pull a readable message out of an upstream error body: `error.message`
+/// (OpenAI, Anthropic, Gemini), `message` (Bedrock), or the raw text.
+pub fn upstream_message(body: &str) -> String {
+    let parsed = serde_json::from_str::<Value>(body).ok();
+    let message = parsed
+        .as_ref()
+        .and_then(|v| {
+            v.pointer("/error/message")
+                .or_else(|| v.get("message"))
+                .or_else(|| v.get("error").filter(|e| e.is_string()))
+        })
+        .and_then(|m| m.as_str())
+        .unwrap_or(body)
+        .trim();
+    match message.char_indices().nth(MAX_MESSAGE_LEN) {
+        Some((end, _)) => format!("{}...", &message[..end]),
+        None => message.to_string(),
+    }
+}
+
+/// One-line summary of all attempts, for the request log.
+pub fn summary(attempts: &[Attempt]) -> String {
+    attempts.iter().map(|a| a.to_string()).collect::<Vec<_>>().join("; ")
+}
+
+/// Build an OpenAI-shaped error response.
+pub fn json_error(status: StatusCode, error_type: &str, code: &str, message: &str, attempts: &[Attempt]) -> Response {
+    let mut error = json!({
+        "message": message,
+        "type": error_type,
+        "code": code,
+        "param": null,
+    });
+    if !attempts.is_empty() {
+        error["attempts"] = json!(attempts);
+    }
+    (status, Json(json!({"error": error}))).into_response()
+}
+
+/// 400 for a request that no configured provider can serve.
+pub fn no_provider(model: &str) -> Response {
+    json_error(
+        StatusCode::BAD_REQUEST,
+        "invalid_request_error",
+        "model_not_found",
+        &format!("No provider found for model '{}'", model),
+        &[],
+    )
+}
+
+/// The response for a request aborted by `failure`: the upstream's own reply
+/// when there is one, otherwise a gateway error.
+pub fn aborted(failure: ProviderError, attempts: &[Attempt]) -> Response {
+    if let Some(response) = failure.response {
+        return response.into_response();
+    }
+    let (status, code) = match failure.attempt.class {
+        ErrorClass::Timeout => (StatusCode::GATEWAY_TIMEOUT, "upstream_timeout"),
+        _ => (StatusCode::BAD_GATEWAY, "upstream_error"),
+    };
+    json_error(status, "api_error", code, &failure.attempt.to_string(), attempts)
+}
+
+/// 503 once every candidate has failed.
+pub fn all_failed(model: &str, attempts: &[Attempt]) -> Response {
+    json_error(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "api_error",
+        "all_providers_failed",
+        &format!("All providers failed for model '{}'", model),
+        attempts,
+    )
+}
+
+/// 504 once the client's `x-request-timeout-ms` deadline has passed.
+pub fn deadline_exceeded(attempts: &[Attempt]) -> Response {
+    json_error(
+        StatusCode::GATEWAY_TIMEOUT,
+        "api_error",
+        "deadline_exceeded",
+        "Request deadline exceeded before any provider answered",
+        attempts,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_message() {
+        assert_eq!(upstream_message(r#"{"error":{"message":"Bad key","type":"auth"}}"#), "Bad key");
+        assert_eq!(upstream_message(r#"{"message":"Throttled"}"#), "Throttled");
+        assert_eq!(upstream_message("  gateway timeout \n"), "gateway timeout");
+        assert_eq!(upstream_message(&"x".repeat(400)).len(), MAX_MESSAGE_LEN + 3);
+    }
+
+    #[test]
+    fn test_classify_responses() {
+        assert_eq!(ErrorClass::from_response(429, ""), ErrorClass::RateLimited);
+        assert_eq!(ErrorClass::from_response(401, ""), ErrorClass::Auth);
+        assert_eq!(ErrorClass::from_response(413, ""), ErrorClass::ContextOverflow);
+        assert_eq!(
+            ErrorClass::from_response(400, r#"{"error":{"code":"context_length_exceeded"}}"#),
+            ErrorClass::ContextOverflow
+        );
+        assert_eq!(
+            ErrorClass::from_response(400, r#"{"error":{"code":"content_filter","message":"filtered"}}"#),
+            ErrorClass::ContentFiltered
+        );
+        assert_eq!(
+            ErrorClass::from_response(400, r#"{"promptFeedback":{"blockReason":"SAFETY"}}"#),
+            ErrorClass::ContentFiltered
+        );
+        assert_eq!(
+            ErrorClass::from_response(400, r#"{"error":{"message":"Invalid value at 'safety_settings[0].threshold'"}}"#),
+            ErrorClass::InvalidRequest
+        );
+        assert_eq!(
+            ErrorClass::from_response(400, r#"{"error":{"message":"Invalid schema for function 'f'"}}"#),
+            ErrorClass::InvalidRequest
+        );
+        assert_eq!(ErrorClass::from_response(529, "overloaded"), ErrorClass::ServerError);
+        assert_eq!(ErrorClass::from_response(504, ""), ErrorClass::Timeout);
+        assert_eq!(ErrorClass::from_response(404, ""), ErrorClass::Other);
+    }
+
+    #[test]
+    fn test_policy_actions() {
+        let policy: ErrorPolicy = serde_json::from_value(json!({"server_error": "retry", "max_retries": 2})).unwrap();
+        assert_eq!(policy.action(ErrorClass::ServerError), ErrorAction::Retry);
+        assert_eq!(policy.max_retries, 2);
+        assert_eq!(policy.action(ErrorClass::InvalidRequest), ErrorAction::Abort);
+        assert_eq!(policy.action(ErrorClass::RateLimited), ErrorAction::Fallthrough);
+    }
+
+    #[test]
+    fn test_summary() {
+        let attempts = vec![
+            Attempt::upstream("a", 500, r#"{"error":{"message":"boom"}}"#),
+            Attempt::failed("b", ErrorClass::Connection, "connection refused"),
+        ];
+        assert_eq!(summary(&attempts), "a: 500 boom; b: connection refused");
+    }
+}
@@ -0,0 +1,136 @@
+//! Expected token usage of a request before it is sent, so candidates can be
+//! ordered by what the whole request is likely to cost rather than by input
+//! price alone. Output is often priced several times higher than input.
+
+use crate::config::Provider;
+use crate::handlers::{ChatCompletionRequest, EmbeddingsRequest};
+use std::fmt;
+
+/// Output tokens assumed when the client sets no limit and there is no history.
+pub const DEFAULT_OUTPUT_TOKENS: u64 = 500;
+
+/// Where the expected output token count came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSource {
+    /// The client's `max_tokens` / `max_completion_tokens`.
+    Limit,
+    /// Average output of earlier requests in the same complexity tier, or of
+    /// all requests when the tier has none.
+    History,
+    Default,
+}
+
+impl fmt::Display for OutputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputSource::Limit => "limit",
+            OutputSource::History => "history",
+            OutputSource::Default => "default",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExpectedUsage {
+    pub prompt_tokens: u64,
+    pub output_tokens: u64,
+    pub output_source: OutputSource,
+}
+
+impl ExpectedUsage {
+    /// Estimate a request's usage. `history` is the average output of
+    /// comparable earlier requests, if any.
+    pub fn estimate(request: &ChatCompletionRequest, history: Option<f64>) -> Self {
+        let (output_tokens, output_source) = match output_limit(request) {
+            Some(limit) => (limit, OutputSource::Limit),
+            None => match history {
+                Some(average) => (average.round() as u64, OutputSource::History),
+                None => (DEFAULT_OUTPUT_TOKENS, OutputSource::Default),
+            },
+        };
+        Self {
+            prompt_tokens: prompt_tokens(request),
+            output_tokens,
+            output_source,
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.output_tokens
+    }
+
+    /// Expected cost of the request on a provider's model, in dollars.
+    pub fn cost(&self, provider: &Provider, model_id: &str) -> Option<f64> {
+        let model = provider.models.iter().find(|m| m.id == model_id)?;
+        Some(
+            (self.prompt_tokens as f64 / 1_000_000.0) * model.input_cost_per_1m
+                + (self.output_tokens as f64 / 1_000_000.0) * model.output_cost_per_1m,
+        )
+    }
+}
+
+/// Rough prompt size: ~4 characters per token over the messages and tool
+/// definitions, as sent.
+fn prompt_tokens(request: &ChatCompletionRequest) -> u64 {
+    let messages = serde_json::to_string(&request.messages).map(|s| s.len()).unwrap_or(0);
+    let tools = request.extra.get("tools").map(|t| t.to_string().len()).unwrap_or(0);
+    ((messages + tools) / 4) as u64
+}
+
+/// Rough size of an embeddings input, at the same ~4 characters per token.
+/// Embeddings produce no output tokens.
+pub fn embedding_tokens(request: &EmbeddingsRequest) -> u64 {
+    (request.input.to_string().len() / 4) as u64
+}
+
+/// The client's cap on output tokens, if it set one.
+fn output_limit(request: &ChatCompletionRequest) -> Option<u64> {
+    ["max_completion_tokens", "max_tokens"]
+        .iter()
+        .find_map(|key| request.extra.get(*key).and_then(|v| v.as_u64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::handlers::ClientApi;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn request(extra: HashMap<String, serde_json::Value>) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: "m".to_string(),
+            messages: vec![json!({"role": "user", "content": "x".repeat(400)})],
+            extra,
+            native_body: None,
+            client_api: ClientApi::Chat,
+        }
+    }
+
+    #[test]
+    fn test_output_sources() {
+        let limited = request(HashMap::from([("max_tokens".to_string(), json!(64))]));
+        let usage = ExpectedUsage::estimate(&limited, Some(900.0));
+        assert_eq!((usage.output_tokens, usage.output_source), (64, OutputSource::Limit));
+        assert!(usage.prompt_tokens >= 100 && usage.prompt_tokens < 120);
+
+        let usage = ExpectedUsage::estimate(&request(HashMap::new()), Some(899.6));
+        assert_eq!((usage.output_tokens, usage.output_source), (900, OutputSource::History));
+
+        let usage = ExpectedUsage::estimate(&request(HashMap::new()), None);
+        assert_eq!((usage.output_tokens, usage.output_source), (DEFAULT_OUTPUT_TOKENS, OutputSource::Default));
+    }
+
+    #[test]
+    fn test_cost_includes_output() {
+        let mut provider = Config::default().providers[0].clone();
+        provider.models.truncate(1);
+        provider.models[0].input_cost_per_1m = 1.0;
+        provider.models[0].output_cost_per_1m = 4.0;
+        let model_id = provider.models[0].id.clone();
+        let usage = ExpectedUsage { prompt_tokens: 1_000_000, output_tokens: 500_000, output_source: OutputSource::Limit };
+        assert_eq!(usage.cost(&provider, &model_id), Some(3.0));
+        assert_eq!(usage.cost(&provider, "not-served"), None);
+    }
+}
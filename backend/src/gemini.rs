@@ -0,0 +1,633 @@
+//! Google Gemini `generateContent` / `streamGenerateContent` adapter.
+//!
+//! Requests are converted from the OpenAI chat shape, and responses and SSE
+//! chunks are converted back into OpenAI `chat.completion` objects.
+
+use crate::config::Provider;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+pub const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// JSON Schema keywords the Gemini function declaration schema rejects.
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &[
+    "$schema", "$id", "$ref", "$defs", "definitions", "additionalProperties",
+    "default", "examples", "strict", "title", "const",
+];
+
+/// Build the request URL. For Google providers `endpoint` is the API base
+/// (e.g. `https://generativelanguage.googleapis.com/v1beta`), not a full URL.
+pub fn url(provider: &Provider, model: &str, streaming: bool) -> String {
+    if streaming {
+        format!("{}:streamGenerateContent?alt=sse", model_url(provider, model))
+    } else {
+        format!("{}:generateContent", model_url(provider, model))
+    }
+}
+
+/// URL of the batch embeddings call for `model`.
+pub fn embed_url(provider: &Provider, model: &str) -> String {
+    format!("{}:batchEmbedContents", model_url(provider, model))
+}
+
+fn model_url(provider: &Provider, model: &str) -> String {
+    let base = provider.endpoint.as_deref().unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/');
+    format!("{}/models/{}", base, model_name(model))
+}
+
+fn model_name(model: &str) -> &str {
+    model.trim_start_matches("google/").trim_start_matches("models/")
+}
+
+/// Map a Gemini `finishReason` to an OpenAI `finish_reason`.
+pub fn finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "MAX_TOKENS" => "length",
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => "content_filter",
+        "MALFORMED_FUNCTION_CALL" => "tool_calls",
+        _ => "stop",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Requests: OpenAI chat -> Gemini
+// ---------------------------------------------------------------------------
+
+/// Flatten string or array-of-parts content into plain text.
+fn text_of(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Convert an OpenAI `image_url` part into an `inlineData` or `fileData` part.
+fn image_part(part: &Value) -> Option<Value> {
+    let url = part.pointer("/image_url/url")?.as_str()?;
+    if let Some(rest) = url.strip_prefix("data:") {
+        let (mime_type, data) = rest.split_once(";base64,")?;
+        return Some(json!({"inlineData": {"mimeType": mime_type, "data": data}}));
+    }
+    let mime_type = match url.rsplit('.').next().map(|ext| ext.to_ascii_lowercase()) {
+        Some(ext) if ext == "png" => "image/png",
+        Some(ext) if ext == "webp" => "image/webp",
+        Some(ext) if ext == "gif" => "image/gif",
+        _ => "image/jpeg",
+    };
+    Some(json!({"fileData": {"mimeType": mime_type, "fileUri": url}}))
+}
+
+fn user_parts(content: &Value) -> Vec<Value> {
+    match content {
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| match p.get("type").and_then(|t| t.as_str()) {
+                Some("text") => Some(json!({"text": p.get("text").cloned().unwrap_or(Value::Null)})),
+                Some("image_url") => image_part(p),
+                _ => None,
+            })
+            .collect(),
+        Value::Null => Vec::new(),
+        other => vec![json!({"text": text_of(other)})],
+    }
+}
+
+/// Strip schema keywords Gemini does not accept, recursively.
+fn sanitize_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(k, _)| !UNSUPPORTED_SCHEMA_KEYS.contains(&k.as_str()))
+                .map(|(k, v)| {
+                    // `properties` maps names to schemas; its keys are not keywords
+                    let v = if k == "properties" {
+                        match v {
+                            Value::Object(props) => Value::Object(
+                                props.iter().map(|(name, s)| (name.clone(), sanitize_schema(s))).collect(),
+                            ),
+                            other => other.clone(),
+                        }
+                    } else {
+                        sanitize_schema(v)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(sanitize_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Append a content, merging it into the previous one when the role repeats
+/// (Gemini expects alternating user/model turns).
+fn push_content(contents: &mut Vec<Value>, role: &str, parts: Vec<Value>) {
+    if parts.is_empty() {
+        return;
+    }
+    let same_role = contents.last_mut().filter(|last| last["role"] == role);
+    if let Some(existing) = same_role.and_then(|last| last["parts"].as_array_mut()) {
+        existing.extend(parts);
+        return;
+    }
+    contents.push(json!({"role": role, "parts": parts}));
+}
+
+/// Convert OpenAI chat messages and parameters into a Gemini request body.
+pub fn request_from_openai(messages: &[Value], extra: &HashMap<String, Value>) -> Value {
+    let mut system = Vec::new();
+    let mut contents: Vec<Value> = Vec::new();
+    // tool_call_id -> function name, needed for functionResponse parts
+    let mut call_names: HashMap<String, String> = HashMap::new();
+
+    for msg in messages {
+        let content = msg.get("content").cloned().unwrap_or(Value::Null);
+        match msg.get("role").and_then(|r| r.as_str()).unwrap_or("user") {
+            "system" | "developer" => system.push(text_of(&content)),
+            "assistant" => {
+                let mut parts = Vec::new();
+                let text = text_of(&content);
+                if !text.is_empty() {
+                    parts.push(json!({"text": text}));
+                }
+                for call in msg.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+                    let name = call.pointer("/function/name").and_then(|n| n.as_str()).unwrap_or("");
+                    if let Some(id) = call.get("id").and_then(|i| i.as_str()) {
+                        call_names.insert(id.to_string(), name.to_string());
+                    }
+                    let args = call
+                        .pointer("/function/arguments")
+                        .and_then(|a| a.as_str())
+                        .and_then(|a| serde_json::from_str::<Value>(a).ok())
+                        .unwrap_or_else(|| json!({}));
+                    parts.push(json!({"functionCall": {"name": name, "args": args}}));
+                }
+                push_content(&mut contents, "model", parts);
+            }
+            "tool" => {
+                let id = msg.get("tool_call_id").and_then(|i| i.as_str()).unwrap_or("");
+                let name = call_names.get(id).cloned().unwrap_or_else(|| id.to_string());
+                let text = text_of(&content);
+                let response = match serde_json::from_str::<Value>(&text) {
+                    Ok(v @ Value::Object(_)) => v,
+                    Ok(v) => json!({"result": v}),
+                    Err(_) => json!({"result": text}),
+                };
+                push_content(&mut contents, "user", vec![json!({"functionResponse": {"name": name, "response": response}})]);
+            }
+            _ => push_content(&mut contents, "user", user_parts(&content)),
+        }
+    }
+
+    let mut body = Map::new();
+    body.insert("contents".to_string(), Value::Array(contents));
+    if !system.is_empty() {
+        body.insert("systemInstruction".to_string(), json!({"parts": [{"text": system.join("\n\n")}]}));
+    }
+
+    if let Some(tools) = extra.get("tools").and_then(|t| t.as_array()) {
+        let declarations: Vec<Value> = tools
+            .iter()
+            .filter_map(|t| t.get("function"))
+            .map(|f| {
+                let mut decl = json!({"name": f.get("name").cloned().unwrap_or(Value::Null)});
+                if let Some(desc) = f.get("description").filter(|d| !d.is_null()) {
+                    decl["description"] = desc.clone();
+                }
+                if let Some(params) = f.get("parameters").filter(|p| !p.is_null()) {
+                    decl["parameters"] = sanitize_schema(params);
+                }
+                decl
+            })
+            .collect();
+        if !declarations.is_empty() {
+            body.insert("tools".to_string(), json!([{"functionDeclarations": declarations}]));
+        }
+    }
+    if let Some(choice) = extra.get("tool_choice") {
+        let config = match choice {
+            Value::String(s) if s == "none" => json!({"mode": "NONE"}),
+            Value::String(s) if s == "required" => json!({"mode": "ANY"}),
+            Value::Object(_) => match choice.pointer("/function/name") {
+                Some(name) => json!({"mode": "ANY", "allowedFunctionNames": [name]}),
+                None => json!({"mode": "AUTO"}),
+            },
+            _ => json!({"mode": "AUTO"}),
+        };
+        body.insert("toolConfig".to_string(), json!({"functionCallingConfig": config}));
+    }
+
+    let mut generation = Map::new();
+    let mut copy = |from: &str, to: &str| {
+        if let Some(v) = extra.get(from).filter(|v| !v.is_null()) {
+            generation.insert(to.to_string(), v.clone());
+        }
+    };
+    copy("temperature", "temperature");
+    copy("top_p", "topP");
+    copy("top_k", "topK");
+    copy("n", "candidateCount");
+    copy("presence_penalty", "presencePenalty");
+    copy("frequency_penalty", "frequencyPenalty");
+    copy("seed", "seed");
+    copy("max_tokens", "maxOutputTokens");
+    copy("max_completion_tokens", "maxOutputTokens");
+    match extra.get("stop") {
+        Some(Value::String(s)) => {
+            generation.insert("stopSequences".to_string(), json!([s]));
+        }
+        Some(stop @ Value::Array(_)) => {
+            generation.insert("stopSequences".to_string(), stop.clone());
+        }
+        _ => {}
+    }
+    let format_kind = extra.get("response_format").and_then(|f| f.get("type")).and_then(|t| t.as_str());
+    if matches!(format_kind, Some("json_object" | "json_schema")) {
+        generation.insert("responseMimeType".to_string(), json!("application/json"));
+    }
+    if !generation.is_empty() {
+        body.insert("generationConfig".to_string(), Value::Object(generation));
+    }
+
+    Value::Object(body)
+}
+
+// ---------------------------------------------------------------------------
+// Responses: Gemini -> OpenAI chat
+// ---------------------------------------------------------------------------
+
+/// Token counts from a Gemini `usageMetadata` object.
+pub fn usage_tokens(usage: &Value) -> (Option<u64>, Option<u64>) {
+    let input = usage.get("promptTokenCount").and_then(|v| v.as_u64());
+    let candidates = usage.get("candidatesTokenCount").and_then(|v| v.as_u64());
+    let thoughts = usage.get("thoughtsTokenCount").and_then(|v| v.as_u64());
+    let output = match (candidates, thoughts) {
+        (None, None) => None,
+        (c, t) => Some(c.unwrap_or(0) + t.unwrap_or(0)),
+    };
+    (input, output)
+}
+
+/// Split a candidate's parts into text and OpenAI tool calls. Thought parts are skipped.
+fn candidate_parts(candidate: &Value, first_tool_index: usize) -> (String, Vec<Value>) {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for part in candidate.pointer("/content/parts").and_then(|p| p.as_array()).into_iter().flatten() {
+        if part.get("thought").and_then(|t| t.as_bool()).unwrap_or(false) {
+            continue;
+        }
+        if let Some(t) = part.get("text").and_then(|t| t.as_str()) {
+            text.push_str(t);
+        }
+        if let Some(call) = part.get("functionCall") {
+            tool_calls.push(json!({
+                "index": first_tool_index + tool_calls.len(),
+                "id": format!("call_{}", uuid::Uuid::new_v4().simple()),
+                "type": "function",
+                "function": {
+                    "name": call.get("name").cloned().unwrap_or(Value::Null),
+                    "arguments": call.get("args").map(|a| a.to_string()).unwrap_or_else(|| "{}".to_string()),
+                },
+            }));
+        }
+    }
+    (text, tool_calls)
+}
+
+/// Convert a buffered Gemini response into an OpenAI chat completion.
+pub fn response_to_openai(resp: &Value, model: &str) -> Value {
+    let choices: Vec<Value> = resp
+        .get("candidates")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, candidate)| {
+            let (text, mut tool_calls) = candidate_parts(candidate, 0);
+            for call in &mut tool_calls {
+                if let Some(obj) = call.as_object_mut() {
+                    obj.remove("index");
+                }
+            }
+            let finish = if !tool_calls.is_empty() {
+                "tool_calls"
+            } else {
+                finish_reason(candidate.get("finishReason").and_then(|f| f.as_str()).unwrap_or("STOP"))
+            };
+            let mut message = json!({
+                "role": "assistant",
+                "content": if text.is_empty() && !tool_calls.is_empty() { Value::Null } else { Value::String(text) },
+            });
+            if !tool_calls.is_empty() {
+                message["tool_calls"] = Value::Array(tool_calls);
+            }
+            json!({
+                "index": candidate.get("index").and_then(|v| v.as_u64()).unwrap_or(i as u64),
+                "message": message,
+                "finish_reason": finish,
+            })
+        })
+        .collect();
+
+    let (input, output) = usage_tokens(resp.get("usageMetadata").unwrap_or(&Value::Null));
+    let (input, output) = (input.unwrap_or(0), output.unwrap_or(0));
+    json!({
+        "id": resp.get("responseId").and_then(|v| v.as_str()).map(|s| s.to_string())
+            .unwrap_or_else(|| format!("chatcmpl-{}", uuid::Uuid::new_v4().simple())),
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": resp.get("modelVersion").and_then(|m| m.as_str()).unwrap_or(model),
+        "choices": choices,
+        "usage": {
+            "prompt_tokens": input,
+            "completion_tokens": output,
+            "total_tokens": input + output,
+        },
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Embeddings: OpenAI /v1/embeddings <-> batchEmbedContents
+// ---------------------------------------------------------------------------
+
+/// Convert an OpenAI embeddings `input` (a string or list of strings) into a
+/// `batchEmbedContents` body.
+pub fn embed_request_from_openai(model: &str, input: &Value, extra: &HashMap<String, Value>) -> Value {
+    let texts: Vec<&str> = match input {
+        Value::String(s) => vec![s.as_str()],
+        Value::Array(items) => items.iter().filter_map(|i| i.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    let requests: Vec<Value> = texts
+        .iter()
+        .map(|text| {
+            let mut request = json!({
+                "model": format!("models/{}", model_name(model)),
+                "content": {"parts": [{"text": text}]},
+            });
+            if let Some(dimensions) = extra.get("dimensions").filter(|d| !d.is_null()) {
+                request["outputDimensionality"] = dimensions.clone();
+            }
+            request
+        })
+        .collect();
+    json!({"requests": requests})
+}
+
+/// Convert a `batchEmbedContents` response into an OpenAI embeddings list.
+/// Gemini reports no token usage for embeddings.
+pub fn embed_response_to_openai(resp: &Value, model: &str) -> Value {
+    let data: Vec<Value> = resp
+        .get("embeddings")
+        .and_then(|e| e.as_array())
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, e)| json!({
+            "object": "embedding",
+            "index": i,
+            "embedding": e.get("values").cloned().unwrap_or_else(|| json!([])),
+        }))
+        .collect();
+    json!({
+        "object": "list",
+        "data": data,
+        "model": model,
+        "usage": {"prompt_tokens": 0, "total_tokens": 0},
+    })
+}
+
+/// Translates a Gemini `alt=sse` stream into OpenAI chunk payloads.
+pub struct ChunkTranslator {
+    id: String,
+    model: String,
+    created: i64,
+    started: bool,
+    finished: bool,
+    tool_calls: usize,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+impl ChunkTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+            model: model.to_string(),
+            created: chrono::Utc::now().timestamp(),
+            started: false,
+            finished: false,
+            tool_calls: 0,
+            input_tokens: None,
+            output_tokens: None,
+        }
+    }
+
+    /// Translate one Gemini stream payload into zero or more OpenAI chunk payloads.
+    pub fn translate(&mut self, data: &str) -> Vec<String> {
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            return Vec::new();
+        };
+        if let Some(usage) = event.get("usageMetadata") {
+            let (input, output) = usage_tokens(usage);
+            self.input_tokens = input.or(self.input_tokens);
+            self.output_tokens = output.or(self.output_tokens);
+        }
+        let Some(candidate) = event.pointer("/candidates/0") else {
+            return Vec::new();
+        };
+
+        let mut delta = Map::new();
+        if !self.started {
+            self.started = true;
+            delta.insert("role".to_string(), json!("assistant"));
+        }
+        let (text, tool_calls) = candidate_parts(candidate, self.tool_calls);
+        if !text.is_empty() {
+            delta.insert("content".to_string(), Value::String(text));
+        }
+        if !tool_calls.is_empty() {
+            self.tool_calls += tool_calls.len();
+            delta.insert("tool_calls".to_string(), Value::Array(tool_calls));
+        }
+        let finish = candidate.get("finishReason").and_then(|f| f.as_str()).map(|reason| {
+            if self.tool_calls > 0 { "tool_calls" } else { finish_reason(reason) }
+        });
+        if delta.is_empty() && finish.is_none() {
+            return Vec::new();
+        }
+        vec![self.chunk(Value::Object(delta), finish)]
+    }
+
+    /// Emit the trailing usage chunk and `[DONE]`; Gemini streams have no terminal event.
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.finished = true;
+        let prompt = self.input_tokens.unwrap_or(0);
+        let completion = self.output_tokens.unwrap_or(0);
+        vec![
+            json!({
+                "id": self.id,
+                "object": "chat.completion.chunk",
+                "created": self.created,
+                "model": self.model,
+                "choices": [],
+                "usage": {
+                    "prompt_tokens": prompt,
+                    "completion_tokens": completion,
+                    "total_tokens": prompt + completion,
+                },
+            })
+            .to_string(),
+            "[DONE]".to_string(),
+        ]
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> String {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason,
+            }],
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_from_openai() {
+        let messages = vec![
+            json!({"role": "system", "content": "Be brief."}),
+            json!({"role": "user", "content": [
+                {"type": "text", "text": "What is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+            ]}),
+            json!({"role": "assistant", "content": null, "tool_calls": [
+                {"id": "call_1", "type": "function", "function": {"name": "lookup", "arguments": "{\"q\":\"x\"}"}}
+            ]}),
+            json!({"role": "tool", "tool_call_id": "call_1", "content": "{\"answer\":42}"}),
+        ];
+        let extra = HashMap::from([
+            ("max_tokens".to_string(), json!(100)),
+            ("temperature".to_string(), json!(0.2)),
+            ("stop".to_string(), json!("END")),
+            ("tools".to_string(), json!([{"type": "function", "function": {
+                "name": "lookup",
+                "description": "Find",
+                "parameters": {"type": "object", "additionalProperties": false, "properties": {"q": {"type": "string", "title": "Q"}}}
+            }}])),
+            ("tool_choice".to_string(), json!("required")),
+        ]);
+
+        let body = request_from_openai(&messages, &extra);
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(body["contents"][0]["role"], "user");
+        assert_eq!(body["contents"][0]["parts"][1]["inlineData"], json!({"mimeType": "image/png", "data": "AAAA"}));
+        assert_eq!(body["contents"][1]["role"], "model");
+        assert_eq!(body["contents"][1]["parts"][0]["functionCall"], json!({"name": "lookup", "args": {"q": "x"}}));
+        assert_eq!(body["contents"][2]["parts"][0]["functionResponse"], json!({"name": "lookup", "response": {"answer": 42}}));
+        let params = &body["tools"][0]["functionDeclarations"][0]["parameters"];
+        assert!(params.get("additionalProperties").is_none());
+        assert_eq!(params["properties"]["q"], json!({"type": "string"}));
+        assert_eq!(body["toolConfig"]["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 100);
+        assert_eq!(body["generationConfig"]["stopSequences"], json!(["END"]));
+    }
+
+    #[test]
+    fn test_consecutive_user_turns_are_merged() {
+        let messages = vec![
+            json!({"role": "user", "content": "a"}),
+            json!({"role": "user", "content": "b"}),
+        ];
+        let body = request_from_openai(&messages, &HashMap::new());
+        assert_eq!(body["contents"].as_array().unwrap().len(), 1);
+        assert_eq!(body["contents"][0]["parts"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_response_to_openai() {
+        let resp = json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "thinking...", "thought": true},
+                    {"text": "Hello"},
+                    {"functionCall": {"name": "lookup", "args": {"q": "x"}}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 11, "candidatesTokenCount": 4, "thoughtsTokenCount": 2},
+            "modelVersion": "gemini-test"
+        });
+        let out = response_to_openai(&resp, "gemini-test");
+        let choice = &out["choices"][0];
+        assert_eq!(choice["message"]["content"], "Hello");
+        assert_eq!(choice["message"]["tool_calls"][0]["function"]["arguments"], "{\"q\":\"x\"}");
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(out["usage"]["prompt_tokens"], 11);
+        assert_eq!(out["usage"]["completion_tokens"], 6);
+    }
+
+    #[test]
+    fn test_stream_translation() {
+        let mut t = ChunkTranslator::new("gemini-test");
+        let first = t.translate(r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"He"}]}}]}"#);
+        let first: Value = serde_json::from_str(&first[0]).unwrap();
+        assert_eq!(first["choices"][0]["delta"], json!({"role": "assistant", "content": "He"}));
+
+        let last = t.translate(r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"llo"}]},"finishReason":"MAX_TOKENS"}],"usageMetadata":{"promptTokenCount":5,"candidatesTokenCount":2}}"#);
+        let last: Value = serde_json::from_str(&last[0]).unwrap();
+        assert_eq!(last["choices"][0]["finish_reason"], "length");
+
+        let tail = t.finish();
+        let usage: Value = serde_json::from_str(&tail[0]).unwrap();
+        assert_eq!(usage["usage"]["prompt_tokens"], 5);
+        assert_eq!(tail[1], "[DONE]");
+        assert!(t.finish().is_empty());
+    }
+
+    #[test]
+    fn test_embeddings_round_trip() {
+        let body = embed_request_from_openai(
+            "google/text-embedding-004",
+            &json!(["a", "b"]),
+            &HashMap::from([("dimensions".to_string(), json!(256))]),
+        );
+        assert_eq!(body["requests"][1]["model"], "models/text-embedding-004");
+        assert_eq!(body["requests"][1]["content"]["parts"][0]["text"], "b");
+        assert_eq!(body["requests"][0]["outputDimensionality"], 256);
+
+        let resp = json!({"embeddings": [{"values": [0.1, 0.2]}, {"values": [0.3, 0.4]}]});
+        let out = embed_response_to_openai(&resp, "text-embedding-004");
+        assert_eq!(out["data"][1]["index"], 1);
+        assert_eq!(out["data"][1]["embedding"], json!([0.3, 0.4]));
+    }
+
+    #[test]
+    fn test_url() {
+        let provider: Provider = serde_json::from_value(json!({
+            "id": "g", "name": "G", "provider_type": "Google", "api_key": null,
+            "endpoint": "http://localhost:9/v1beta/", "tier": "Free", "enabled": true,
+            "priority": 1, "models": []
+        }))
+        .unwrap();
+        assert_eq!(url(&provider, "google/gemini-2.5-flash", false), "http://localhost:9/v1beta/models/gemini-2.5-flash:generateContent");
+        assert_eq!(url(&provider, "gemini-2.5-flash", true), "http://localhost:9/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse");
+    }
+}
@@ -1,10 +1,29 @@
+use crate::admin::{self, Role};
+use crate::anthropic;
+use crate::balance::InFlight;
+use crate::bedrock;
+use crate::breaker::{BreakerConfig, Outcome};
+use crate::budget::{self, Verdict};
 use crate::cache;
+use crate::client::ClientPool;
+use crate::completions;
 use crate::config::{Config, Provider, ProviderType};
+use crate::discovery;
+use crate::error::{self, Attempt, ErrorAction, ErrorClass, ErrorPolicy, ProviderError, UpstreamError};
+use crate::estimate::{self, ExpectedUsage};
+use crate::gemini;
+use crate::keys::KeyLease;
+use crate::ratelimit::RateLimiter;
+use crate::responses;
+use crate::retry::{self, RetryPolicy, Step};
 use crate::router::Router;
 use crate::scorer::Scorer;
 use crate::state::{AppState, RequestLog};
+use crate::stream::{self, StreamTranslator, UpstreamStream};
+use crate::timeout;
+use crate::virtual_keys::{self, Rejection, VirtualKey};
 use axum::{
-    extract::{State, Json, Query},
+    extract::{connect_info::ConnectInfo, Extension, State, Json, Query},
     http::{StatusCode, HeaderMap},
     response::{IntoResponse, Response},
 };
@@ -12,7 +31,14 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::time::Instant;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+const OPENAI_CHAT_COMPLETIONS_URL: &str = "https://api.openai.com/v1/chat/completions";
+
+/// `api-version` used for Azure OpenAI providers that don't set one.
+const AZURE_DEFAULT_API_VERSION: &str = "2024-10-21";
+const ANTHROPIC_MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
 
 #[derive(Debug, Deserialize)]
 pub struct ChatCompletionRequest {
@@ -20,6 +46,81 @@ pub struct ChatCompletionRequest {
     pub messages: Vec<Value>,
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
+    /// Original body when the client called another API, for providers that
+    /// speak that API natively.
+    #[serde(skip)]
+    pub native_body: Option<Value>,
+    /// API the client called; responses are returned in its shape.
+    #[serde(skip)]
+    pub client_api: ClientApi,
+}
+
+/// The API a chat request arrived on.
+#[derive(Debug, Clone, Default)]
+pub enum ClientApi {
+    /// OpenAI `/v1/chat/completions`.
+    #[default]
+    Chat,
+    /// Anthropic `/v1/messages`.
+    Anthropic,
+    /// OpenAI `/v1/responses`.
+    Responses(responses::Conversation),
+    /// Legacy OpenAI `/v1/completions`.
+    Completions(completions::Options),
+}
+
+impl ClientApi {
+    /// Whether `provider_type` serves this API itself, so the original body can be sent as-is.
+    fn is_native(&self, provider_type: &ProviderType) -> bool {
+        match self {
+            Self::Chat | Self::Completions(_) => false,
+            Self::Anthropic => *provider_type == ProviderType::Anthropic,
+            Self::Responses(_) => *provider_type == ProviderType::OpenAI,
+        }
+    }
+}
+
+/// Anthropic Messages API request. Only the fields needed for validation are
+/// typed; the full body is kept for native passthrough.
+#[derive(Debug, Deserialize)]
+pub struct MessagesRequest {
+    pub model: String,
+    pub messages: Vec<Value>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// OpenAI Responses API request. The body is kept whole for native passthrough.
+#[derive(Debug, Deserialize)]
+pub struct ResponsesRequest {
+    pub model: String,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// Legacy OpenAI text completions request.
+#[derive(Debug, Deserialize)]
+pub struct CompletionsRequest {
+    pub model: String,
+    #[serde(default)]
+    pub prompt: Value,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// A successful upstream reply, either fully buffered or still streaming.
+pub(crate) enum ProviderReply {
+    Buffered(StatusCode, Vec<u8>),
+    Streaming(Box<UpstreamStream>),
+}
+
+impl ProviderReply {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Buffered(status, _) => *status,
+            Self::Streaming(upstream) => upstream.status,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -28,6 +129,15 @@ pub struct ModelListResponse {
     pub data: Vec<ModelEntry>,
 }
 
+/// OpenAI embeddings request.
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingsRequest {
+    pub model: String,
+    pub input: Value,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ModelEntry {
     pub id: String,
@@ -36,6 +146,11 @@ pub struct ModelEntry {
     pub owned_by: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub token: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LogsQuery {
     pub limit: Option<usize>,
@@ -45,12 +160,19 @@ pub struct LogsQuery {
     pub provider: Option<String>,
 }
 
-pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn list_models(State(state): State<AppState>, headers: HeaderMap) -> Response {
     let config = state.get_config().await;
+    let key = match virtual_keys::authenticate(&config.virtual_keys, &headers) {
+        Ok(key) => key,
+        Err(rejection) => return rejection.into_response(),
+    };
     let mut models = Vec::new();
 
     // Add virtual router/<profile> models
     for profile in &config.profiles {
+        if key.is_some_and(|k| k.check_access(Some(&profile.name), None).is_err()) {
+            continue;
+        }
         models.push(ModelEntry {
             id: format!("router/{}", profile.name),
             object: "model".to_string(),
@@ -61,6 +183,9 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
 
     for provider in config.providers {
         for model in provider.models {
+            if key.is_some_and(|k| k.check_access(None, Some(&model.id)).is_err()) {
+                continue;
+            }
             models.push(ModelEntry {
                 id: model.id,
                 object: "model".to_string(),
@@ -74,12 +199,203 @@ pub async fn list_models(State(state): State<AppState>) -> impl IntoResponse {
         object: "list".to_string(),
         data: models,
     })
+    .into_response()
 }
 
 pub async fn chat_completions(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(request): Json<ChatCompletionRequest>,
+) -> Response {
+    route_chat(state, headers, request).await
+}
+
+/// Anthropic Messages API endpoint. The request is normalized to the OpenAI
+/// chat shape for scoring and routing, then answered in Anthropic shape.
+pub async fn messages(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<MessagesRequest>,
+) -> Response {
+    let mut body = Value::Object(request.extra.into_iter().collect());
+    body["model"] = Value::String(request.model.clone());
+    body["messages"] = Value::Array(request.messages);
+    let (messages, extra) = anthropic::request_to_openai(&body);
+
+    let chat_request = ChatCompletionRequest {
+        model: request.model,
+        messages,
+        extra,
+        native_body: Some(body),
+        client_api: ClientApi::Anthropic,
+    };
+    route_chat(state, headers, chat_request).await
+}
+
+/// OpenAI Responses API endpoint. Forwarded as-is to providers that serve it,
+/// otherwise run through chat completions and converted back.
+///
+/// `previous_response_id` is resolved from the router's own store of earlier
+/// responses, so conversations can continue on any provider. Ids the router
+/// has not seen are left for the native provider that issued them.
+pub async fn responses(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ResponsesRequest>,
+) -> Response {
+    let mut body = Value::Object(request.extra.into_iter().collect());
+    body["model"] = Value::String(request.model.clone());
+
+    let previous_response_id = body.get("previous_response_id").and_then(|v| v.as_str()).map(str::to_string);
+    let mut history = Vec::new();
+    let mut native_body = Some(body.clone());
+    if let Some(ref id) = previous_response_id {
+        match state.get_response(id).await {
+            Some(stored) => {
+                history = stored.messages;
+                native_body = None;
+            }
+            None => tracing::info!(previous_response_id = %id, "Unknown previous response, leaving it to native providers"),
+        }
+    }
+    history.extend(responses::input_to_messages(body.get("input").unwrap_or(&Value::Null)));
+
+    let mut messages = history.clone();
+    if let Some(instructions) = body.get("instructions").and_then(|i| i.as_str()) {
+        messages.insert(0, serde_json::json!({"role": "system", "content": instructions}));
+    }
+    let conversation = responses::Conversation {
+        messages: history,
+        previous_response_id,
+        store: body.get("store").and_then(|v| v.as_bool()).unwrap_or(true),
+    };
+
+    let chat_request = ChatCompletionRequest {
+        model: request.model,
+        messages,
+        extra: responses::request_params(&body),
+        native_body,
+        client_api: ClientApi::Responses(conversation),
+    };
+    route_chat(state, headers, chat_request).await
+}
+
+/// Legacy text completions endpoint. The prompt is sent as a single user
+/// message through the chat pipeline and answered as a `text_completion`.
+pub async fn completions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<CompletionsRequest>,
+) -> Response {
+    let prompt = match completions::prompt_text(&request.prompt) {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            return error::json_error(StatusCode::BAD_REQUEST, "invalid_request_error", "invalid_prompt", e, &[]);
+        }
+    };
+    let options = completions::Options {
+        echo: request.extra.get("echo").and_then(|v| v.as_bool()).unwrap_or(false),
+        include_usage: request
+            .extra
+            .get("stream_options")
+            .and_then(|o| o.get("include_usage"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        prompt,
+    };
+
+    let chat_request = ChatCompletionRequest {
+        model: request.model,
+        messages: vec![serde_json::json!({"role": "user", "content": options.prompt})],
+        extra: completions::request_params(&request.extra),
+        native_body: None,
+        client_api: ClientApi::Completions(options),
+    };
+    route_chat(state, headers, chat_request).await
+}
+
+/// OpenAI embeddings endpoint. Routed across providers declaring the model as
+/// an embedding model, with the same cache and fallback as chat.
+pub async fn embeddings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<EmbeddingsRequest>,
+) -> Response {
+    let start = Instant::now();
+    let mut log_entry = RequestLog::new(&request.model);
+    let config = state.get_config().await;
+    let deadline = timeout::deadline(&headers, start);
+
+    let tokens = estimate::embedding_tokens(&request);
+    match authorize(&state, &config, &headers, None, Some(&request.model), tokens, deadline).await {
+        Ok(key) => log_entry.virtual_key = key.map(|k| k.id.clone()),
+        Err(rejection) => return rejection.into_response(),
+    }
+    let verdict = Verdict::check(&config.budgets, &state.spend, None, log_entry.virtual_key.as_deref());
+    if let Some(ref exceeded) = verdict.rejected {
+        return budget::exceeded(exceeded);
+    }
+
+    let cache_config = config.cache.clone().unwrap_or_default();
+    let cache_key_str = cache::cache_key(
+        &format!("embeddings:{}", request.model),
+        std::slice::from_ref(&request.input),
+        &request.extra,
+    );
+    if let Some(cached_body) = cache::get(&cache_config, &cache_key_str) {
+        tracing::info!(model = %request.model, key = %&cache_key_str[..12], "Embeddings cache hit");
+        log_entry.provider = Some("cache".to_string());
+        log_entry.status = "success".to_string();
+        log_entry.status_code = Some(200);
+        log_entry.duration_ms = start.elapsed().as_millis() as u64;
+        log_entry.cache_status = Some("hit".to_string());
+        state.add_log(log_entry).await;
+        return (StatusCode::OK, [(axum::http::header::CONTENT_TYPE, "application/json")], cached_body).into_response();
+    }
+
+    let mut candidates = Router::route_embedding_request(&config, &request.model, &state.availability().await);
+    if candidates.is_empty() {
+        log_entry.status = "no_provider".to_string();
+        log_entry.error_message = Some("No provider found for model".to_string());
+        log_entry.duration_ms = start.elapsed().as_millis() as u64;
+        state.add_log(log_entry).await;
+        return error::no_provider(&request.model);
+    }
+    candidates.retain(|p| verdict.allows(p));
+    if candidates.is_empty() {
+        return over_budget(&state, log_entry, start).await;
+    }
+
+    let dispatch = Dispatch::new(&state, &config, &headers, Forward::Embeddings(&request), deadline);
+    let mut attempts = Vec::new();
+    let tried = dispatch.try_candidates(&candidates, &request.model, tokens, &mut log_entry, &mut attempts).await;
+    let (provider, reply, _in_flight) = match tried {
+        Tried::Served(provider, reply, in_flight) => (provider, reply, in_flight),
+        Tried::Aborted(failure) => return abort(&state, log_entry, start, failure, &attempts).await,
+        Tried::DeadlineExceeded => return deadline_exceeded(&state, log_entry, start, &attempts).await,
+        Tried::Exhausted if timeout::expired(deadline) => {
+            return deadline_exceeded(&state, log_entry, start, &attempts).await;
+        }
+        Tried::Exhausted => return all_failed(&state, log_entry, start, &request.model, &attempts).await,
+    };
+    let ProviderReply::Buffered(status, body) = reply else {
+        unreachable!("embeddings are never streamed")
+    };
+    log_entry.provider = Some(provider.name.clone());
+    log_entry.status = "success".to_string();
+    log_entry.status_code = Some(status.as_u16());
+    log_entry.cache_status = Some("miss".to_string());
+    log_entry.duration_ms = start.elapsed().as_millis() as u64;
+    cache::put(&cache_config, &cache_key_str, &request.model, &body);
+    state.add_log(log_entry).await;
+    (status, [(axum::http::header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+/// Shared scoring, routing, caching and fallback pipeline behind the chat endpoints.
+async fn route_chat(
+    state: AppState,
+    headers: HeaderMap,
+    request: ChatCompletionRequest,
 ) -> Response {
     let start = Instant::now();
     let mut log_entry = RequestLog::new(&request.model);
@@ -93,6 +409,24 @@ pub async fn chat_completions(
         tracing::info!(profile = %profile_name, "Per-request profile override via router/ model name");
     }
 
+    // --- Client authentication: virtual key and its policies ---
+    let deadline = timeout::deadline(&headers, start);
+    let profile = profile_override.unwrap_or(&config.active_profile);
+    let requested_model = profile_override.is_none().then_some(request.model.as_str());
+    let tokens = ExpectedUsage::estimate(&request, None).total_tokens();
+    let key = match authorize(&state, &config, &headers, Some(profile), requested_model, tokens, deadline).await {
+        Ok(key) => key,
+        Err(rejection) => return rejection.into_response(),
+    };
+    log_entry.virtual_key = key.map(|k| k.id.clone());
+    log_entry.profile = Some(profile.to_string());
+
+    // --- Spend budgets ---
+    let verdict = Verdict::check(&config.budgets, &state.spend, Some(profile), log_entry.virtual_key.as_deref());
+    if let Some(ref exceeded) = verdict.rejected {
+        return budget::exceeded(exceeded);
+    }
+
     // --- Session persistence: extract session ID ---
     let session_config = config.session.clone().unwrap_or_default();
     let session_id = if session_config.enabled {
@@ -101,37 +435,66 @@ pub async fn chat_completions(
         None
     };
     log_entry.session_id = session_id.clone();
+    let dispatch = Dispatch::new(&state, &config, &headers, Forward::Chat(&request), deadline);
+    let mut attempts = Vec::new();
 
     // --- Session persistence: check for pinned session ---
-    if let Some(ref sid) = session_id {
-        if let Some(pinned) = state.get_session(sid, session_config.ttl_seconds).await {
-            // Verify the pinned provider still exists and is enabled
-            if let Some(provider) = config.providers.iter().find(|p| p.id == pinned.provider_id && p.enabled) {
-                state.touch_session(sid).await;
-                log_entry.session_pinned = Some(true);
-                log_entry.effective_model = Some(pinned.model_id.clone());
-                tracing::info!(
-                    session_id = %sid,
-                    pinned_model = %pinned.model_id,
-                    pinned_provider = %pinned.provider_id,
-                    "Session pinned - using cached provider+model"
-                );
-
-                // Forward directly to the pinned provider
-                let client = reqwest::Client::new();
-                if let Some((status, final_body)) = forward_to_provider(
-                    &client, &headers, &request, provider, &pinned.model_id, &mut log_entry,
-                ).await {
+    let pinned = match session_id {
+        Some(ref sid) => state.get_session(sid, session_config.ttl_seconds).await.map(|pinned| (sid, pinned)),
+        None => None,
+    };
+    if let Some((sid, pinned)) = pinned {
+        // Verify the pinned provider still exists, is enabled and within
+        // budget, and the client's key may use the pinned model
+        let model_allowed = key.is_none_or(|k| k.check_model(&pinned.model_id).is_ok());
+        if let Some(provider) = config.providers.iter().find(|p| {
+            p.id == pinned.provider_id && p.enabled && verdict.allows(p) && model_allowed
+        }) {
+            state.touch_session(sid).await;
+            log_entry.session_pinned = Some(true);
+            log_entry.effective_model = Some(pinned.model_id.clone());
+            tracing::info!(
+                session_id = %sid,
+                pinned_model = %pinned.model_id,
+                pinned_provider = %pinned.provider_id,
+                "Session pinned - using cached provider+model"
+            );
+
+            // Forward directly to the pinned provider
+            let usage = ExpectedUsage::estimate(&request, state.average_output_tokens(None).await);
+            let tried = dispatch.try_candidates(
+                std::slice::from_ref(provider), &pinned.model_id, usage.total_tokens(), &mut log_entry, &mut attempts,
+            ).await;
+            let reply = match tried {
+                Tried::Served(_, reply, in_flight) => Some((reply, in_flight)),
+                Tried::Aborted(failure) => return abort(&state, log_entry, start, failure, &attempts).await,
+                Tried::DeadlineExceeded => return deadline_exceeded(&state, log_entry, start, &attempts).await,
+                Tried::Exhausted => None,
+            };
+            match reply {
+                Some((reply, in_flight)) => {
                     log_entry.provider = Some(provider.name.clone());
                     log_entry.status = "success".to_string();
-                    log_entry.status_code = Some(status.as_u16());
-                    log_entry.duration_ms = start.elapsed().as_millis() as u64;
                     log_entry.cache_status = Some("skip".to_string());
-                    state.add_log(log_entry).await;
-                    return (status, final_body).into_response();
+                    log_entry.expected_cost = usage.cost(provider, &pinned.model_id);
+                    match reply {
+                        ProviderReply::Buffered(status, final_body) => {
+                            log_entry.status_code = Some(status.as_u16());
+                            log_entry.duration_ms = start.elapsed().as_millis() as u64;
+                            remember_response(&state, &request, &final_body).await;
+                            state.add_log(log_entry).await;
+                            return (status, final_body).into_response();
+                        }
+                        ProviderReply::Streaming(upstream) => {
+                            log_entry.status_code = Some(upstream.status.as_u16());
+                            return stream::relay(
+                                state, log_entry, start, *upstream, provider.clone(), pinned.model_id, in_flight,
+                            );
+                        }
+                    }
                 }
                 // Pinned provider failed — fall through to normal routing
-                tracing::warn!(session_id = %sid, "Pinned session provider failed, falling through");
+                None => tracing::warn!(session_id = %sid, "Pinned session provider failed, falling through"),
             }
         }
     }
@@ -139,19 +502,25 @@ pub async fn chat_completions(
     // Check cache before making any upstream requests (skip for streaming requests)
     let cache_config = config.cache.clone().unwrap_or_default();
     let is_streaming = request.extra.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
-    let cache_key_str = cache::cache_key(&request.model, &request.messages, &request.extra);
-
-    if !is_streaming {
-        if let Some(cached_body) = cache::get(&cache_config, &cache_key_str) {
-            tracing::info!(model = %request.model, key = %&cache_key_str[..12], "Cache hit");
-            log_entry.provider = Some("cache".to_string());
-            log_entry.status = "success".to_string();
-            log_entry.status_code = Some(200);
-            log_entry.duration_ms = start.elapsed().as_millis() as u64;
-            log_entry.cache_status = Some("hit".to_string());
-            state.add_log(log_entry).await;
-            return (StatusCode::OK, cached_body).into_response();
-        }
+    // Responses are cached per client API, since each has its own shape
+    let cache_namespace = match request.client_api {
+        ClientApi::Chat => request.model.clone(),
+        ClientApi::Anthropic => format!("anthropic:{}", request.model),
+        ClientApi::Responses(_) => format!("responses:{}", request.model),
+        ClientApi::Completions(ref options) => format!("completions:{}:echo={}", request.model, options.echo),
+    };
+    let cache_key_str = cache::cache_key(&cache_namespace, &request.messages, &request.extra);
+
+    let cached = if is_streaming { None } else { cache::get(&cache_config, &cache_key_str) };
+    if let Some(cached_body) = cached {
+        tracing::info!(model = %request.model, key = %&cache_key_str[..12], "Cache hit");
+        log_entry.provider = Some("cache".to_string());
+        log_entry.status = "success".to_string();
+        log_entry.status_code = Some(200);
+        log_entry.duration_ms = start.elapsed().as_millis() as u64;
+        log_entry.cache_status = Some("hit".to_string());
+        state.add_log(log_entry).await;
+        return (StatusCode::OK, cached_body).into_response();
     }
 
     // --- Tool detection (before scoring) ---
@@ -195,8 +564,19 @@ pub async fn chat_completions(
         );
     }
 
+    // --- Expected usage, for ordering candidates by total cost ---
+    let usage = ExpectedUsage::estimate(&request, state.average_output_tokens(log_entry.complexity_tier.as_deref()).await);
+    tracing::debug!(
+        prompt_tokens = usage.prompt_tokens,
+        output_tokens = usage.output_tokens,
+        output_source = %usage.output_source,
+        "Estimated request usage"
+    );
+
     // --- Route with agentic flag ---
-    let candidates = Router::route_request_with_profile(&config, &request.model, complexity, profile_override, is_agentic);
+    let mut candidates = Router::route_request_with_profile(
+        &config, &request.model, complexity, profile_override, is_agentic, &state.availability().await, Some(&usage),
+    );
     let effective_model = Router::resolve_model_id_with_profile(&config, &request.model, complexity, profile_override, is_agentic).to_string();
 
     if effective_model != request.model {
@@ -209,75 +589,383 @@ pub async fn chat_completions(
         );
     }
 
+    // The key must be allowed to use the model routing settled on, not just
+    // the one the client named
+    if let Some((key, Err(rejection))) = key.map(|k| (k, k.check_model(&effective_model))) {
+        tracing::warn!(key = %key.id, rejection = ?rejection, "Client request rejected");
+        return rejection.into_response();
+    }
+
     if candidates.is_empty() {
         log_entry.status = "no_provider".to_string();
         log_entry.error_message = Some("No provider found for model".to_string());
         log_entry.duration_ms = start.elapsed().as_millis() as u64;
         state.add_log(log_entry).await;
-        return (StatusCode::BAD_REQUEST, "No provider found for model").into_response();
+        return error::no_provider(&request.model);
+    }
+    candidates.retain(|p| verdict.allows(p));
+    if candidates.is_empty() {
+        return over_budget(&state, log_entry, start).await;
     }
-
-    let client = reqwest::Client::new();
 
     // Try each candidate
-    for provider in &candidates {
-        log_entry.providers_tried.push(provider.name.clone());
-
-        if let Some((status, final_body)) = forward_to_provider(
-            &client, &headers, &request, provider, &effective_model, &mut log_entry,
-        ).await {
-            log_entry.provider = Some(provider.name.clone());
-            log_entry.status = "success".to_string();
+    let tried = dispatch.try_candidates(&candidates, &effective_model, usage.total_tokens(), &mut log_entry, &mut attempts).await;
+    let (provider, reply, in_flight) = match tried {
+        Tried::Served(provider, reply, in_flight) => (provider, reply, in_flight),
+        Tried::Aborted(failure) => return abort(&state, log_entry, start, failure, &attempts).await,
+        Tried::DeadlineExceeded => return deadline_exceeded(&state, log_entry, start, &attempts).await,
+        Tried::Exhausted if timeout::expired(deadline) => {
+            return deadline_exceeded(&state, log_entry, start, &attempts).await;
+        }
+        Tried::Exhausted => return all_failed(&state, log_entry, start, &request.model, &attempts).await,
+    };
+    log_entry.provider = Some(provider.name.clone());
+    log_entry.status = "success".to_string();
+    log_entry.expected_cost = usage.cost(provider, &effective_model);
+    log_entry.cache_status = Some(if is_streaming { "skip".to_string() } else { "miss".to_string() });
+
+    // Record session pin on success
+    if let Some(ref sid) = session_id {
+        state.set_session(
+            sid.clone(),
+            provider.id.clone(),
+            effective_model.clone(),
+        ).await;
+    }
+
+    match reply {
+        ProviderReply::Buffered(status, final_body) => {
             log_entry.status_code = Some(status.as_u16());
             log_entry.duration_ms = start.elapsed().as_millis() as u64;
-            log_entry.cache_status = Some(if is_streaming { "skip".to_string() } else { "miss".to_string() });
 
             // Store in cache (skip for streaming requests)
             if !is_streaming {
                 cache::put(&cache_config, &cache_key_str, &request.model, &final_body);
             }
 
-            // Record session pin on success
-            if let Some(ref sid) = session_id {
-                if session_config.enabled {
-                    state.set_session(
-                        sid.clone(),
-                        provider.id.clone(),
-                        effective_model.clone(),
-                    ).await;
+            remember_response(&state, &request, &final_body).await;
+            state.add_log(log_entry).await;
+            (status, final_body).into_response()
+        }
+        ProviderReply::Streaming(upstream) => {
+            log_entry.status_code = Some(upstream.status.as_u16());
+            stream::relay(state, log_entry, start, *upstream, provider.clone(), effective_model, in_flight)
+        }
+    }
+}
+
+/// Authenticate the client's virtual key and check it may make a request
+/// through `profile` for `model`: access, budget, then rate limit, waiting up
+/// to the key's `max_wait_ms`. Returns the key, or `None` when no virtual
+/// keys are configured.
+async fn authorize<'a>(
+    state: &AppState,
+    config: &'a Config,
+    headers: &HeaderMap,
+    profile: Option<&str>,
+    model: Option<&str>,
+    tokens: u64,
+    deadline: Option<Instant>,
+) -> Result<Option<&'a VirtualKey>, Rejection> {
+    let key = virtual_keys::authenticate(&config.virtual_keys, headers)
+        .inspect_err(|_| tracing::warn!("Request without a valid API key rejected"))?;
+    let Some(key) = key else { return Ok(None) };
+    let max_wait = Duration::from_millis(key.rate_limit.as_ref().map_or(0, |l| l.max_wait_ms));
+    let checked = async {
+        key.check_access(profile, model)?;
+        let mut waited = Duration::ZERO;
+        loop {
+            match state.virtual_keys.check(key, &state.spend, tokens) {
+                Err(Rejection::RateLimited(wait)) if waited + wait <= max_wait && timeout::fits(deadline, wait) => {
+                    tokio::time::sleep(wait).await;
+                    waited += wait;
                 }
+                result => return result,
             }
+        }
+    }
+    .await;
+    match checked {
+        Ok(()) => Ok(Some(key)),
+        Err(rejection) => {
+            tracing::warn!(key = %key.id, rejection = ?rejection, "Client request rejected");
+            Err(rejection)
+        }
+    }
+}
 
-            state.add_log(log_entry).await;
-            return (status, final_body).into_response();
+/// The request sent to each candidate provider.
+#[derive(Clone, Copy)]
+enum Forward<'a> {
+    Chat(&'a ChatCompletionRequest),
+    Embeddings(&'a EmbeddingsRequest),
+}
+
+/// How trying a list of candidates ended.
+enum Tried<'a> {
+    /// A provider answered. The in-flight guard should live until the reply
+    /// has been relayed.
+    Served(&'a Provider, ProviderReply, InFlight),
+    /// Every candidate failed or was skipped.
+    Exhausted,
+    /// A failure whose class says not to fall back.
+    Aborted(ProviderError),
+    DeadlineExceeded,
+}
+
+/// What every attempt at one client request shares.
+struct Dispatch<'a> {
+    state: &'a AppState,
+    headers: &'a HeaderMap,
+    forward: Forward<'a>,
+    policy: ErrorPolicy,
+    breaker_config: BreakerConfig,
+    deadline: Option<Instant>,
+}
+
+impl<'a> Dispatch<'a> {
+    fn new(state: &'a AppState, config: &Config, headers: &'a HeaderMap, forward: Forward<'a>, deadline: Option<Instant>) -> Self {
+        Self {
+            state,
+            headers,
+            forward,
+            policy: config.error_policy.clone().unwrap_or_default(),
+            breaker_config: config.circuit_breaker.clone().unwrap_or_default(),
+            deadline,
+        }
+    }
+
+    /// Try `candidates` in order for `model`: skip open circuits, clear rate
+    /// limits and take a pooled key, then send, retrying each provider as its
+    /// retry policy says before falling through to the next. Failed attempts
+    /// are recorded in `log_entry` and `attempts`.
+    async fn try_candidates<'p>(
+        &self,
+        candidates: &'p [Provider],
+        model: &str,
+        tokens: u64,
+        log_entry: &mut RequestLog,
+        attempts: &mut Vec<Attempt>,
+    ) -> Tried<'p> {
+        let state = self.state;
+        for provider in candidates {
+            if !state.breakers.allow(&provider.id, model, &self.breaker_config) {
+                attempts.push(circuit_open(provider));
+                continue;
+            }
+            let retry = RetryPolicy::for_provider(provider, &self.policy);
+            let in_flight = state.load.start(&provider.id);
+            let mut attempt = 1;
+            let mut delay = Duration::ZERO;
+            loop {
+                if timeout::expired(self.deadline) {
+                    return Tried::DeadlineExceeded;
+                }
+                let lease = match admit(state, provider, model, tokens, self.deadline).await {
+                    Ok(lease) => lease,
+                    Err(failure) => {
+                        // Nothing was sent, so give back the probe a half-open
+                        // circuit may have handed this request
+                        state.breakers.record(&provider.id, model, &self.breaker_config, Outcome::Unknown);
+                        log_entry.record_failure(&failure.attempt, delay);
+                        attempts.push(failure.attempt);
+                        break;
+                    }
+                };
+                log_entry.providers_tried.push(provider.name.clone());
+                let keyed = KeyLease::apply(lease.as_ref(), provider);
+                match self.send(&keyed, model, log_entry).await {
+                    Ok(reply) => {
+                        log_entry.record_success(&provider.name, delay, reply.status().as_u16());
+                        log_entry.provider_id = Some(provider.id.clone());
+                        log_entry.api_key_label = lease.map(|l| l.label);
+                        state.breakers.record(&provider.id, model, &self.breaker_config, Outcome::Healthy);
+                        return Tried::Served(provider, reply, in_flight);
+                    }
+                    Err(failure) => {
+                        log_entry.record_failure(&failure.attempt, delay);
+                        let key_failed = state.keys.cool_down(provider, lease.as_ref(), &failure);
+                        if !key_failed {
+                            record_failure(state, provider, model, &self.breaker_config, failure.attempt.class);
+                        }
+                        attempts.push(failure.attempt.clone());
+                        match next_step(&retry, &self.policy, provider, &failure, attempt, key_failed) {
+                            Step::Retry(wait) if timeout::fits(self.deadline, wait) => {
+                                retry_wait(&failure.attempt, wait).await;
+                                attempt += 1;
+                                delay = wait;
+                            }
+                            Step::Retry(_) | Step::Fallthrough => break,
+                            Step::Abort => return Tried::Aborted(failure),
+                        }
+                    }
+                }
+            }
+        }
+        Tried::Exhausted
+    }
+
+    async fn send(&self, provider: &Provider, model: &str, log_entry: &mut RequestLog) -> Result<ProviderReply, ProviderError> {
+        let clients = &self.state.clients;
+        match self.forward {
+            Forward::Chat(request) => {
+                forward_to_provider(clients, self.headers, request, provider, model, self.deadline, log_entry).await
+            }
+            Forward::Embeddings(request) => {
+                let (status, body) = forward_embeddings(clients, self.headers, request, provider, self.deadline, log_entry).await?;
+                Ok(ProviderReply::Buffered(status, body))
+            }
+        }
+    }
+}
+
+/// Clear an attempt with the provider's rate limits, waiting for capacity up
+/// to their `max_wait_ms` and the deadline, then take a pooled key for it.
+async fn admit(
+    state: &AppState,
+    provider: &Provider,
+    model: &str,
+    tokens: u64,
+    deadline: Option<Instant>,
+) -> Result<Option<KeyLease>, ProviderError> {
+    let max_wait = RateLimiter::max_wait(provider, model);
+    let mut waited = Duration::ZERO;
+    loop {
+        match state.limits.try_take(provider, model, tokens) {
+            Ok(()) => break,
+            Err(wait) if waited + wait <= max_wait && timeout::fits(deadline, wait) => {
+                tracing::info!(
+                    provider = %provider.name,
+                    model = %model,
+                    wait_ms = wait.as_millis() as u64,
+                    "Waiting for rate limit capacity"
+                );
+                tokio::time::sleep(wait).await;
+                waited += wait;
+            }
+            Err(wait) => {
+                tracing::info!(provider = %provider.name, model = %model, "Rate limit reached, skipping provider");
+                return Err(Attempt::failed(
+                    &provider.name,
+                    ErrorClass::RateLimited,
+                    format!("rate limit reached, capacity in {}ms", wait.as_millis()),
+                )
+                .into());
+            }
         }
     }
+    state.keys.acquire(provider, tokens)
+}
+
+/// What follows a failed attempt. A failure that put the key on cooldown is
+/// retried right away with another key, unless the error policy aborts on it;
+/// when no key is left the next attempt falls through.
+fn next_step(
+    retry: &RetryPolicy,
+    policy: &ErrorPolicy,
+    provider: &Provider,
+    failure: &ProviderError,
+    attempt: u32,
+    key_failed: bool,
+) -> Step {
+    let rotations_left = attempt < retry.max_attempts + provider.api_keys.len() as u32;
+    if key_failed && rotations_left && policy.action(failure.attempt.class) != ErrorAction::Abort {
+        return Step::Retry(Duration::ZERO);
+    }
+    retry.next_step(policy, failure, attempt)
+}
+
+/// Sleep before retrying a provider.
+async fn retry_wait(failure: &Attempt, wait: Duration) {
+    tracing::info!(
+        provider = %failure.provider,
+        class = failure.class.as_str(),
+        wait_ms = wait.as_millis() as u64,
+        "Retrying provider"
+    );
+    tokio::time::sleep(wait).await;
+}
+
+/// Log a failure whose class says not to fall back, and answer with it.
+async fn abort(
+    state: &AppState,
+    mut log_entry: RequestLog,
+    start: Instant,
+    failure: ProviderError,
+    attempts: &[Attempt],
+) -> Response {
+    tracing::info!(
+        provider = %failure.attempt.provider,
+        class = failure.attempt.class.as_str(),
+        "Provider failure aborts the request, not falling back"
+    );
+    log_entry.provider = Some(failure.attempt.provider.clone());
+    log_entry.status = "error".to_string();
+    log_entry.error_class = Some(failure.attempt.class);
+    log_entry.error_message = Some(failure.attempt.to_string());
+    let response = error::aborted(failure, attempts);
+    log_entry.status_code = Some(response.status().as_u16());
+    log_entry.duration_ms = start.elapsed().as_millis() as u64;
+    state.add_log(log_entry).await;
+    response
+}
+
+/// Log and answer a request after every candidate failed.
+async fn all_failed(state: &AppState, mut log_entry: RequestLog, start: Instant, model: &str, attempts: &[Attempt]) -> Response {
+    let response = error::all_failed(model, attempts);
+    log_entry.status = "error".to_string();
+    log_entry.error_class = attempts.last().map(|a| a.class);
+    log_entry.status_code = Some(response.status().as_u16());
+    log_entry.error_message = Some(format!("All providers failed: {}", error::summary(attempts)));
+    log_entry.duration_ms = start.elapsed().as_millis() as u64;
+    state.add_log(log_entry).await;
+    response
+}
+
+/// Log and refuse a request whose candidates are all over budget.
+async fn over_budget(state: &AppState, mut log_entry: RequestLog, start: Instant) -> Response {
+    tracing::warn!(model = %log_entry.model, "No provider within budget");
+    let response = budget::no_provider(&log_entry.model);
+    log_entry.status = "budget_exceeded".to_string();
+    log_entry.status_code = Some(response.status().as_u16());
+    log_entry.error_message = Some("No provider within budget".to_string());
+    log_entry.duration_ms = start.elapsed().as_millis() as u64;
+    state.add_log(log_entry).await;
+    response
+}
 
+/// Log and answer a request whose client deadline passed.
+async fn deadline_exceeded(state: &AppState, mut log_entry: RequestLog, start: Instant, attempts: &[Attempt]) -> Response {
+    let response = error::deadline_exceeded(attempts);
     log_entry.status = "error".to_string();
-    log_entry.error_message = Some("All providers failed".to_string());
+    log_entry.error_class = Some(ErrorClass::Timeout);
+    log_entry.status_code = Some(response.status().as_u16());
+    log_entry.error_message = Some(match attempts {
+        [] => "Request deadline exceeded".to_string(),
+        _ => format!("Request deadline exceeded: {}", error::summary(attempts)),
+    });
     log_entry.duration_ms = start.elapsed().as_millis() as u64;
     state.add_log(log_entry).await;
+    response
+}
 
-    (StatusCode::SERVICE_UNAVAILABLE, "All providers failed").into_response()
+/// Store a buffered Responses API result so it can be continued later.
+async fn remember_response(state: &AppState, request: &ChatCompletionRequest, body: &[u8]) {
+    let ClientApi::Responses(conversation) = &request.client_api else { return };
+    if let Ok(response) = serde_json::from_slice::<Value>(body) {
+        responses::remember(state, conversation, &response).await;
+    }
 }
 
 /// Extract a session ID from the request using a priority chain.
 fn extract_session_id(headers: &HeaderMap, request: &ChatCompletionRequest) -> Option<String> {
     // 1. Custom header
-    if let Some(val) = headers.get("x-session-id") {
-        if let Ok(s) = val.to_str() {
-            if !s.is_empty() {
-                return Some(s.to_string());
-            }
-        }
+    if let Some(s) = headers.get("x-session-id").and_then(|v| v.to_str().ok()).filter(|s| !s.is_empty()) {
+        return Some(s.to_string());
     }
     // 2. Request body field
-    if let Some(val) = request.extra.get("conversation_id") {
-        if let Some(s) = val.as_str() {
-            if !s.is_empty() {
-                return Some(s.to_string());
-            }
-        }
+    if let Some(s) = request.extra.get("conversation_id").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        return Some(s.to_string());
     }
     // 3. Fingerprint from system message + first user message
     let mut parts = String::new();
@@ -308,58 +996,224 @@ fn has_tools(request: &ChatCompletionRequest) -> bool {
         .unwrap_or(false)
 }
 
-/// Forward a request to a single provider. Returns Some((StatusCode, body)) on success.
-async fn forward_to_provider(
-    client: &reqwest::Client,
-    headers: &HeaderMap,
-    request: &ChatCompletionRequest,
-    provider: &Provider,
-    effective_model: &str,
-    log_entry: &mut RequestLog,
-) -> Option<(StatusCode, Vec<u8>)> {
-    let url = provider.endpoint.clone().unwrap_or_else(|| "https://api.openai.com/v1/chat/completions".to_string());
-    let api_key = provider.api_key.clone().unwrap_or_default();
-    let is_anthropic = provider.provider_type == ProviderType::Anthropic;
+/// URL of an OpenAI-style API `path` ("chat/completions", "embeddings", ...) on
+/// an OpenAI-compatible provider. A configured `endpoint` is the chat
+/// completions URL; other paths are resolved next to it.
+fn openai_api_url(provider: &Provider, model_id: &str, path: &str) -> String {
+    match provider.provider_type {
+        ProviderType::Ollama => format!("{}/v1/{}", discovery::base_url(provider), path),
+        ProviderType::AzureOpenAI => {
+            let base = provider.endpoint.as_deref().unwrap_or_default().trim_end_matches('/');
+            let deployment = provider
+                .models
+                .iter()
+                .find(|m| m.id == model_id)
+                .and_then(|m| m.deployment.as_deref())
+                .unwrap_or(model_id);
+            let api_version = provider.api_version.as_deref().unwrap_or(AZURE_DEFAULT_API_VERSION);
+            format!("{}/openai/deployments/{}/{}?api-version={}", base, deployment, path, api_version)
+        }
+        _ => {
+            let endpoint = provider.endpoint.as_deref().unwrap_or(OPENAI_CHAT_COMPLETIONS_URL);
+            if path == "chat/completions" {
+                return endpoint.to_string();
+            }
+            let base = endpoint.trim_end_matches('/');
+            let base = base.strip_suffix("/chat/completions").unwrap_or(base);
+            format!("{}/{}", base, path)
+        }
+    }
+}
+
+/// Feed a failed attempt to the circuit breaker and, when the provider is at
+/// fault, to the load balancer's error counts.
+fn record_failure(state: &AppState, provider: &Provider, model: &str, breaker_config: &BreakerConfig, class: ErrorClass) {
+    let outcome = Outcome::from_class(class);
+    state.breakers.record(&provider.id, model, breaker_config, outcome);
+    if outcome == Outcome::Failed {
+        state.load.record_error(&provider.id);
+    }
+}
 
-    // Build headers based on provider type
+/// Note a provider skipped because its circuit is open.
+fn circuit_open(provider: &Provider) -> Attempt {
+    tracing::info!(provider = %provider.name, "Circuit open, skipping provider");
+    Attempt::failed(&provider.name, ErrorClass::Other, "circuit open")
+}
+
+/// The shared client for a provider's connection settings.
+fn pooled_client(clients: &ClientPool, provider: &Provider) -> Result<reqwest::Client, ProviderError> {
+    clients.get(provider).map_err(|e| {
+        tracing::warn!("Provider {} cannot be reached: {}", provider.name, e);
+        Attempt::failed(&provider.name, ErrorClass::Other, e).into()
+    })
+}
+
+/// The error for a provider that sent nothing before the first-byte timeout.
+fn first_byte_timeout(provider: &Provider, limit: Duration) -> ProviderError {
+    tracing::warn!("Provider {} sent nothing within {}ms", provider.name, limit.as_millis());
+    Attempt::failed(&provider.name, ErrorClass::Timeout, format!("no response within {}ms", limit.as_millis())).into()
+}
+
+/// URL of a provider's model listing, for health checks. None for Bedrock,
+/// whose listing is not on the runtime endpoint.
+pub(crate) fn models_url(provider: &Provider) -> Option<String> {
+    match provider.provider_type {
+        ProviderType::Bedrock => None,
+        ProviderType::Google => {
+            let base = provider.endpoint.as_deref().unwrap_or(gemini::DEFAULT_BASE_URL).trim_end_matches('/');
+            Some(format!("{}/models", base))
+        }
+        ProviderType::Anthropic => {
+            let endpoint = provider.endpoint.as_deref().unwrap_or(ANTHROPIC_MESSAGES_URL).trim_end_matches('/');
+            let base = endpoint.strip_suffix("/messages").unwrap_or(endpoint);
+            Some(format!("{}/models", base))
+        }
+        ProviderType::AzureOpenAI => {
+            let base = provider.endpoint.as_deref().unwrap_or_default().trim_end_matches('/');
+            let api_version = provider.api_version.as_deref().unwrap_or(AZURE_DEFAULT_API_VERSION);
+            Some(format!("{}/openai/models?api-version={}", base, api_version))
+        }
+        _ => Some(openai_api_url(provider, "", "models")),
+    }
+}
+
+/// Copy the client's headers and swap in the provider's own authentication.
+/// Bedrock requests carry no key here; they are signed once the body is known.
+pub(crate) fn provider_headers(headers: &HeaderMap, provider: &Provider) -> HeaderMap {
+    let api_key = provider.api_key.clone().unwrap_or_default();
     let mut forward_headers = headers.clone();
     forward_headers.remove("host");
     forward_headers.remove("content-length");
     // Let reqwest handle content encoding (gzip decompression) transparently
     forward_headers.remove("accept-encoding");
+    forward_headers.remove(timeout::DEADLINE_HEADER);
 
-    if is_anthropic {
+    if provider.provider_type == ProviderType::Anthropic {
         forward_headers.remove("authorization");
         forward_headers.insert("x-api-key", api_key.parse().unwrap());
-        forward_headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
-        forward_headers.insert("content-type", "application/json".parse().unwrap());
-    } else {
-        forward_headers.insert("Authorization", format!("Bearer {}", api_key).parse().unwrap());
+        if !forward_headers.contains_key("anthropic-version") {
+            forward_headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+        }
+        return forward_headers;
+    }
+
+    forward_headers.remove("x-api-key");
+    forward_headers.remove("anthropic-version");
+    forward_headers.remove("anthropic-beta");
+    match provider.provider_type {
+        ProviderType::Google => {
+            forward_headers.remove("authorization");
+            forward_headers.insert("x-goog-api-key", api_key.parse().unwrap());
+        }
+        ProviderType::AzureOpenAI => {
+            forward_headers.remove("authorization");
+            forward_headers.insert("api-key", api_key.parse().unwrap());
+        }
+        ProviderType::Bedrock => {
+            forward_headers.remove("authorization");
+        }
+        _ => {
+            forward_headers.insert("Authorization", format!("Bearer {}", api_key).parse().unwrap());
+        }
     }
+    forward_headers
+}
+
+/// Turn an upstream error response into a classified provider error, keeping
+/// the reply so it can be passed back if the request is aborted.
+async fn upstream_error(provider: &Provider, response: reqwest::Response) -> ProviderError {
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let retry_after = retry::retry_after(status.as_u16(), response.headers());
+    let body = response.bytes().await.unwrap_or_default();
+    let text = String::from_utf8_lossy(&body);
+    tracing::warn!("Provider {} failed: {} - {}", provider.name, status, text);
+
+    ProviderError {
+        attempt: Attempt::upstream(&provider.name, status.as_u16(), &text),
+        response: Some(Box::new(UpstreamError {
+            status: StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY),
+            content_type,
+            body,
+        })),
+        retry_after,
+    }
+}
+
+/// Forward a request to a single provider. Fails if the provider errored before
+/// sending any response body, so the caller can try the next candidate.
+pub(crate) async fn forward_to_provider(
+    clients: &ClientPool,
+    headers: &HeaderMap,
+    request: &ChatCompletionRequest,
+    provider: &Provider,
+    effective_model: &str,
+    deadline: Option<Instant>,
+    log_entry: &mut RequestLog,
+) -> Result<ProviderReply, ProviderError> {
+    let is_anthropic = provider.provider_type == ProviderType::Anthropic;
+    let is_google = provider.provider_type == ProviderType::Google;
+    let is_bedrock = provider.provider_type == ProviderType::Bedrock;
+    let native_body = request.native_body.as_ref().filter(|_| request.client_api.is_native(&provider.provider_type));
+    let is_streaming = request.extra.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
+    if let ClientApi::Responses(conversation) = &request.client_api {
+        // A previous_response_id the router could not resolve only means
+        // something to the provider that issued it
+        if native_body.is_none() && request.native_body.is_some() && conversation.previous_response_id.is_some() {
+            tracing::info!("Provider {} cannot continue an unknown previous response, skipping", provider.name);
+            return Err(Attempt::failed(
+                &provider.name,
+                ErrorClass::Other,
+                "previous_response_id is unknown to this provider",
+            ).into());
+        }
+    }
+    let url = if let (ClientApi::Responses(_), Some(_)) = (&request.client_api, native_body) {
+        openai_api_url(provider, effective_model, "responses")
+    } else if is_google {
+        gemini::url(provider, effective_model, is_streaming)
+    } else if is_bedrock {
+        let region = provider.aws.as_ref().map(|aws| aws.region.as_str()).unwrap_or("us-east-1");
+        bedrock::url(provider, region, effective_model, is_streaming)
+    } else {
+        openai_api_url(provider, effective_model, "chat/completions")
+    };
+    let mut forward_headers = provider_headers(headers, provider);
 
     // Build request body based on provider type
-    let body: Value = if is_anthropic {
+    let body: Value = if let Some(native) = native_body {
+        // The provider speaks the client's API: pass through
+        let mut native = native.clone();
+        native["model"] = Value::String(effective_model.to_string());
+        native
+    } else if is_anthropic {
         let mut openai_req = build_openai_chat_request(request, effective_model);
         // Don't pass stream: false to Anthropic API - omit the field instead
         if openai_req.stream == Some(false) {
             openai_req.stream = None;
         }
-        let mut anthropic_req: aidapter::anthropic::types::ChatRequest = (&openai_req).into();
+        let anthropic_req: aidapter::anthropic::types::ChatRequest = (&openai_req).into();
 
         // Manually build JSON to control float serialization
         let mut req_json = serde_json::to_value(&anthropic_req).unwrap_or_default();
 
         // Replace temperature with a properly rounded value
-        if let Some(temp) = anthropic_req.temperature {
-            if let Some(obj) = req_json.as_object_mut() {
-                // Use the original f64 temperature from request instead of the f32
-                if let Some(orig_temp) = request.extra.get("temperature").and_then(|v| v.as_f64()) {
-                    obj.insert("temperature".to_string(), Value::Number(serde_json::Number::from_f64(orig_temp).unwrap()));
-                }
-            }
+        // Use the original f64 temperature from request instead of the f32
+        let orig_temp = request.extra.get("temperature").and_then(|v| v.as_f64());
+        if let (Some(_), Some(obj), Some(orig_temp)) = (anthropic_req.temperature, req_json.as_object_mut(), orig_temp) {
+            obj.insert("temperature".to_string(), Value::Number(serde_json::Number::from_f64(orig_temp).unwrap()));
         }
 
         req_json
+    } else if is_google {
+        gemini::request_from_openai(&request.messages, &request.extra)
+    } else if is_bedrock {
+        bedrock::request_from_openai(&request.messages, &request.extra)
     } else {
         let mut body_map = serde_json::Map::new();
         body_map.insert("model".to_string(), Value::String(effective_model.to_string()));
@@ -370,6 +1224,10 @@ async fn forward_to_provider(
                 body_map.insert(k.clone(), v.clone());
             }
         }
+        // Translated streams need usage at the end of the stream
+        if !matches!(request.client_api, ClientApi::Chat) && is_streaming {
+            body_map.insert("stream_options".to_string(), serde_json::json!({"include_usage": true}));
+        }
         Value::Object(body_map)
     };
 
@@ -381,67 +1239,245 @@ async fn forward_to_provider(
         "Sending request to provider"
     );
 
-    let res = client.post(&url)
+    let body_bytes = serde_json::to_vec(&body).unwrap_or_default();
+    if is_bedrock {
+        match bedrock::sign(provider, &url, &body_bytes) {
+            Ok(signed) => {
+                for (name, value) in signed {
+                    forward_headers.insert(name, value.parse().unwrap());
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Provider {} cannot sign request: {}", provider.name, e);
+                return Err(Attempt::failed(&provider.name, ErrorClass::Auth, e).into());
+            }
+        }
+    }
+    forward_headers.insert("content-type", "application/json".parse().unwrap());
+
+    let client = pooled_client(clients, provider)?;
+    let limits = provider.timeouts.clone().unwrap_or_default().limits(deadline, is_streaming);
+    let first_byte_at = tokio::time::Instant::now() + limits.first_byte;
+    let send = client.post(&url)
         .headers(forward_headers)
-        .json(&body)
-        .send()
-        .await;
+        .body(body_bytes)
+        .timeout(limits.total)
+        .send();
+    let Ok(res) = tokio::time::timeout_at(first_byte_at, send).await else {
+        return Err(first_byte_timeout(provider, limits.first_byte));
+    };
 
     match res {
         Ok(response) => {
             if response.status().is_success() {
-                let resp_status = response.status();
-                let body_bytes = response.bytes().await.unwrap_or_default();
-
-                // Convert response back to OpenAI format if Anthropic
-                let final_body = if is_anthropic {
-                    match serde_json::from_slice::<aidapter::anthropic::types::ChatResponse>(&body_bytes) {
-                        Ok(anthropic_resp) => {
-                            log_entry.input_tokens = Some(anthropic_resp.usage.input_tokens as u64);
-                            log_entry.output_tokens = Some(anthropic_resp.usage.output_tokens as u64);
-                            let openai_resp: aidapter::openai::types::ChatResponse = (&anthropic_resp).into();
-                            serde_json::to_vec(&openai_resp).unwrap_or_else(|_| body_bytes.to_vec())
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to parse Anthropic response: {:?}", e);
-                            body_bytes.to_vec()
+                if is_streaming {
+                    let translator = StreamTranslator::new(
+                        &provider.provider_type, &request.client_api, effective_model, native_body.is_some(),
+                    );
+                    let open = tokio::time::timeout_at(first_byte_at, UpstreamStream::open(response, translator));
+                    return match open.await {
+                        Err(_) => Err(first_byte_timeout(provider, limits.first_byte)),
+                        Ok(Ok(upstream)) => Ok(ProviderReply::Streaming(Box::new(upstream))),
+                        Ok(Err(e)) => {
+                            tracing::warn!("Provider {} {}", provider.name, e);
+                            Err(Attempt::failed(&provider.name, ErrorClass::Connection, e).into())
                         }
+                    };
+                }
+
+                let resp_status = response.status();
+                let body_bytes = match response.bytes().await {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        tracing::warn!("Provider {} failed while sending the body: {:?}", provider.name, e);
+                        return Err(Attempt::transport(&provider.name, &e).into());
                     }
-                } else {
+                };
+
+                // Convert the response into the client's format
+                let final_body = if native_body.is_some() {
                     if let Ok(resp_json) = serde_json::from_slice::<Value>(&body_bytes) {
-                        if let Some(usage) = resp_json.get("usage") {
-                            log_entry.input_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64());
-                            log_entry.output_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64());
+                        if let ClientApi::Responses(_) = request.client_api {
+                            (log_entry.input_tokens, log_entry.output_tokens) =
+                                responses::usage_tokens(resp_json.get("usage").unwrap_or(&Value::Null));
+                        } else {
+                            let mut usage = anthropic::Usage::default();
+                            usage.read(&resp_json["usage"]);
+                            log_entry.input_tokens = usage.input_tokens;
+                            log_entry.output_tokens = usage.output_tokens;
                         }
                     }
                     body_bytes.to_vec()
+                } else {
+                    match response_to_openai(provider, &body_bytes, effective_model, log_entry) {
+                        // OpenAI-compatible replies to chat clients are forwarded untouched
+                        Some(_) if matches!(request.client_api, ClientApi::Chat) && !(is_anthropic || is_google || is_bedrock) => {
+                            body_bytes.to_vec()
+                        }
+                        Some(openai_resp) => {
+                            let out = match &request.client_api {
+                                ClientApi::Chat => openai_resp,
+                                ClientApi::Anthropic => anthropic::response_from_openai(&openai_resp, effective_model),
+                                ClientApi::Responses(conversation) => responses::response_from_openai(
+                                    &openai_resp, effective_model, conversation.previous_response_id.as_deref(),
+                                ),
+                                ClientApi::Completions(options) => {
+                                    completions::response_from_openai(&openai_resp, effective_model, options)
+                                }
+                            };
+                            serde_json::to_vec(&out).unwrap_or_else(|_| body_bytes.to_vec())
+                        }
+                        None => body_bytes.to_vec(),
+                    }
                 };
 
                 // Estimate cost
-                if let (Some(input_t), Some(output_t)) = (log_entry.input_tokens, log_entry.output_tokens) {
-                    if let Some(model_cfg) = provider.models.iter().find(|m| m.id == effective_model) {
-                        let cost = (input_t as f64 / 1_000_000.0) * model_cfg.input_cost_per_1m
-                            + (output_t as f64 / 1_000_000.0) * model_cfg.output_cost_per_1m;
-                        log_entry.estimated_cost = Some(cost);
-                    }
-                }
+                log_entry.record_cost(provider, effective_model);
 
                 let axum_status = StatusCode::from_u16(resp_status.as_u16()).unwrap_or(StatusCode::OK);
-                Some((axum_status, final_body))
+                Ok(ProviderReply::Buffered(axum_status, final_body))
             } else {
-                let status = response.status();
-                let error_body = response.text().await.unwrap_or_default();
-                tracing::warn!("Provider {} failed: {} - {}", provider.name, status, error_body);
-                None
+                Err(upstream_error(provider, response).await)
             }
         }
         Err(e) => {
             tracing::warn!("Provider {} error: {:?}", provider.name, e);
-            None
+            Err(Attempt::transport(&provider.name, &e).into())
+        }
+    }
+}
+
+/// Normalize a provider's buffered reply into an OpenAI chat completion,
+/// recording token usage. Returns None if the body can't be parsed.
+fn response_to_openai(
+    provider: &Provider,
+    body_bytes: &[u8],
+    effective_model: &str,
+    log_entry: &mut RequestLog,
+) -> Option<Value> {
+    if provider.provider_type == ProviderType::Anthropic {
+        return match serde_json::from_slice::<aidapter::anthropic::types::ChatResponse>(body_bytes) {
+            Ok(anthropic_resp) => {
+                log_entry.input_tokens = Some(anthropic_resp.usage.input_tokens as u64);
+                log_entry.output_tokens = Some(anthropic_resp.usage.output_tokens as u64);
+                let openai_resp: aidapter::openai::types::ChatResponse = (&anthropic_resp).into();
+                serde_json::to_value(&openai_resp).ok()
+            }
+            Err(e) => {
+                tracing::warn!("Failed to parse Anthropic response: {:?}", e);
+                None
+            }
+        };
+    }
+
+    let native = match serde_json::from_slice::<Value>(body_bytes) {
+        Ok(native) => native,
+        Err(e) => {
+            tracing::warn!("Failed to parse {} response: {:?}", provider.name, e);
+            return None;
+        }
+    };
+    match provider.provider_type {
+        ProviderType::Google => {
+            (log_entry.input_tokens, log_entry.output_tokens) =
+                gemini::usage_tokens(native.get("usageMetadata").unwrap_or(&Value::Null));
+            Some(gemini::response_to_openai(&native, effective_model))
+        }
+        ProviderType::Bedrock => {
+            (log_entry.input_tokens, log_entry.output_tokens) =
+                bedrock::usage_tokens(native.get("usage").unwrap_or(&Value::Null));
+            Some(bedrock::response_to_openai(&native, effective_model))
+        }
+        _ => {
+            if let Some(usage) = native.get("usage") {
+                log_entry.input_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64());
+                log_entry.output_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64());
+            }
+            Some(native)
         }
     }
 }
 
+/// Send an embeddings request to a single provider, returning an OpenAI-shaped body.
+async fn forward_embeddings(
+    clients: &ClientPool,
+    headers: &HeaderMap,
+    request: &EmbeddingsRequest,
+    provider: &Provider,
+    deadline: Option<Instant>,
+    log_entry: &mut RequestLog,
+) -> Result<(StatusCode, Vec<u8>), ProviderError> {
+    let is_google = provider.provider_type == ProviderType::Google;
+    let (url, body) = match provider.provider_type {
+        ProviderType::Anthropic | ProviderType::Bedrock => {
+            tracing::warn!("Provider {} does not support embeddings", provider.name);
+            return Err(Attempt::failed(&provider.name, ErrorClass::Other, "embeddings not supported").into());
+        }
+        ProviderType::Google => (
+            gemini::embed_url(provider, &request.model),
+            gemini::embed_request_from_openai(&request.model, &request.input, &request.extra),
+        ),
+        _ => {
+            let mut body: serde_json::Map<String, Value> = request
+                .extra
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            body.insert("model".to_string(), Value::String(request.model.clone()));
+            body.insert("input".to_string(), request.input.clone());
+            (openai_api_url(provider, &request.model, "embeddings"), Value::Object(body))
+        }
+    };
+
+    let client = pooled_client(clients, provider)?;
+    let limits = provider.timeouts.clone().unwrap_or_default().limits(deadline, false);
+    let send = client.post(&url)
+        .headers(provider_headers(headers, provider))
+        .json(&body)
+        .timeout(limits.total)
+        .send();
+    let Ok(res) = tokio::time::timeout(limits.first_byte, send).await else {
+        return Err(first_byte_timeout(provider, limits.first_byte));
+    };
+
+    let response = match res {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => return Err(upstream_error(provider, response).await),
+        Err(e) => {
+            tracing::warn!("Provider {} error: {:?}", provider.name, e);
+            return Err(Attempt::transport(&provider.name, &e).into());
+        }
+    };
+
+    let status = StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::OK);
+    let body_bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Provider {} failed while sending the body: {:?}", provider.name, e);
+            return Err(Attempt::transport(&provider.name, &e).into());
+        }
+    };
+    let final_body = match serde_json::from_slice::<Value>(&body_bytes) {
+        Ok(resp_json) if is_google => {
+            let openai_resp = gemini::embed_response_to_openai(&resp_json, &request.model);
+            serde_json::to_vec(&openai_resp).unwrap_or_else(|_| body_bytes.to_vec())
+        }
+        Ok(resp_json) => {
+            log_entry.input_tokens = resp_json.pointer("/usage/prompt_tokens").and_then(|v| v.as_u64());
+            log_entry.output_tokens = Some(0);
+            body_bytes.to_vec()
+        }
+        Err(e) => {
+            tracing::warn!("Failed to parse {} embeddings response: {:?}", provider.name, e);
+            body_bytes.to_vec()
+        }
+    };
+    log_entry.record_cost(provider, &request.model);
+
+    Ok((status, final_body))
+}
+
 pub async fn get_logs(
     State(state): State<AppState>,
     Query(params): Query<LogsQuery>,
@@ -454,20 +1490,9 @@ pub async fn get_logs(
     let filtered: Vec<&RequestLog> = all_logs.iter()
         .rev() // newest first
         .filter(|log| {
-            if let Some(ref s) = params.status {
-                if &log.status != s { return false; }
-            }
-            if let Some(ref m) = params.model {
-                if !log.model.contains(m.as_str()) { return false; }
-            }
-            if let Some(ref p) = params.provider {
-                if let Some(ref lp) = log.provider {
-                    if !lp.contains(p.as_str()) { return false; }
-                } else {
-                    return false;
-                }
-            }
-            true
+            params.status.as_ref().is_none_or(|s| &log.status == s)
+                && params.model.as_ref().is_none_or(|m| log.model.contains(m.as_str()))
+                && params.provider.as_ref().is_none_or(|p| log.provider.as_ref().is_some_and(|lp| lp.contains(p.as_str())))
         })
         .collect();
 
@@ -482,8 +1507,11 @@ pub async fn get_logs(
     }))
 }
 
-pub async fn get_config(State(state): State<AppState>) -> impl IntoResponse {
-    let config = state.get_config().await;
+pub async fn get_config(State(state): State<AppState>, Extension(role): Extension<Role>) -> impl IntoResponse {
+    let mut config = state.get_config().await;
+    if role == Role::ReadOnly {
+        admin::redact_secrets(&mut config);
+    }
     Json(config)
 }
 
@@ -497,6 +1525,65 @@ pub async fn update_config(
     }
 }
 
+/// Whether the admin API needs a token, and the role of the one presented.
+/// Lets the dashboard decide whether to show its login form.
+pub async fn get_auth(
+    State(state): State<AppState>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let client = connect_info.map(|Extension(ConnectInfo(addr))| addr.ip());
+    Json(serde_json::json!({
+        "required": state.admin.required(),
+        "role": state.admin.role_of(&headers, client).ok(),
+    }))
+}
+
+/// Check an admin token for the dashboard's login form. The dashboard then
+/// sends it as a bearer token.
+pub async fn login(
+    State(state): State<AppState>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    Json(request): Json<LoginRequest>,
+) -> Response {
+    let client = connect_info.map(|Extension(ConnectInfo(addr))| addr.ip());
+    match state.admin.check(Some(&request.token), client) {
+        Ok(role) => Json(serde_json::json!({"role": role})).into_response(),
+        Err(admin::Denied::Unauthorized) => {
+            tracing::warn!(client = ?client, "Failed admin login");
+            error::json_error(StatusCode::UNAUTHORIZED, "invalid_request_error", "invalid_token", "Invalid admin token", &[])
+        }
+        Err(denied) => denied.into_response(),
+    }
+}
+
+/// Re-run model discovery for local providers and report what each one serves.
+pub async fn discover_models(State(state): State<AppState>) -> impl IntoResponse {
+    let results: serde_json::Map<String, Value> = discovery::refresh(&state)
+        .await
+        .into_iter()
+        .map(|(id, result)| {
+            let entry = match result {
+                Ok(models) => serde_json::json!({"models": models}),
+                Err(e) => serde_json::json!({"error": e}),
+            };
+            (id, entry)
+        })
+        .collect();
+    Json(Value::Object(results))
+}
+
+/// Latest health check results for the enabled providers.
+pub async fn get_provider_health(State(state): State<AppState>) -> impl IntoResponse {
+    let config = state.get_config().await;
+    let health_config = config.health_check.clone().unwrap_or_default();
+    Json(serde_json::json!({
+        "enabled": health_config.enabled,
+        "interval_seconds": health_config.interval_seconds,
+        "providers": state.health.report(&config.providers).await,
+    }))
+}
+
 pub async fn get_stats(State(state): State<AppState>) -> impl IntoResponse {
     let logs = state.get_logs().await;
     let config = state.get_config().await;
@@ -578,6 +1665,19 @@ pub async fn get_stats(State(state): State<AppState>) -> impl IntoResponse {
     // Recent requests (last 10)
     let recent: Vec<&RequestLog> = logs.iter().rev().take(10).collect();
 
+    let mut latency: Vec<Value> = state.latency.snapshot().into_iter().map(|((provider_id, model), stats)| {
+        let name = config.providers.iter().find(|p| p.id == provider_id).map(|p| p.name.clone());
+        serde_json::json!({
+            "provider": name.unwrap_or_else(|| provider_id.clone()),
+            "provider_id": provider_id,
+            "model": model,
+            "latency_ms": stats.latency_ms.round() as u64,
+            "ttft_ms": stats.ttft_ms.map(|ms| ms.round() as u64),
+            "samples": stats.samples,
+        })
+    }).collect();
+    latency.sort_by(|a, b| (a["provider"].as_str(), a["model"].as_str()).cmp(&(b["provider"].as_str(), b["model"].as_str())));
+
     Json(serde_json::json!({
         "requests": total_requests,
         "successful": successful,
@@ -592,6 +1692,11 @@ pub async fn get_stats(State(state): State<AppState>) -> impl IntoResponse {
         "agentic_count": agentic_count,
         "session_pinned_count": session_pinned_count,
         "active_sessions": active_sessions,
+        "circuits": state.breakers.status(&config.circuit_breaker.clone().unwrap_or_default()),
+        "latency": latency,
+        "rate_limits": state.limits.status(&config.providers),
+        "virtual_keys": state.virtual_keys.status(&config.virtual_keys, &state.spend),
+        "budgets": state.spend.status(&config.budgets),
     }))
 }
 
@@ -0,0 +1,311 @@
+//! Active health checks for providers.
+//!
+//! A background task probes every enabled provider on an interval, either by
+//! listing its models (free on most APIs) or with a one-token chat request.
+//! Providers that fail `failure_threshold` probes in a row are marked down and
+//! skipped by the router until a probe succeeds again.
+
+use crate::breaker::Outcome;
+use crate::config::Provider;
+use crate::error::{self, ErrorClass};
+use crate::handlers::{self, ChatCompletionRequest, ClientApi};
+use crate::keys;
+use crate::state::{AppState, RequestLog};
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// How a provider is probed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeKind {
+    /// List the provider's models. Providers without a listing (Bedrock,
+    /// some gateways) get a chat probe instead.
+    Models,
+    /// Send a one-token chat completion.
+    Chat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    pub enabled: bool,
+    pub interval_seconds: u64,
+    /// Limit on a single probe.
+    pub timeout_seconds: u64,
+    pub probe: ProbeKind,
+    /// Model for chat probes; defaults to the provider's cheapest chat model.
+    pub probe_model: Option<String>,
+    /// Consecutive failed probes before a provider is marked down.
+    pub failure_threshold: u32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_seconds: 60,
+            timeout_seconds: 10,
+            probe: ProbeKind::Models,
+            probe_model: None,
+            failure_threshold: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// Not probed yet, or failing but not past the threshold from the start.
+    Unknown,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderHealth {
+    pub provider_id: String,
+    pub name: String,
+    pub status: HealthStatus,
+    /// Duration of the last successful probe.
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub last_checked: Option<DateTime<Utc>>,
+    pub last_up: Option<DateTime<Utc>>,
+    pub consecutive_failures: u32,
+}
+
+impl ProviderHealth {
+    fn unknown(provider: &Provider) -> Self {
+        Self {
+            provider_id: provider.id.clone(),
+            name: provider.name.clone(),
+            status: HealthStatus::Unknown,
+            latency_ms: None,
+            last_error: None,
+            last_checked: None,
+            last_up: None,
+            consecutive_failures: 0,
+        }
+    }
+}
+
+/// Latest probe results, keyed by provider id.
+#[derive(Clone, Default)]
+pub struct HealthRegistry {
+    providers: Arc<RwLock<HashMap<String, ProviderHealth>>>,
+}
+
+impl HealthRegistry {
+    /// Record a probe result: the probe's duration, or why it failed.
+    pub async fn record(&self, provider: &Provider, result: Result<Duration, String>, failure_threshold: u32) {
+        let mut providers = self.providers.write().await;
+        let health = providers
+            .entry(provider.id.clone())
+            .or_insert_with(|| ProviderHealth::unknown(provider));
+        let now = Utc::now();
+        health.name = provider.name.clone();
+        health.last_checked = Some(now);
+        match result {
+            Ok(latency) => {
+                if health.status == HealthStatus::Down {
+                    tracing::info!(provider = %provider.name, "Provider is back up");
+                }
+                health.status = HealthStatus::Up;
+                health.latency_ms = Some(latency.as_millis() as u64);
+                health.last_error = None;
+                health.last_up = Some(now);
+                health.consecutive_failures = 0;
+            }
+            Err(e) => {
+                health.consecutive_failures += 1;
+                if health.consecutive_failures >= failure_threshold && health.status != HealthStatus::Down {
+                    tracing::warn!(provider = %provider.name, error = %e, "Provider marked down");
+                    health.status = HealthStatus::Down;
+                }
+                health.last_error = Some(e);
+            }
+        }
+    }
+
+    /// Ids of providers currently marked down.
+    pub async fn down(&self) -> HashSet<String> {
+        self.providers
+            .read()
+            .await
+            .values()
+            .filter(|h| h.status == HealthStatus::Down)
+            .map(|h| h.provider_id.clone())
+            .collect()
+    }
+
+    /// Health of the enabled providers, in config order.
+    pub async fn report(&self, providers: &[Provider]) -> Vec<ProviderHealth> {
+        let known = self.providers.read().await;
+        providers
+            .iter()
+            .filter(|p| p.enabled)
+            .map(|p| known.get(&p.id).cloned().unwrap_or_else(|| ProviderHealth::unknown(p)))
+            .collect()
+    }
+
+    /// Forget providers that are no longer configured and enabled.
+    async fn retain(&self, providers: &[Provider]) {
+        let ids: HashSet<&str> = providers.iter().filter(|p| p.enabled).map(|p| p.id.as_str()).collect();
+        self.providers.write().await.retain(|id, _| ids.contains(id.as_str()));
+    }
+}
+
+/// The model a chat probe uses: the configured one if the provider serves it,
+/// otherwise its cheapest chat model.
+fn probe_model(provider: &Provider, config: &HealthConfig) -> Option<String> {
+    if let Some(model) = config.probe_model.as_ref().filter(|m| provider.models.iter().any(|pm| &pm.id == *m)) {
+        return Some(model.clone());
+    }
+    provider
+        .models
+        .iter()
+        .filter(|m| !m.embedding)
+        .min_by(|a, b| a.input_cost_per_1m.total_cmp(&b.input_cost_per_1m))
+        .map(|m| m.id.clone())
+}
+
+/// Probe by listing models. Returns false when the provider has no listing
+/// (404/405), so a chat probe can be used instead.
+async fn probe_models(state: &AppState, provider: &Provider, url: &str, timeout: Duration) -> Result<bool, String> {
+    let client = state.clients.get(provider)?;
+    let response = client
+        .get(url)
+        .headers(handlers::provider_headers(&HeaderMap::new(), provider))
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status().as_u16();
+    if response.status().is_success() {
+        return Ok(true);
+    }
+    if status == 404 || status == 405 {
+        return Ok(false);
+    }
+    let body = response.text().await.unwrap_or_default();
+    match Outcome::from_class(ErrorClass::from_response(status, &body)) {
+        Outcome::Healthy => Ok(true),
+        _ => Err(format!("{} {}", status, error::upstream_message(&body))),
+    }
+}
+
+async fn probe_chat(state: &AppState, provider: &Provider, model: &str, timeout: Duration) -> Result<(), String> {
+    let request = ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![json!({"role": "user", "content": "ping"})],
+        extra: HashMap::from([("max_tokens".to_string(), Value::from(1))]),
+        native_body: None,
+        client_api: ClientApi::Chat,
+    };
+    let mut log_entry = RequestLog::new(model);
+    let deadline = Some(Instant::now() + timeout);
+    match handlers::forward_to_provider(
+        &state.clients, &HeaderMap::new(), &request, provider, model, deadline, &mut log_entry,
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(failure) if Outcome::from_class(failure.attempt.class) == Outcome::Healthy => Ok(()),
+        Err(failure) => Err(failure.attempt.to_string()),
+    }
+}
+
+/// Probe one provider, returning how long it took.
+pub async fn probe(state: &AppState, provider: &Provider, config: &HealthConfig) -> Result<Duration, String> {
+    let provider = &*keys::primary(provider);
+    let timeout = Duration::from_secs(config.timeout_seconds);
+    let start = Instant::now();
+    let listed = match handlers::models_url(provider).filter(|_| config.probe == ProbeKind::Models) {
+        Some(url) => probe_models(state, provider, &url, timeout).await?,
+        None => false,
+    };
+    if !listed {
+        let model = probe_model(provider, config).ok_or("no chat model to probe with")?;
+        probe_chat(state, provider, &model, timeout).await?;
+    }
+    Ok(start.elapsed())
+}
+
+/// Probe every enabled provider concurrently and record the results.
+pub async fn check_all(state: &AppState, config: &HealthConfig) {
+    let providers = state.get_config().await.providers;
+    state.health.retain(&providers).await;
+    let checks = providers.iter().filter(|p| p.enabled).map(|provider| async move {
+        let result = probe(state, provider, config).await;
+        state.health.record(provider, result, config.failure_threshold).await;
+    });
+    futures_util::future::join_all(checks).await;
+}
+
+/// Run health checks forever. The config is re-read every round, so changes
+/// to the interval or probe take effect without a restart.
+pub async fn run(state: AppState) {
+    loop {
+        let config = state.get_config().await.health_check.unwrap_or_default();
+        if config.enabled {
+            check_all(&state, &config).await;
+        }
+        tokio::time::sleep(Duration::from_secs(config.interval_seconds.max(1))).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_marked_down_after_threshold() {
+        let provider = Config::default().providers[0].clone();
+        let registry = HealthRegistry::default();
+
+        registry.record(&provider, Err("500 boom".to_string()), 2).await;
+        let report = registry.report(std::slice::from_ref(&provider)).await;
+        assert_eq!(report[0].status, HealthStatus::Unknown);
+        assert!(registry.down().await.is_empty());
+
+        registry.record(&provider, Err("500 boom".to_string()), 2).await;
+        assert!(registry.down().await.contains(&provider.id));
+
+        registry.record(&provider, Ok(Duration::from_millis(42)), 2).await;
+        let report = registry.report(std::slice::from_ref(&provider)).await;
+        assert_eq!(report[0].status, HealthStatus::Up);
+        assert_eq!(report[0].latency_ms, Some(42));
+        assert_eq!(report[0].last_error, None);
+        assert!(registry.down().await.is_empty());
+    }
+
+    #[test]
+    fn test_probe_model() {
+        let mut provider = Config::default().providers[0].clone();
+        let mut cheap = provider.models[0].clone();
+        cheap.id = "cheap".to_string();
+        cheap.input_cost_per_1m = 0.1;
+        let mut pricey = cheap.clone();
+        pricey.id = "pricey".to_string();
+        pricey.input_cost_per_1m = 10.0;
+        let mut embed = cheap.clone();
+        embed.id = "embed".to_string();
+        embed.input_cost_per_1m = 0.01;
+        embed.embedding = true;
+        provider.models = vec![pricey, embed, cheap];
+
+        let config = HealthConfig::default();
+        assert_eq!(probe_model(&provider, &config).as_deref(), Some("cheap"));
+        let configured = HealthConfig { probe_model: Some("pricey".to_string()), ..config.clone() };
+        assert_eq!(probe_model(&provider, &configured).as_deref(), Some("pricey"));
+        let unknown = HealthConfig { probe_model: Some("not-served".to_string()), ..config };
+        assert_eq!(probe_model(&provider, &unknown).as_deref(), Some("cheap"));
+    }
+}
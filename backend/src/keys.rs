@@ -0,0 +1,241 @@
+//! Pools of API keys per provider.
+//!
+//! A provider can list several keys (for example one per team account)
+//! instead of a single `api_key`. Each attempt takes the next key by smooth
+//! weighted round-robin, skipping keys that are cooling down or have used up
+//! their own requests/tokens per minute. A key that is rate limited or
+//! rejected is put on cooldown and the attempt is repeated with another key.
+
+use crate::config::Provider;
+use crate::error::{Attempt, ErrorClass, ProviderError};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Cooldown after a 429 that did not say how long to wait.
+const RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(60);
+/// Cooldown after the provider rejected a key; long, since a revoked or
+/// unfunded key rarely recovers by itself.
+const AUTH_COOLDOWN: Duration = Duration::from_secs(600);
+/// Shortest cooldown, so a key is not retried in a tight loop.
+const MIN_COOLDOWN: Duration = Duration::from_secs(1);
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// One key in a provider's pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub key: String,
+    /// Name shown in logs instead of the key; defaults to `key-<n>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Requests per minute this key may send.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpm: Option<u32>,
+    /// Tokens per minute this key may use, counted from request estimates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tpm: Option<u64>,
+    /// Share of requests relative to the provider's other keys (default 1).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+}
+
+impl ApiKey {
+    fn label(&self, index: usize) -> String {
+        self.label.clone().unwrap_or_else(|| format!("key-{}", index + 1))
+    }
+}
+
+/// The key chosen for one attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyLease {
+    index: usize,
+    pub label: String,
+}
+
+impl KeyLease {
+    /// The provider as it should be called with this key.
+    pub fn apply<'a>(lease: Option<&KeyLease>, provider: &'a Provider) -> Cow<'a, Provider> {
+        match lease.and_then(|l| provider.api_keys.get(l.index)) {
+            Some(key) => Cow::Owned(Provider { api_key: Some(key.key.clone()), ..provider.clone() }),
+            None => Cow::Borrowed(provider),
+        }
+    }
+}
+
+/// The provider with its single `api_key`, or its first pooled key, for
+/// requests outside of routing such as health checks.
+pub fn primary(provider: &Provider) -> Cow<'_, Provider> {
+    if provider.api_key.is_some() || provider.api_keys.is_empty() {
+        return Cow::Borrowed(provider);
+    }
+    KeyLease::apply(Some(&KeyLease { index: 0, label: provider.api_keys[0].label(0) }), provider)
+}
+
+#[derive(Default)]
+struct KeyState {
+    cooldown_until: Option<Instant>,
+    /// Requests in the last minute: (when, estimated tokens).
+    recent: VecDeque<(Instant, u64)>,
+    /// Smooth weighted round-robin counter.
+    current_weight: i64,
+}
+
+impl KeyState {
+    fn prune(&mut self, now: Instant) {
+        while self.recent.front().is_some_and(|(at, _)| now.duration_since(*at) >= MINUTE) {
+            self.recent.pop_front();
+        }
+    }
+
+    fn available(&self, key: &ApiKey, tokens: u64, now: Instant) -> bool {
+        if self.cooldown_until.is_some_and(|until| now < until) {
+            return false;
+        }
+        if key.rpm.is_some_and(|rpm| self.recent.len() >= rpm as usize) {
+            return false;
+        }
+        let used: u64 = self.recent.iter().map(|(_, t)| t).sum();
+        // A request bigger than the whole limit still goes through on an idle key
+        key.tpm.is_none_or(|tpm| used == 0 || used + tokens <= tpm)
+    }
+}
+
+/// Key usage and cooldowns, keyed by (provider id, key label).
+#[derive(Clone, Default)]
+pub struct KeyRing {
+    keys: Arc<Mutex<HashMap<(String, String), KeyState>>>,
+}
+
+impl KeyRing {
+    fn lock(&self) -> MutexGuard<'_, HashMap<(String, String), KeyState>> {
+        self.keys.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Take a key for an attempt expected to use `tokens`. `Ok(None)` when
+    /// the provider has no key pool; an error when every key is unavailable.
+    pub fn acquire(&self, provider: &Provider, tokens: u64) -> Result<Option<KeyLease>, ProviderError> {
+        if provider.api_keys.is_empty() {
+            return Ok(None);
+        }
+        let now = Instant::now();
+        let mut states = self.lock();
+        let mut available = Vec::new();
+        for (index, key) in provider.api_keys.iter().enumerate() {
+            let state = states.entry((provider.id.clone(), key.label(index))).or_default();
+            state.prune(now);
+            if state.available(key, tokens, now) {
+                available.push(index);
+            }
+        }
+        if available.is_empty() {
+            return Err(Attempt::failed(
+                &provider.name,
+                ErrorClass::RateLimited,
+                "all API keys are cooling down or at their limits",
+            )
+            .into());
+        }
+
+        // Smooth weighted round-robin over the available keys
+        let weight = |index: usize| provider.api_keys[index].weight.unwrap_or(1) as i64;
+        let total: i64 = available.iter().map(|&i| weight(i)).sum();
+        let mut best: Option<(usize, i64)> = None;
+        for &index in &available {
+            let state = states.get_mut(&(provider.id.clone(), provider.api_keys[index].label(index))).expect("state created above");
+            state.current_weight += weight(index);
+            if best.is_none_or(|(_, w)| state.current_weight > w) {
+                best = Some((index, state.current_weight));
+            }
+        }
+        let (index, _) = best.expect("at least one key available");
+        let label = provider.api_keys[index].label(index);
+        let state = states.get_mut(&(provider.id.clone(), label.clone())).expect("state created above");
+        state.current_weight -= total;
+        state.recent.push_back((now, tokens));
+        Ok(Some(KeyLease { index, label }))
+    }
+
+    /// Put the leased key on cooldown if the failure was about the key.
+    /// Returns whether it was, so the attempt can move on to another key.
+    pub fn cool_down(&self, provider: &Provider, lease: Option<&KeyLease>, failure: &ProviderError) -> bool {
+        let Some(lease) = lease else { return false };
+        let cooldown = match failure.attempt.class {
+            ErrorClass::RateLimited => failure.retry_after.unwrap_or(RATE_LIMIT_COOLDOWN),
+            ErrorClass::Auth => AUTH_COOLDOWN,
+            _ => return false,
+        }
+        .max(MIN_COOLDOWN);
+        tracing::warn!(
+            provider = %provider.name,
+            key = %lease.label,
+            cooldown_seconds = cooldown.as_secs(),
+            class = failure.attempt.class.as_str(),
+            "API key put on cooldown"
+        );
+        self.lock()
+            .entry((provider.id.clone(), lease.label.clone()))
+            .or_default()
+            .cooldown_until = Some(Instant::now() + cooldown);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn key(label: &str, weight: Option<u32>) -> ApiKey {
+        ApiKey { key: format!("sk-{}", label), label: Some(label.to_string()), rpm: None, tpm: None, weight }
+    }
+
+    fn provider(keys: Vec<ApiKey>) -> Provider {
+        Provider { api_keys: keys, ..Config::default().providers[0].clone() }
+    }
+
+    fn labels(ring: &KeyRing, provider: &Provider, n: usize) -> Vec<String> {
+        (0..n).map(|_| ring.acquire(provider, 0).unwrap().unwrap().label).collect()
+    }
+
+    #[test]
+    fn test_weighted_rotation() {
+        let ring = KeyRing::default();
+        let provider = provider(vec![key("a", Some(2)), key("b", None)]);
+        assert_eq!(labels(&ring, &provider, 6), vec!["a", "b", "a", "a", "b", "a"]);
+
+        let lease = ring.acquire(&provider, 0).unwrap();
+        assert_eq!(KeyLease::apply(lease.as_ref(), &provider).api_key.as_deref(), Some("sk-a"));
+        let single = Config::default().providers[0].clone();
+        assert_eq!(ring.acquire(&single, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_cooldown_and_limits() {
+        let ring = KeyRing::default();
+        let mut limited = key("limited", None);
+        limited.rpm = Some(1);
+        let provider = provider(vec![key("a", None), limited]);
+
+        let lease = ring.acquire(&provider, 0).unwrap();
+        let rate_limited: ProviderError = Attempt::upstream(&provider.name, 429, "").into();
+        assert!(ring.cool_down(&provider, lease.as_ref(), &rate_limited));
+        let server_error: ProviderError = Attempt::upstream(&provider.name, 500, "").into();
+        assert!(!ring.cool_down(&provider, lease.as_ref(), &server_error));
+
+        // "a" is cooling down, "limited" has one request a minute
+        assert_eq!(labels(&ring, &provider, 1), vec!["limited"]);
+        let err = ring.acquire(&provider, 0).unwrap_err();
+        assert_eq!(err.attempt.class, ErrorClass::RateLimited);
+    }
+
+    #[test]
+    fn test_primary_key() {
+        let pooled = provider(vec![key("a", None), key("b", None)]);
+        let pooled = Provider { api_key: None, ..pooled };
+        assert_eq!(primary(&pooled).api_key.as_deref(), Some("sk-a"));
+        let single = Provider { api_key: Some("sk-own".to_string()), ..pooled };
+        assert_eq!(primary(&single).api_key.as_deref(), Some("sk-own"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Observed provider latency, for latency-aware routing.
+//!
+//! Every successful request updates an exponentially weighted moving average
+//! of its total duration and, for streams, its time to first token, per
+//! provider and model. Only requests served by their first attempt are
+//! counted, so retry waits and failed candidates don't skew the averages.
+
+use crate::state::RequestLog;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Weight of the newest sample in the moving averages.
+const ALPHA: f64 = 0.2;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencyStats {
+    /// Average total duration in milliseconds.
+    pub latency_ms: f64,
+    /// Average time to first token in milliseconds, from streaming requests.
+    pub ttft_ms: Option<f64>,
+    pub samples: u64,
+}
+
+impl LatencyStats {
+    /// What routing compares: time to first token where streams have been
+    /// seen, otherwise the total duration (the first token of a buffered
+    /// reply arrives with the last).
+    pub fn routing_ms(&self) -> f64 {
+        self.ttft_ms.unwrap_or(self.latency_ms)
+    }
+
+    fn update(&mut self, duration_ms: u64, ttft_ms: Option<u64>) {
+        self.latency_ms = ewma(self.latency_ms, duration_ms as f64, self.samples == 0);
+        if let Some(ttft) = ttft_ms {
+            self.ttft_ms = Some(match self.ttft_ms {
+                Some(avg) => ewma(avg, ttft as f64, false),
+                None => ttft as f64,
+            });
+        }
+        self.samples += 1;
+    }
+}
+
+fn ewma(average: f64, sample: f64, first: bool) -> f64 {
+    if first { sample } else { ALPHA * sample + (1.0 - ALPHA) * average }
+}
+
+/// Latency averages keyed by (provider id, model), like the other
+/// per-provider state, so renaming a provider keeps its history.
+pub type Latencies = HashMap<(String, String), LatencyStats>;
+
+#[derive(Clone, Default)]
+pub struct LatencyTracker {
+    stats: Arc<Mutex<Latencies>>,
+}
+
+impl LatencyTracker {
+    /// Fold a completed request into the averages, if it is a clean sample.
+    pub fn record(&self, log: &RequestLog) {
+        if log.status != "success" || log.cache_status.as_deref() == Some("hit") || log.attempts.len() != 1 {
+            return;
+        }
+        let Some(ref provider) = log.provider_id else { return };
+        let model = log.effective_model.clone().unwrap_or_else(|| log.model.clone());
+        self.stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((provider.clone(), model))
+            .or_default()
+            .update(log.duration_ms, log.ttft_ms);
+    }
+
+    pub fn snapshot(&self) -> Latencies {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AttemptLog;
+
+    fn success(provider: &str, duration_ms: u64, ttft_ms: Option<u64>) -> RequestLog {
+        let mut log = RequestLog::new("m");
+        log.status = "success".to_string();
+        log.provider = Some(provider.to_string());
+        log.provider_id = Some(provider.to_string());
+        log.duration_ms = duration_ms;
+        log.ttft_ms = ttft_ms;
+        log.attempts = vec![AttemptLog {
+            provider: provider.to_string(),
+            delay_ms: 0,
+            status: Some(200),
+            outcome: "success".to_string(),
+        }];
+        log
+    }
+
+    #[test]
+    fn test_moving_average() {
+        let tracker = LatencyTracker::default();
+        tracker.record(&success("p", 1000, None));
+        tracker.record(&success("p", 2000, None));
+        let stats = tracker.snapshot()[&("p".to_string(), "m".to_string())].clone();
+        assert_eq!(stats.samples, 2);
+        assert!((stats.latency_ms - 1200.0).abs() < 1e-9);
+        assert_eq!(stats.routing_ms(), stats.latency_ms);
+
+        tracker.record(&success("p", 3000, Some(100)));
+        let stats = tracker.snapshot()[&("p".to_string(), "m".to_string())].clone();
+        assert_eq!(stats.ttft_ms, Some(100.0));
+        assert_eq!(stats.routing_ms(), 100.0);
+    }
+
+    #[test]
+    fn test_unclean_samples_ignored() {
+        let tracker = LatencyTracker::default();
+        let mut retried = success("p", 5000, None);
+        retried.attempts.push(retried.attempts[0].clone());
+        tracker.record(&retried);
+        let mut failed = success("p", 5000, None);
+        failed.status = "error".to_string();
+        tracker.record(&failed);
+        let mut cached = success("cache", 1, None);
+        cached.cache_status = Some("hit".to_string());
+        tracker.record(&cached);
+        assert!(tracker.snapshot().is_empty());
+    }
+}
@@ -1,11 +1,34 @@
+pub mod admin;
+pub mod anthropic;
+pub mod balance;
+pub mod bedrock;
+pub mod breaker;
+pub mod budget;
 pub mod cache;
+pub mod client;
+pub mod completions;
 pub mod config;
+pub mod discovery;
+pub mod error;
+pub mod estimate;
+pub mod gemini;
 pub mod handlers;
+pub mod health;
+pub mod keys;
+pub mod latency;
+pub mod ratelimit;
+pub mod responses;
+pub mod retry;
 pub mod router;
 pub mod scorer;
+pub mod sigv4;
 pub mod state;
+pub mod stream;
+pub mod timeout;
+pub mod virtual_keys;
 
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -15,15 +38,28 @@ use tower_http::services::{ServeDir, ServeFile};
 pub fn app(state: state::AppState) -> Router {
     let spa_fallback = ServeDir::new("static").fallback(ServeFile::new("static/index.html"));
 
-    Router::new()
-        .route("/v1/chat/completions", post(handlers::chat_completions))
-        .route("/v1/models", get(handlers::list_models))
+    // Admin API: any admin token may read, only a full one may change things
+    let admin_api = Router::new()
         .route(
             "/api/config",
             get(handlers::get_config).post(handlers::update_config),
         )
+        .route("/api/providers/discover", post(handlers::discover_models))
         .route("/api/stats", get(handlers::get_stats))
+        .route("/api/health/providers", get(handlers::get_provider_health))
         .route("/api/logs", get(handlers::get_logs))
+        .route_layer(middleware::from_fn_with_state(state.clone(), admin::require_role));
+
+    Router::new()
+        .route("/v1/chat/completions", post(handlers::chat_completions))
+        .route("/v1/completions", post(handlers::completions))
+        .route("/v1/messages", post(handlers::messages))
+        .route("/v1/responses", post(handlers::responses))
+        .route("/v1/embeddings", post(handlers::embeddings))
+        .route("/v1/models", get(handlers::list_models))
+        .route("/api/auth", get(handlers::get_auth))
+        .route("/api/auth/login", post(handlers::login))
+        .merge(admin_api)
         .with_state(state)
         .fallback_service(spa_fallback)
 }
@@ -14,7 +14,26 @@ async fn main() {
         .init();
 
     let config_path = PathBuf::from("config/config.json");
-    let state = backend::state::AppState::new(config_path).await;
+    let mut state = backend::state::AppState::new(config_path).await.unwrap_or_else(|e| {
+        tracing::error!("Cannot start: {:#}", e);
+        std::process::exit(1);
+    });
+    state.admin = backend::admin::AdminAuth::from_env().unwrap_or_else(|e| {
+        tracing::error!("Cannot start: {:#}", e);
+        std::process::exit(1);
+    });
+    if !state.admin.required() {
+        tracing::warn!("ADMIN_TOKEN is not set; the admin API is read-only and config changes are refused");
+    }
+
+    // Discover local models in the background so a stopped server doesn't delay startup
+    let discovery_state = state.clone();
+    tokio::spawn(async move {
+        backend::discovery::refresh(&discovery_state).await;
+    });
+
+    // Probe providers periodically so the router can skip ones that are down
+    tokio::spawn(backend::health::run(state.clone()));
 
     let app = backend::app(state).layer(
         CorsLayer::new()
@@ -27,5 +46,8 @@ async fn main() {
     tracing::info!("listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Client addresses let failed admin logins be throttled per client
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
@@ -0,0 +1,260 @@
+//! Client-side rate limits for providers and models.
+//!
+//! Requests-per-minute and tokens-per-minute limits are enforced with token
+//! buckets that refill continuously, so the router holds back requests it
+//! knows the upstream would reject with a 429. A request finding a bucket
+//! empty either waits for it to refill, up to `max_wait_ms`, or moves on to
+//! the next candidate. Token counts are the request's estimate.
+
+use crate::config::Provider;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    /// Requests per minute.
+    pub rpm: Option<u32>,
+    /// Tokens per minute.
+    pub tpm: Option<u64>,
+    /// Longest a request waits for capacity before trying another provider.
+    pub max_wait_ms: u64,
+}
+
+/// Bucket key: provider id, and the model id for model-level limits.
+type Key = (String, Option<String>);
+
+#[derive(Debug)]
+struct Bucket {
+    requests: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            requests: limit.rpm.unwrap_or(0) as f64,
+            tokens: limit.tpm.unwrap_or(0) as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let minutes = now.duration_since(self.updated).as_secs_f64() / 60.0;
+        if let Some(rpm) = limit.rpm {
+            self.requests = (self.requests + minutes * rpm as f64).min(rpm as f64);
+        }
+        if let Some(tpm) = limit.tpm {
+            self.tokens = (self.tokens + minutes * tpm as f64).min(tpm as f64);
+        }
+        self.updated = now;
+    }
+
+    /// Time until the bucket holds a request and `tokens`. Requests larger
+    /// than the whole token limit only need a full bucket.
+    fn wait(&self, limit: &RateLimit, tokens: u64) -> Duration {
+        let until = |have: f64, need: f64, per_minute: f64| {
+            if have >= need { 0.0 } else { (need - have) / per_minute * 60.0 }
+        };
+        let mut seconds: f64 = 0.0;
+        if let Some(rpm) = limit.rpm.filter(|r| *r > 0) {
+            seconds = seconds.max(until(self.requests, 1.0, rpm as f64));
+        }
+        if let Some(tpm) = limit.tpm.filter(|t| *t > 0) {
+            seconds = seconds.max(until(self.tokens, tokens.min(tpm) as f64, tpm as f64));
+        }
+        Duration::from_secs_f64(seconds)
+    }
+
+    fn take(&mut self, tokens: u64) {
+        self.requests -= 1.0;
+        self.tokens -= tokens as f64;
+    }
+}
+
+/// The limits that apply to a request on a provider's model.
+fn limits<'a>(provider: &'a Provider, model_id: &str) -> Vec<(Key, &'a RateLimit)> {
+    let mut limits = Vec::new();
+    if let Some(ref limit) = provider.rate_limit {
+        limits.push(((provider.id.clone(), None), limit));
+    }
+    if let Some(limit) = provider.models.iter().find(|m| m.id == model_id).and_then(|m| m.rate_limit.as_ref()) {
+        limits.push(((provider.id.clone(), Some(model_id.to_string())), limit));
+    }
+    limits
+}
+
+/// Remaining capacity of one bucket, as shown in `/api/stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LimitStatus {
+    pub provider_id: String,
+    /// None for the provider-wide limit.
+    pub model: Option<String>,
+    pub rpm: Option<u32>,
+    pub tpm: Option<u64>,
+    pub remaining_requests: Option<u32>,
+    pub remaining_tokens: Option<u64>,
+}
+
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<Key, Bucket>>>,
+}
+
+impl RateLimiter {
+    fn lock(&self) -> MutexGuard<'_, HashMap<Key, Bucket>> {
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Take capacity for a request of `tokens` from every bucket that applies,
+    /// or return how long until there is enough.
+    pub fn try_take(&self, provider: &Provider, model_id: &str, tokens: u64) -> Result<(), Duration> {
+        self.take(limits(provider, model_id), tokens)
+    }
+
+    /// Take capacity from a limit of something other than a provider, such
+    /// as a client key, identified by `id`.
+    pub fn try_take_limit(&self, id: &str, limit: &RateLimit, tokens: u64) -> Result<(), Duration> {
+        self.take(vec![((id.to_string(), None), limit)], tokens)
+    }
+
+    fn take(&self, limits: Vec<(Key, &RateLimit)>, tokens: u64) -> Result<(), Duration> {
+        if limits.is_empty() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = self.lock();
+        let mut wait = Duration::ZERO;
+        for (key, limit) in &limits {
+            let bucket = buckets.entry(key.clone()).or_insert_with(|| Bucket::full(limit, now));
+            bucket.refill(limit, now);
+            wait = wait.max(bucket.wait(limit, tokens));
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        for (key, _) in &limits {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.take(tokens);
+            }
+        }
+        Ok(())
+    }
+
+    /// The longest wait any applicable limit allows.
+    pub fn max_wait(provider: &Provider, model_id: &str) -> Duration {
+        let ms = limits(provider, model_id).iter().map(|(_, l)| l.max_wait_ms).max().unwrap_or(0);
+        Duration::from_millis(ms)
+    }
+
+    /// Buckets with no request left, for routing.
+    pub fn exhausted(&self, providers: &[Provider]) -> Exhausted {
+        let now = Instant::now();
+        let mut buckets = self.lock();
+        let mut exhausted = HashSet::new();
+        for provider in providers {
+            for model in &provider.models {
+                for (key, limit) in limits(provider, &model.id) {
+                    if let Some(bucket) = buckets.get_mut(&key) {
+                        bucket.refill(limit, now);
+                        if !bucket.wait(limit, 1).is_zero() {
+                            exhausted.insert((provider.id.clone(), model.id.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        Exhausted(exhausted)
+    }
+
+    /// Remaining capacity of every configured limit.
+    pub fn status(&self, providers: &[Provider]) -> Vec<LimitStatus> {
+        let now = Instant::now();
+        let mut buckets = self.lock();
+        let mut status = Vec::new();
+        for provider in providers.iter().filter(|p| p.enabled) {
+            let scopes = std::iter::once((None, provider.rate_limit.as_ref())).chain(
+                provider.models.iter().map(|m| (Some(m.id.clone()), m.rate_limit.as_ref())),
+            );
+            for (model, limit) in scopes {
+                let Some(limit) = limit else { continue };
+                let bucket = buckets
+                    .entry((provider.id.clone(), model.clone()))
+                    .or_insert_with(|| Bucket::full(limit, now));
+                bucket.refill(limit, now);
+                status.push(LimitStatus {
+                    provider_id: provider.id.clone(),
+                    model,
+                    rpm: limit.rpm,
+                    tpm: limit.tpm,
+                    remaining_requests: limit.rpm.map(|_| bucket.requests.max(0.0) as u32),
+                    remaining_tokens: limit.tpm.map(|_| bucket.tokens.max(0.0) as u64),
+                });
+            }
+        }
+        status
+    }
+}
+
+/// Provider/model pairs with an empty bucket; the router tries them last.
+#[derive(Debug, Clone, Default)]
+pub struct Exhausted(HashSet<(String, String)>);
+
+impl Exhausted {
+    pub fn contains(&self, provider_id: &str, model: &str) -> bool {
+        self.0.contains(&(provider_id.to_string(), model.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn provider(limit: RateLimit) -> Provider {
+        let mut provider = Config::default().providers[0].clone();
+        provider.models.truncate(1);
+        provider.rate_limit = Some(limit);
+        provider
+    }
+
+    #[test]
+    fn test_requests_per_minute() {
+        let limiter = RateLimiter::default();
+        let provider = provider(RateLimit { rpm: Some(2), ..RateLimit::default() });
+        let model = provider.models[0].id.clone();
+
+        assert_eq!(limiter.try_take(&provider, &model, 0), Ok(()));
+        assert_eq!(limiter.try_take(&provider, &model, 0), Ok(()));
+        let wait = limiter.try_take(&provider, &model, 0).unwrap_err();
+        // One request refills every 30 seconds
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+        assert!(limiter.exhausted(std::slice::from_ref(&provider)).contains(&provider.id, &model));
+
+        let status = limiter.status(std::slice::from_ref(&provider));
+        assert_eq!(status[0].remaining_requests, Some(0));
+        assert_eq!(status[0].model, None);
+    }
+
+    #[test]
+    fn test_model_tokens_per_minute() {
+        let limiter = RateLimiter::default();
+        let mut provider = provider(RateLimit::default());
+        provider.rate_limit = None;
+        provider.models[0].rate_limit = Some(RateLimit { tpm: Some(1000), max_wait_ms: 500, ..RateLimit::default() });
+        let model = provider.models[0].id.clone();
+
+        assert_eq!(limiter.try_take(&provider, &model, 800), Ok(()));
+        // 600 more tokens need 400 to refill: 24 seconds
+        let wait = limiter.try_take(&provider, &model, 600).unwrap_err();
+        assert!(wait > Duration::from_secs(23) && wait <= Duration::from_secs(24));
+        assert_eq!(limiter.try_take(&provider, &model, 100), Ok(()));
+        assert_eq!(RateLimiter::max_wait(&provider, &model), Duration::from_millis(500));
+
+        // Other models of the provider are not limited
+        assert_eq!(limiter.try_take(&provider, "other-model", 5000), Ok(()));
+    }
+}
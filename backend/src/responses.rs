@@ -0,0 +1,638 @@
+//! OpenAI Responses API (`/v1/responses`) translation.
+//!
+//! Requests are normalized into chat messages for scoring and routing, and
+//! chat completions (buffered or streamed) are converted back into Response
+//! objects and events. Completed conversations are kept in `AppState` so that
+//! `previous_response_id` works with any provider.
+
+use crate::state::AppState;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// The chat history behind a Responses request, kept so its result can be
+/// stored for later `previous_response_id` lookups.
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    /// Messages so far. `instructions` are not part of it; they don't carry over.
+    pub messages: Vec<Value>,
+    pub previous_response_id: Option<String>,
+    /// Whether the result should be stored (`store`, default true).
+    pub store: bool,
+}
+
+pub fn new_response_id() -> String {
+    format!("resp_{}", uuid::Uuid::new_v4().simple())
+}
+
+// ---------------------------------------------------------------------------
+// Requests: Responses -> chat
+// ---------------------------------------------------------------------------
+
+/// Convert a message `content` (string or list of input parts) into chat content.
+fn content_to_chat(content: &Value) -> Value {
+    let Value::Array(parts) = content else {
+        return content.clone();
+    };
+    let parts: Vec<Value> = parts
+        .iter()
+        .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+            Some("input_text") | Some("output_text") | Some("text") => {
+                Some(json!({"type": "text", "text": part.get("text").cloned().unwrap_or(Value::Null)}))
+            }
+            Some("input_image") => {
+                let url = part.get("image_url").and_then(|u| u.as_str())?;
+                Some(json!({"type": "image_url", "image_url": {"url": url}}))
+            }
+            _ => None,
+        })
+        .collect();
+    // Plain text keeps the message usable by providers without multi-part support
+    if parts.iter().all(|p| p["type"] == "text") {
+        Value::String(parts.iter().filter_map(|p| p["text"].as_str()).collect::<Vec<_>>().join("\n"))
+    } else {
+        Value::Array(parts)
+    }
+}
+
+/// Convert the `input` of a Responses request into chat messages.
+pub fn input_to_messages(input: &Value) -> Vec<Value> {
+    let items = match input {
+        Value::String(text) => return vec![json!({"role": "user", "content": text})],
+        Value::Array(items) => items,
+        _ => return Vec::new(),
+    };
+
+    let mut messages: Vec<Value> = Vec::new();
+    for item in items {
+        match item.get("type").and_then(|t| t.as_str()).unwrap_or("message") {
+            "message" => {
+                let role = item.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+                let content = content_to_chat(item.get("content").unwrap_or(&Value::Null));
+                messages.push(json!({"role": role, "content": content}));
+            }
+            "function_call" => {
+                let call = json!({
+                    "id": item.get("call_id").cloned().unwrap_or(Value::Null),
+                    "type": "function",
+                    "function": {
+                        "name": item.get("name").cloned().unwrap_or(Value::Null),
+                        "arguments": item.get("arguments").cloned().unwrap_or_else(|| json!("{}")),
+                    },
+                });
+                // Consecutive calls belong to one assistant turn
+                match messages.last_mut() {
+                    Some(last) if last["role"] == "assistant" && last["tool_calls"].is_array() => {
+                        last["tool_calls"].as_array_mut().unwrap().push(call);
+                    }
+                    Some(last) if last["role"] == "assistant" && last.get("tool_calls").is_none() => {
+                        last["tool_calls"] = json!([call]);
+                    }
+                    _ => messages.push(json!({"role": "assistant", "content": null, "tool_calls": [call]})),
+                }
+            }
+            "function_call_output" => {
+                let output = match item.get("output") {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                };
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": item.get("call_id").cloned().unwrap_or(Value::Null),
+                    "content": output,
+                }));
+            }
+            // Reasoning items and built-in tool calls have no chat equivalent
+            _ => {}
+        }
+    }
+    messages
+}
+
+/// Chat parameters for a Responses request. Built-in tools (web search, file
+/// search, ...) only work on native providers and are dropped here.
+pub fn request_params(body: &Value) -> HashMap<String, Value> {
+    let mut extra = HashMap::new();
+    for (from, to) in [
+        ("temperature", "temperature"),
+        ("top_p", "top_p"),
+        ("max_output_tokens", "max_tokens"),
+        ("stream", "stream"),
+        ("parallel_tool_calls", "parallel_tool_calls"),
+        ("user", "user"),
+        ("metadata", "metadata"),
+    ] {
+        if let Some(v) = body.get(from).filter(|v| !v.is_null()) {
+            extra.insert(to.to_string(), v.clone());
+        }
+    }
+
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        let functions: Vec<Value> = tools
+            .iter()
+            .filter(|t| t.get("type").and_then(|t| t.as_str()) == Some("function"))
+            .map(|t| {
+                let mut function = json!({"name": t.get("name").cloned().unwrap_or(Value::Null)});
+                for key in ["description", "parameters", "strict"] {
+                    if let Some(v) = t.get(key).filter(|v| !v.is_null()) {
+                        function[key] = v.clone();
+                    }
+                }
+                json!({"type": "function", "function": function})
+            })
+            .collect();
+        if !functions.is_empty() {
+            extra.insert("tools".to_string(), Value::Array(functions));
+        }
+    }
+    match body.get("tool_choice") {
+        Some(choice @ Value::String(_)) => {
+            extra.insert("tool_choice".to_string(), choice.clone());
+        }
+        Some(choice) if choice.get("type").and_then(|t| t.as_str()) == Some("function") => {
+            extra.insert(
+                "tool_choice".to_string(),
+                json!({"type": "function", "function": {"name": choice.get("name").cloned().unwrap_or(Value::Null)}}),
+            );
+        }
+        _ => {}
+    }
+
+    match body.pointer("/text/format") {
+        Some(format) if format["type"] == "json_schema" => {
+            let mut schema = Map::new();
+            for key in ["name", "schema", "strict", "description"] {
+                if let Some(v) = format.get(key) {
+                    schema.insert(key.to_string(), v.clone());
+                }
+            }
+            extra.insert("response_format".to_string(), json!({"type": "json_schema", "json_schema": schema}));
+        }
+        Some(format) if format["type"] == "json_object" => {
+            extra.insert("response_format".to_string(), json!({"type": "json_object"}));
+        }
+        _ => {}
+    }
+    extra
+}
+
+// ---------------------------------------------------------------------------
+// Responses: chat -> Responses
+// ---------------------------------------------------------------------------
+
+fn message_item(text: &str) -> Value {
+    json!({
+        "type": "message",
+        "id": format!("msg_{}", uuid::Uuid::new_v4().simple()),
+        "status": "completed",
+        "role": "assistant",
+        "content": [{"type": "output_text", "text": text, "annotations": []}],
+    })
+}
+
+fn function_call_item(call_id: &Value, name: &Value, arguments: &str) -> Value {
+    json!({
+        "type": "function_call",
+        "id": format!("fc_{}", uuid::Uuid::new_v4().simple()),
+        "status": "completed",
+        "call_id": call_id,
+        "name": name,
+        "arguments": arguments,
+    })
+}
+
+/// Assemble a Response object.
+fn response_object(
+    id: &str,
+    model: &str,
+    created_at: i64,
+    finish_reason: Option<&str>,
+    output: Vec<Value>,
+    usage: (u64, u64),
+    previous_response_id: Option<&str>,
+) -> Value {
+    let (status, incomplete_details) = match finish_reason {
+        Some("length") => ("incomplete", json!({"reason": "max_output_tokens"})),
+        Some("content_filter") => ("incomplete", json!({"reason": "content_filter"})),
+        _ => ("completed", Value::Null),
+    };
+    json!({
+        "id": id,
+        "object": "response",
+        "created_at": created_at,
+        "status": status,
+        "incomplete_details": incomplete_details,
+        "model": model,
+        "output": output,
+        "previous_response_id": previous_response_id,
+        "usage": {
+            "input_tokens": usage.0,
+            "output_tokens": usage.1,
+            "total_tokens": usage.0 + usage.1,
+        },
+    })
+}
+
+/// Convert an OpenAI chat completion into a Response object.
+pub fn response_from_openai(resp: &Value, model: &str, previous_response_id: Option<&str>) -> Value {
+    let choice = &resp["choices"][0];
+    let message = &choice["message"];
+    let mut output = Vec::new();
+    if let Some(text) = message.get("content").and_then(|c| c.as_str()).filter(|t| !t.is_empty()) {
+        output.push(message_item(text));
+    }
+    for call in message.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+        output.push(function_call_item(
+            call.get("id").unwrap_or(&Value::Null),
+            call.pointer("/function/name").unwrap_or(&Value::Null),
+            call.pointer("/function/arguments").and_then(|a| a.as_str()).unwrap_or("{}"),
+        ));
+    }
+    let usage = &resp["usage"];
+    response_object(
+        &new_response_id(),
+        resp.get("model").and_then(|m| m.as_str()).unwrap_or(model),
+        chrono::Utc::now().timestamp(),
+        choice.get("finish_reason").and_then(|f| f.as_str()),
+        output,
+        (
+            usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+            usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+        ),
+        previous_response_id,
+    )
+}
+
+/// Token counts from a Response `usage` object.
+pub fn usage_tokens(usage: &Value) -> (Option<u64>, Option<u64>) {
+    (
+        usage.get("input_tokens").and_then(|v| v.as_u64()),
+        usage.get("output_tokens").and_then(|v| v.as_u64()),
+    )
+}
+
+/// Convert the output items of a Response into the chat assistant turn that produced them.
+pub fn output_to_messages(response: &Value) -> Vec<Value> {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for item in response.get("output").and_then(|o| o.as_array()).into_iter().flatten() {
+        match item.get("type").and_then(|t| t.as_str()) {
+            Some("message") => {
+                for part in item.get("content").and_then(|c| c.as_array()).into_iter().flatten() {
+                    if let Some(t) = part.get("text").and_then(|t| t.as_str()) {
+                        text.push_str(t);
+                    }
+                }
+            }
+            Some("function_call") => tool_calls.push(json!({
+                "id": item.get("call_id").cloned().unwrap_or(Value::Null),
+                "type": "function",
+                "function": {
+                    "name": item.get("name").cloned().unwrap_or(Value::Null),
+                    "arguments": item.get("arguments").cloned().unwrap_or_else(|| json!("{}")),
+                },
+            })),
+            _ => {}
+        }
+    }
+    if text.is_empty() && tool_calls.is_empty() {
+        return Vec::new();
+    }
+    let mut message = json!({"role": "assistant", "content": if text.is_empty() { Value::Null } else { Value::String(text) }});
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+    vec![message]
+}
+
+/// Store a completed response so later requests can continue from it.
+pub async fn remember(state: &AppState, conversation: &Conversation, response: &Value) {
+    if !conversation.store {
+        return;
+    }
+    let Some(id) = response.get("id").and_then(|i| i.as_str()) else {
+        return;
+    };
+    let mut messages = conversation.messages.clone();
+    messages.extend(output_to_messages(response));
+    state.store_response(id.to_string(), messages).await;
+}
+
+// ---------------------------------------------------------------------------
+// Streaming: chat chunks -> Responses events
+// ---------------------------------------------------------------------------
+
+struct OpenItem {
+    output_index: usize,
+    id: String,
+    /// Tool call id and name; None for the text message item.
+    call: Option<(Value, Value)>,
+    text: String,
+}
+
+/// Translates an OpenAI chunk stream into Responses API stream events.
+pub struct EventTranslator {
+    id: String,
+    model: String,
+    created_at: i64,
+    previous_response_id: Option<String>,
+    sequence: u64,
+    started: bool,
+    finished: bool,
+    text_item: Option<OpenItem>,
+    /// OpenAI tool call index -> open function call item
+    tool_items: Vec<(u64, OpenItem)>,
+    output: Vec<(usize, Value)>,
+    next_output: usize,
+    finish_reason: Option<String>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    completed: Option<Value>,
+}
+
+impl EventTranslator {
+    pub fn new(model: &str, previous_response_id: Option<String>) -> Self {
+        Self {
+            id: new_response_id(),
+            model: model.to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            previous_response_id,
+            sequence: 0,
+            started: false,
+            finished: false,
+            text_item: None,
+            tool_items: Vec::new(),
+            output: Vec::new(),
+            next_output: 0,
+            finish_reason: None,
+            input_tokens: None,
+            output_tokens: None,
+            completed: None,
+        }
+    }
+
+    /// The final Response object, once the stream has finished.
+    pub fn completed(&self) -> Option<&Value> {
+        self.completed.as_ref()
+    }
+
+    /// Translate the payload of one OpenAI `data:` line into Responses SSE frames.
+    pub fn translate(&mut self, data: &str) -> Vec<String> {
+        if data == "[DONE]" {
+            return self.finish();
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            return Vec::new();
+        };
+        let mut frames = Vec::new();
+
+        if !self.started {
+            self.started = true;
+            if let Some(model) = chunk.get("model").and_then(|m| m.as_str()) {
+                self.model = model.to_string();
+            }
+            let response = self.snapshot("in_progress", Vec::new());
+            frames.push(self.event("response.created", json!({"response": response})));
+            frames.push(self.event("response.in_progress", json!({"response": response})));
+        }
+
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object()) {
+            self.input_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64()).or(self.input_tokens);
+            self.output_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64()).or(self.output_tokens);
+        }
+
+        let choice = &chunk["choices"][0];
+        let delta = &choice["delta"];
+        if let Some(text) = delta.get("content").and_then(|c| c.as_str()).filter(|t| !t.is_empty()) {
+            if self.text_item.is_none() {
+                let item = self.open_item(None, &mut frames);
+                frames.push(self.event("response.content_part.added", json!({
+                    "item_id": item.id,
+                    "output_index": item.output_index,
+                    "content_index": 0,
+                    "part": {"type": "output_text", "text": "", "annotations": []},
+                })));
+                self.text_item = Some(item);
+            }
+            let item = self.text_item.as_mut().unwrap();
+            item.text.push_str(text);
+            let (item_id, output_index) = (item.id.clone(), item.output_index);
+            frames.push(self.event("response.output_text.delta", json!({
+                "item_id": item_id,
+                "output_index": output_index,
+                "content_index": 0,
+                "delta": text,
+            })));
+        }
+
+        for call in delta.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+            let index = call.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+            if !self.tool_items.iter().any(|(i, _)| *i == index) {
+                let call_id = call.get("id").cloned().unwrap_or(Value::Null);
+                let name = call.pointer("/function/name").cloned().unwrap_or(Value::Null);
+                let item = self.open_item(Some((call_id, name)), &mut frames);
+                self.tool_items.push((index, item));
+            }
+            let Some(args) = call.pointer("/function/arguments").and_then(|a| a.as_str()).filter(|a| !a.is_empty()) else {
+                continue;
+            };
+            let item = &mut self.tool_items.iter_mut().find(|(i, _)| *i == index).unwrap().1;
+            item.text.push_str(args);
+            let (item_id, output_index) = (item.id.clone(), item.output_index);
+            frames.push(self.event("response.function_call_arguments.delta", json!({
+                "item_id": item_id,
+                "output_index": output_index,
+                "delta": args,
+            })));
+        }
+
+        if let Some(reason) = choice.get("finish_reason").and_then(|f| f.as_str()) {
+            self.finish_reason = Some(reason.to_string());
+        }
+        frames
+    }
+
+    /// Close open items and emit the terminal event. Only the first call emits anything.
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.finished || !self.started {
+            return Vec::new();
+        }
+        self.finished = true;
+        let mut frames = Vec::new();
+
+        if let Some(item) = self.text_item.take() {
+            let part = json!({"type": "output_text", "text": item.text, "annotations": []});
+            frames.push(self.event("response.output_text.done", json!({
+                "item_id": item.id, "output_index": item.output_index, "content_index": 0, "text": item.text,
+            })));
+            frames.push(self.event("response.content_part.done", json!({
+                "item_id": item.id, "output_index": item.output_index, "content_index": 0, "part": part,
+            })));
+            let mut done = message_item(&item.text);
+            done["id"] = json!(item.id);
+            frames.push(self.event("response.output_item.done", json!({"output_index": item.output_index, "item": done})));
+            self.output.push((item.output_index, done));
+        }
+        for (_, item) in std::mem::take(&mut self.tool_items) {
+            let (call_id, name) = item.call.clone().unwrap_or((Value::Null, Value::Null));
+            frames.push(self.event("response.function_call_arguments.done", json!({
+                "item_id": item.id, "output_index": item.output_index, "arguments": item.text,
+            })));
+            let mut done = function_call_item(&call_id, &name, &item.text);
+            done["id"] = json!(item.id);
+            frames.push(self.event("response.output_item.done", json!({"output_index": item.output_index, "item": done})));
+            self.output.push((item.output_index, done));
+        }
+
+        self.output.sort_by_key(|(index, _)| *index);
+        let output: Vec<Value> = self.output.iter().map(|(_, item)| item.clone()).collect();
+        let response = response_object(
+            &self.id,
+            &self.model,
+            self.created_at,
+            self.finish_reason.as_deref(),
+            output,
+            (self.input_tokens.unwrap_or(0), self.output_tokens.unwrap_or(0)),
+            self.previous_response_id.as_deref(),
+        );
+        let kind = if response["status"] == "incomplete" { "response.incomplete" } else { "response.completed" };
+        frames.push(self.event(kind, json!({"response": response})));
+        self.completed = Some(response);
+        frames
+    }
+
+    fn open_item(&mut self, call: Option<(Value, Value)>, frames: &mut Vec<String>) -> OpenItem {
+        let output_index = self.next_output;
+        self.next_output += 1;
+        let item = match &call {
+            Some((call_id, name)) => json!({
+                "type": "function_call",
+                "id": format!("fc_{}", uuid::Uuid::new_v4().simple()),
+                "status": "in_progress",
+                "call_id": call_id,
+                "name": name,
+                "arguments": "",
+            }),
+            None => json!({
+                "type": "message",
+                "id": format!("msg_{}", uuid::Uuid::new_v4().simple()),
+                "status": "in_progress",
+                "role": "assistant",
+                "content": [],
+            }),
+        };
+        let id = item["id"].as_str().unwrap_or_default().to_string();
+        frames.push(self.event("response.output_item.added", json!({"output_index": output_index, "item": item})));
+        OpenItem { output_index, id, call, text: String::new() }
+    }
+
+    fn snapshot(&self, status: &str, output: Vec<Value>) -> Value {
+        let mut response = response_object(
+            &self.id, &self.model, self.created_at, None, output, (0, 0), self.previous_response_id.as_deref(),
+        );
+        response["status"] = json!(status);
+        response["usage"] = Value::Null;
+        response
+    }
+
+    fn event(&mut self, kind: &str, mut body: Value) -> String {
+        body["type"] = json!(kind);
+        body["sequence_number"] = json!(self.sequence);
+        self.sequence += 1;
+        format!("event: {}\ndata: {}\n\n", kind, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_to_messages() {
+        let input = json!([
+            {"role": "user", "content": [
+                {"type": "input_text", "text": "What's this?"},
+                {"type": "input_image", "image_url": "data:image/png;base64,AAAA"}
+            ]},
+            {"type": "function_call", "call_id": "call_1", "name": "lookup", "arguments": "{}"},
+            {"type": "function_call", "call_id": "call_2", "name": "lookup", "arguments": "{\"q\":1}"},
+            {"type": "function_call_output", "call_id": "call_1", "output": "found"},
+            {"type": "reasoning", "summary": []}
+        ]);
+        let messages = input_to_messages(&input);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["content"][1]["image_url"]["url"], "data:image/png;base64,AAAA");
+        assert_eq!(messages[1]["tool_calls"].as_array().unwrap().len(), 2);
+        assert_eq!(messages[2], json!({"role": "tool", "tool_call_id": "call_1", "content": "found"}));
+
+        assert_eq!(input_to_messages(&json!("Hi")), vec![json!({"role": "user", "content": "Hi"})]);
+    }
+
+    #[test]
+    fn test_request_params() {
+        let body = json!({
+            "max_output_tokens": 100,
+            "tools": [
+                {"type": "function", "name": "lookup", "parameters": {"type": "object"}},
+                {"type": "web_search_preview"}
+            ],
+            "tool_choice": {"type": "function", "name": "lookup"},
+            "text": {"format": {"type": "json_schema", "name": "out", "schema": {"type": "object"}}}
+        });
+        let extra = request_params(&body);
+        assert_eq!(extra["max_tokens"], 100);
+        assert_eq!(extra["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(extra["tools"][0]["function"]["name"], "lookup");
+        assert_eq!(extra["tool_choice"]["function"]["name"], "lookup");
+        assert_eq!(extra["response_format"]["json_schema"]["name"], "out");
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        let chat = json!({
+            "model": "gpt-test",
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi", "tool_calls": [
+                {"id": "call_1", "type": "function", "function": {"name": "lookup", "arguments": "{}"}}
+            ]}, "finish_reason": "tool_calls"}],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 2}
+        });
+        let response = response_from_openai(&chat, "gpt-test", Some("resp_prev"));
+        assert_eq!(response["object"], "response");
+        assert_eq!(response["status"], "completed");
+        assert_eq!(response["output"][0]["content"][0]["text"], "Hi");
+        assert_eq!(response["output"][1]["call_id"], "call_1");
+        assert_eq!(response["usage"]["input_tokens"], 9);
+        assert_eq!(response["previous_response_id"], "resp_prev");
+
+        let messages = output_to_messages(&response);
+        assert_eq!(messages[0]["content"], "Hi");
+        assert_eq!(messages[0]["tool_calls"][0]["id"], "call_1");
+    }
+
+    #[test]
+    fn test_event_translator() {
+        let mut t = EventTranslator::new("gpt-test", None);
+        let mut frames = Vec::new();
+        frames.extend(t.translate(r#"{"model":"gpt-test","choices":[{"index":0,"delta":{"role":"assistant","content":"He"}}]}"#));
+        frames.extend(t.translate(r#"{"choices":[{"index":0,"delta":{"content":"llo"},"finish_reason":"length"}]}"#));
+        frames.extend(t.translate(r#"{"choices":[],"usage":{"prompt_tokens":4,"completion_tokens":2}}"#));
+        frames.extend(t.translate("[DONE]"));
+
+        let events: Vec<&str> = frames
+            .iter()
+            .map(|f| f.lines().next().unwrap().trim_start_matches("event: "))
+            .collect();
+        assert_eq!(events, vec![
+            "response.created", "response.in_progress",
+            "response.output_item.added", "response.content_part.added",
+            "response.output_text.delta", "response.output_text.delta",
+            "response.output_text.done", "response.content_part.done", "response.output_item.done",
+            "response.incomplete",
+        ]);
+        let completed = t.completed().unwrap();
+        assert_eq!(completed["output"][0]["content"][0]["text"], "Hello");
+        assert_eq!(completed["usage"]["output_tokens"], 2);
+        assert_eq!(completed["incomplete_details"]["reason"], "max_output_tokens");
+        assert!(t.finish().is_empty());
+    }
+}
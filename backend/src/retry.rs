@@ -0,0 +1,245 @@
+//! Retries on the same provider: exponential backoff with jitter, and the
+//! wait a rate-limited or overloaded upstream asks for.
+//!
+//! Upstreams answering 429 or 503 usually say when to come back, through
+//! `Retry-After` (seconds or an HTTP date), `retry-after-ms`, or the
+//! `x-ratelimit-reset-*` family (`"1s"`, `"6m0s"`, `"20ms"`). That wait is used
+//! instead of the backoff; when it is longer than `max_delay_ms` the router
+//! moves on to the next candidate rather than sleeping.
+
+use crate::config::Provider;
+use crate::error::{ErrorAction, ErrorClass, ErrorPolicy, ProviderError};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Headers carrying the time until a rate limit resets.
+const RESET_HEADERS: &[&str] = &["x-ratelimit-reset-requests", "x-ratelimit-reset-tokens", "x-ratelimit-reset"];
+
+/// Per-provider retry settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts on this provider, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for every further retry.
+    pub base_delay_ms: u64,
+    /// Upper bound on any single wait, including upstream-requested ones.
+    pub max_delay_ms: u64,
+    /// Random spread applied to the backoff, as a fraction of the delay (0-1).
+    pub jitter: f64,
+    /// Failure classes retried on this provider.
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 200,
+            max_delay_ms: 10_000,
+            jitter: 0.2,
+            retry_on: vec![ErrorClass::RateLimited, ErrorClass::ServerError, ErrorClass::Timeout, ErrorClass::Connection],
+        }
+    }
+}
+
+/// What to do after a failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Wait, then try the same provider again.
+    Retry(Duration),
+    Fallthrough,
+    Abort,
+}
+
+impl RetryPolicy {
+    /// The provider's own policy, or one that only retries the classes the
+    /// error policy marks as `retry`, `max_retries` times.
+    pub fn for_provider(provider: &Provider, errors: &ErrorPolicy) -> Self {
+        provider.retry.clone().unwrap_or_else(|| Self {
+            max_attempts: errors.max_retries + 1,
+            retry_on: Vec::new(),
+            ..Self::default()
+        })
+    }
+
+    /// Decide what follows failed attempt number `attempt` (1-based). An
+    /// `abort` in the error policy always wins; otherwise the class is retried
+    /// when either policy asks for it and attempts remain.
+    pub fn next_step(&self, errors: &ErrorPolicy, failure: &ProviderError, attempt: u32) -> Step {
+        let class = failure.attempt.class;
+        let action = errors.action(class);
+        if action == ErrorAction::Abort {
+            return Step::Abort;
+        }
+        let retryable = action == ErrorAction::Retry || self.retry_on.contains(&class);
+        if !retryable || attempt >= self.max_attempts {
+            return Step::Fallthrough;
+        }
+        match failure.retry_after {
+            Some(wait) if wait > Duration::from_millis(self.max_delay_ms) => {
+                tracing::info!(
+                    provider = %failure.attempt.provider,
+                    wait_ms = wait.as_millis() as u64,
+                    "Upstream asked to wait longer than max_delay_ms, not retrying"
+                );
+                Step::Fallthrough
+            }
+            Some(wait) => Step::Retry(wait),
+            None => Step::Retry(self.backoff(attempt)),
+        }
+    }
+
+    /// Backoff before retry number `retry` (1-based): `base * 2^(retry-1)`,
+    /// spread by `jitter` and capped at `max_delay_ms`.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.base_delay_ms as f64 * 2f64.powi(retry.saturating_sub(1).min(30) as i32);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let spread = 1.0 + jitter * (fastrand::f64() * 2.0 - 1.0);
+        let delay = (exponential * spread).min(self.max_delay_ms as f64);
+        Duration::from_millis(delay.max(0.0) as u64)
+    }
+}
+
+/// The wait an upstream asked for on a 429 or 503 reply, if any.
+/// `Retry-After` takes precedence; otherwise the longest rate-limit reset.
+pub fn retry_after(status: u16, headers: &HeaderMap) -> Option<Duration> {
+    if status != 429 && status != 503 {
+        return None;
+    }
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Some(Duration::from_secs_f64(ms.max(0.0) / 1000.0));
+    }
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<f64>() {
+            return Some(Duration::from_secs_f64(secs.max(0.0)));
+        }
+        if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+            let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+            return Some(wait.to_std().unwrap_or(Duration::ZERO));
+        }
+    }
+    RESET_HEADERS.iter().filter_map(|name| header(name).and_then(parse_reset)).max()
+}
+
+/// Parse a rate-limit reset value: a duration such as `"1m30s"` or `"250ms"`,
+/// plain seconds, or a Unix timestamp.
+fn parse_reset(value: &str) -> Option<Duration> {
+    if let Ok(number) = value.parse::<f64>() {
+        // Large values are absolute reset times rather than relative waits
+        if number > 1_000_000_000.0 {
+            let wait = number - chrono::Utc::now().timestamp() as f64;
+            return Some(Duration::from_secs_f64(wait.max(0.0)));
+        }
+        return Some(Duration::from_secs_f64(number.max(0.0)));
+    }
+    parse_duration(value)
+}
+
+/// Parse a Go-style duration string (`"6m0s"`, `"1.5s"`, `"20ms"`).
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest.find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(rest.len());
+        let seconds_per_unit = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" | "µs" => 1e-6,
+            "ns" => 1e-9,
+            _ => return None,
+        };
+        total += number * seconds_per_unit;
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Attempt;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("1h2m"), Some(Duration::from_secs(3720)));
+        assert_eq!(parse_duration("soon"), None);
+    }
+
+    #[test]
+    fn test_retry_after_headers() {
+        assert_eq!(retry_after(429, &headers(&[("retry-after", "2")])), Some(Duration::from_secs(2)));
+        assert_eq!(retry_after(503, &headers(&[("retry-after-ms", "150")])), Some(Duration::from_millis(150)));
+        assert_eq!(
+            retry_after(429, &headers(&[("x-ratelimit-reset-requests", "1s"), ("x-ratelimit-reset-tokens", "6m0s")])),
+            Some(Duration::from_secs(360))
+        );
+        assert_eq!(
+            retry_after(429, &headers(&[("retry-after", "Thu, 01 Jan 1970 00:00:00 GMT")])),
+            Some(Duration::ZERO)
+        );
+        // Only rate-limited and unavailable replies carry a usable hint
+        assert_eq!(retry_after(500, &headers(&[("retry-after", "2")])), None);
+        assert_eq!(retry_after(429, &HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = RetryPolicy { base_delay_ms: 100, max_delay_ms: 1000, jitter: 0.0, ..RetryPolicy::default() };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(10), Duration::from_millis(1000));
+
+        let jittered = RetryPolicy { jitter: 0.5, ..policy };
+        for _ in 0..50 {
+            let delay = jittered.backoff(2);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(300));
+        }
+    }
+
+    #[test]
+    fn test_next_step() {
+        let errors = ErrorPolicy::default();
+        let policy = RetryPolicy { jitter: 0.0, ..RetryPolicy::default() };
+        let failure = |status: u16, retry_after: Option<Duration>| ProviderError {
+            retry_after,
+            ..Attempt::upstream("p", status, "").into()
+        };
+
+        assert_eq!(policy.next_step(&errors, &failure(500, None), 1), Step::Retry(Duration::from_millis(200)));
+        assert_eq!(policy.next_step(&errors, &failure(500, None), 3), Step::Fallthrough);
+        assert_eq!(
+            policy.next_step(&errors, &failure(429, Some(Duration::from_secs(1))), 1),
+            Step::Retry(Duration::from_secs(1))
+        );
+        assert_eq!(policy.next_step(&errors, &failure(429, Some(Duration::from_secs(60))), 1), Step::Fallthrough);
+        assert_eq!(policy.next_step(&errors, &failure(400, None), 1), Step::Abort);
+        assert_eq!(policy.next_step(&errors, &failure(401, None), 1), Step::Fallthrough);
+
+        // Without a provider policy only `retry` classes are retried
+        let errors = ErrorPolicy { server_error: ErrorAction::Retry, max_retries: 1, ..ErrorPolicy::default() };
+        let fallback = RetryPolicy { max_attempts: errors.max_retries + 1, retry_on: Vec::new(), jitter: 0.0, ..RetryPolicy::default() };
+        assert!(matches!(fallback.next_step(&errors, &failure(500, None), 1), Step::Retry(_)));
+        assert_eq!(fallback.next_step(&errors, &failure(500, None), 2), Step::Fallthrough);
+        assert_eq!(fallback.next_step(&errors, &failure(429, None), 1), Step::Fallthrough);
+    }
+}
@@ -1,6 +1,27 @@
-use crate::config::{Config, Provider, Tier};
+use crate::balance::{self, Load, LoadBalancing};
+use crate::breaker::OpenCircuits;
+use crate::config::{CandidateOrder, Config, Provider, Tier};
+use crate::estimate::ExpectedUsage;
+use crate::latency::Latencies;
+use crate::ratelimit::Exhausted;
 use crate::scorer::ComplexityTier;
 use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// Live provider state the router takes into account.
+#[derive(Debug, Clone, Default)]
+pub struct Availability {
+    /// Provider/model pairs whose circuit is not closed; tried last.
+    pub open_circuits: OpenCircuits,
+    /// Ids of providers failing their health checks; skipped.
+    pub down: HashSet<String>,
+    /// Observed latency, for profiles ordering by it.
+    pub latency: Latencies,
+    /// In-flight requests and recent errors, for load balancing.
+    pub load: Load,
+    /// Provider/model pairs out of rate-limit capacity; tried last.
+    pub rate_limited: Exhausted,
+}
 
 pub struct Router;
 
@@ -16,7 +37,7 @@ impl Router {
         complexity: Option<ComplexityTier>,
         use_agentic: bool,
     ) -> Vec<Provider> {
-        Self::route_request_with_profile(config, model_id, complexity, None, use_agentic)
+        Self::route_request_with_profile(config, model_id, complexity, None, use_agentic, &Availability::default(), None)
     }
 
     pub fn route_request_with_profile(
@@ -25,6 +46,8 @@ impl Router {
         complexity: Option<ComplexityTier>,
         profile_override: Option<&str>,
         use_agentic: bool,
+        availability: &Availability,
+        usage: Option<&ExpectedUsage>,
     ) -> Vec<Provider> {
         // 1. Find the profile (override or active)
         let profile_name = profile_override.unwrap_or(&config.active_profile);
@@ -117,28 +140,94 @@ impl Router {
                 .collect();
         }
 
-        // 6. Sort candidates
-        candidates.sort_by(|a, b| {
+        // 6. Sort candidates: by tier, then as the profile orders within a tier.
+        //    Cost is the expected cost of the whole request when its usage is
+        //    known, otherwise the input price.
+        let cost = |p: &Provider| match usage {
+            Some(usage) => usage.cost(p, effective_model_id).unwrap_or(f64::MAX),
+            None => p.models.iter().find(|m| m.id == effective_model_id).map(|m| m.input_cost_per_1m).unwrap_or(f64::MAX),
+        };
+        let latency = |p: &Provider| {
+            availability.latency
+                .get(&(p.id.clone(), effective_model_id.to_string()))
+                .map(|s| s.routing_ms())
+        };
+        let max_cost = candidates.iter().map(cost).filter(|c| *c < f64::MAX).fold(0.0, f64::max);
+        let max_latency = candidates.iter().filter_map(latency).fold(0.0, f64::max);
+        let weight = profile.latency_weight.unwrap_or(0.5).clamp(0.0, 1.0);
+        // Cost and latency relative to the priciest/slowest candidate.
+        // Unmeasured providers count as fastest so they get tried and measured.
+        let weighted = |p: &Provider| {
+            let relative_cost = if max_cost > 0.0 { cost(p).min(max_cost) / max_cost } else { 0.0 };
+            let relative_latency = match latency(p) {
+                Some(ms) if max_latency > 0.0 => ms / max_latency,
+                _ => 0.0,
+            };
+            (1.0 - weight) * relative_cost + weight * relative_latency
+        };
+
+        // Candidates that rank equal are interchangeable; priority breaks the tie
+        let rank = |a: &Provider, b: &Provider| {
             let tier_a_idx = effective_tiers.iter().position(|t| t == &a.tier).unwrap_or(usize::MAX);
             let tier_b_idx = effective_tiers.iter().position(|t| t == &b.tier).unwrap_or(usize::MAX);
 
+            let by_cost = || cost(a).partial_cmp(&cost(b)).unwrap_or(Ordering::Equal);
             match tier_a_idx.cmp(&tier_b_idx) {
-                Ordering::Equal => {
-                    let cost_a = a.models.iter().find(|m| m.id == effective_model_id).map(|m| m.input_cost_per_1m).unwrap_or(f64::MAX);
-                    let cost_b = b.models.iter().find(|m| m.id == effective_model_id).map(|m| m.input_cost_per_1m).unwrap_or(f64::MAX);
-
-                    match cost_a.partial_cmp(&cost_b).unwrap_or(Ordering::Equal) {
-                        Ordering::Equal => b.priority.cmp(&a.priority),
-                        ord => ord,
+                Ordering::Equal => match profile.ordering {
+                    CandidateOrder::Cost => by_cost(),
+                    CandidateOrder::Latency => {
+                        let latency_a = latency(a).unwrap_or(0.0);
+                        let latency_b = latency(b).unwrap_or(0.0);
+                        latency_a.partial_cmp(&latency_b).unwrap_or(Ordering::Equal).then_with(by_cost)
                     }
-                }
+                    CandidateOrder::Weighted => {
+                        weighted(a).partial_cmp(&weighted(b)).unwrap_or(Ordering::Equal).then_with(by_cost)
+                    }
+                },
                 ord => ord,
             }
+        };
+        candidates.sort_by(|a, b| rank(a, b).then_with(|| b.priority.cmp(&a.priority)));
+
+        // 7. Spread traffic across candidates that rank equal
+        if profile.load_balancing != LoadBalancing::None {
+            let mut start = 0;
+            while start < candidates.len() {
+                let len = candidates[start..]
+                    .iter()
+                    .take_while(|p| rank(p, &candidates[start]) == Ordering::Equal)
+                    .count();
+                balance::balance(&mut candidates[start..start + len], effective_model_id, profile.load_balancing, &availability.load);
+                start += len;
+            }
+        }
+
+        // 8. Skip providers that are down, unless nothing else is left
+        if candidates.iter().any(|p| !availability.down.contains(&p.id)) {
+            candidates.retain(|p| !availability.down.contains(&p.id));
+        }
+
+        // 9. Providers with an open circuit for this model go last, and before
+        //    them those out of rate-limit capacity (stable)
+        candidates.sort_by_key(|p| {
+            (
+                availability.open_circuits.contains(&p.id, effective_model_id),
+                availability.rate_limited.contains(&p.id, effective_model_id),
+            )
         });
 
         candidates
     }
 
+    /// Providers serving `model_id` as an embedding model, in the same tier/cost
+    /// order as chat routing under the active profile.
+    pub fn route_embedding_request(config: &Config, model_id: &str, availability: &Availability) -> Vec<Provider> {
+        Self::route_request_with_profile(config, model_id, None, None, false, availability, None)
+            .into_iter()
+            .filter(|p| p.models.iter().any(|m| m.id == model_id && m.embedding))
+            .collect()
+    }
+
     /// Given a config and complexity tier, resolve the effective model_id
     /// that should be used (after applying model_mapping).
     pub fn resolve_model_id<'a>(config: &'a Config, model_id: &'a str, complexity: Option<ComplexityTier>, use_agentic: bool) -> &'a str {
@@ -168,10 +257,8 @@ impl Router {
                 ComplexityTier::Complex => "complex",
                 ComplexityTier::Reasoning => "reasoning",
             };
-            if let Some(mapping) = mapping_source.get(tier_key) {
-                if !mapping.model_id.is_empty() {
-                    return &mapping.model_id;
-                }
+            if let Some(mapping) = mapping_source.get(tier_key).filter(|m| !m.model_id.is_empty()) {
+                return &mapping.model_id;
             }
         }
         model_id
@@ -191,7 +278,12 @@ fn default_provider_tiers_for_complexity(complexity: ComplexityTier) -> Vec<Tier
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::balance::LoadTracker;
+    use crate::breaker::{BreakerConfig, CircuitBreakers, Outcome};
     use crate::config::{ProviderType, Model, RoutingProfile};
+    use crate::estimate::OutputSource;
+    use crate::latency::LatencyStats;
+    use crate::ratelimit::{RateLimit, RateLimiter};
     use std::collections::HashMap;
 
     fn make_provider(id: &str, name: &str, tier: Tier, cost: f64, priority: u8) -> Provider {
@@ -200,7 +292,16 @@ mod tests {
             name: name.to_string(),
             provider_type: ProviderType::OpenAI,
             api_key: None,
+            api_keys: Vec::new(),
             endpoint: None,
+            api_version: None,
+            aws: None,
+            retry: None,
+            timeouts: None,
+            proxy: None,
+            ca_cert: None,
+            rate_limit: None,
+            weight: None,
             tier,
             enabled: true,
             priority,
@@ -213,6 +314,9 @@ mod tests {
                     context_window: 8192,
                     supports_vision: false,
                     supports_function_calling: true,
+                    deployment: None,
+                    embedding: false,
+                    rate_limit: None,
                 }
             ],
         }
@@ -227,6 +331,11 @@ mod tests {
             cache: None,
             agentic_mode: false,
             session: None,
+            error_policy: None,
+            circuit_breaker: None,
+            health_check: None,
+            virtual_keys: Vec::new(),
+            budgets: Vec::new(),
         }
     }
 
@@ -237,6 +346,9 @@ mod tests {
             allowed_tiers: tiers,
             model_mapping: HashMap::new(),
             agentic_model_mapping: HashMap::new(),
+            ordering: CandidateOrder::Cost,
+            latency_weight: None,
+            load_balancing: LoadBalancing::None,
         }
     }
 
@@ -282,6 +394,9 @@ mod tests {
             allowed_tiers: vec![Tier::Subscription, Tier::Cheap, Tier::Free],
             model_mapping: HashMap::new(),
             agentic_model_mapping: HashMap::new(),
+            ordering: CandidateOrder::Cost,
+            latency_weight: None,
+            load_balancing: LoadBalancing::None,
         }];
 
         let config = make_config(providers, profiles, "auto");
@@ -309,6 +424,9 @@ mod tests {
             allowed_tiers: vec![Tier::Subscription, Tier::Cheap, Tier::Free],
             model_mapping: HashMap::new(),
             agentic_model_mapping: HashMap::new(),
+            ordering: CandidateOrder::Cost,
+            latency_weight: None,
+            load_balancing: LoadBalancing::None,
         }];
 
         let config = make_config(providers, profiles, "auto");
@@ -333,6 +451,9 @@ mod tests {
             allowed_tiers: vec![Tier::Cheap],
             model_mapping: HashMap::new(),
             agentic_model_mapping: HashMap::new(),
+            ordering: CandidateOrder::Cost,
+            latency_weight: None,
+            load_balancing: LoadBalancing::None,
         }];
 
         let config = make_config(providers, profiles, "eco");
@@ -359,6 +480,9 @@ mod tests {
             allowed_tiers: vec![Tier::Subscription, Tier::Cheap, Tier::Free],
             model_mapping: HashMap::new(),
             agentic_model_mapping: HashMap::new(),
+            ordering: CandidateOrder::Cost,
+            latency_weight: None,
+            load_balancing: LoadBalancing::None,
         }];
 
         let config = make_config(providers, profiles, "auto");
@@ -370,4 +494,182 @@ mod tests {
         assert_eq!(candidates[0].id, "sub");
         assert_eq!(candidates[1].id, "cheap");
     }
+
+    #[test]
+    fn test_embedding_routing_skips_chat_models() {
+        let mut cheap = make_provider("cheap", "Cheap", Tier::Cheap, 0.02, 1);
+        cheap.models[0].id = "text-embedding-3-small".to_string();
+        cheap.models[0].embedding = true;
+        let mut sub = make_provider("sub", "Subscription", Tier::Subscription, 0.01, 1);
+        sub.models[0].id = "text-embedding-3-small".to_string();
+        sub.models[0].embedding = true;
+        // Same id but declared as a chat model: not an embedding candidate
+        let mut chat = make_provider("chat", "Chat", Tier::Subscription, 0.0, 1);
+        chat.models[0].id = "text-embedding-3-small".to_string();
+
+        let profiles = vec![make_profile("auto", "auto", vec![Tier::Cheap, Tier::Subscription])];
+        let config = make_config(vec![sub, chat, cheap], profiles, "auto");
+
+        let candidates = Router::route_embedding_request(&config, "text-embedding-3-small", &Availability::default());
+        let ids: Vec<&str> = candidates.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["cheap", "sub"]);
+    }
+
+    #[test]
+    fn test_open_circuit_tried_last() {
+        let providers = vec![
+            make_provider("p1", "Cheap 1", Tier::Cheap, 0.5, 1),
+            make_provider("p2", "Cheap 2", Tier::Cheap, 1.0, 1),
+        ];
+        let profiles = vec![make_profile("auto", "auto", vec![Tier::Cheap])];
+        let config = make_config(providers, profiles, "auto");
+
+        let breakers = CircuitBreakers::default();
+        let breaker_config = BreakerConfig { failure_threshold: 1, ..BreakerConfig::default() };
+        breakers.record("p1", "gpt-4", &breaker_config, Outcome::Failed);
+
+        let availability = Availability { open_circuits: breakers.open_circuits(), ..Availability::default() };
+        let candidates = Router::route_request_with_profile(&config, "gpt-4", None, None, false, &availability, None);
+        let ids: Vec<&str> = candidates.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["p2", "p1"]);
+    }
+
+    #[test]
+    fn test_rate_limited_tried_before_open_circuit() {
+        let mut providers = vec![
+            make_provider("p1", "Cheap 1", Tier::Cheap, 0.5, 1),
+            make_provider("p2", "Cheap 2", Tier::Cheap, 1.0, 1),
+            make_provider("p3", "Cheap 3", Tier::Cheap, 2.0, 1),
+        ];
+        providers[0].rate_limit = Some(RateLimit { rpm: Some(1), ..RateLimit::default() });
+        let limiter = RateLimiter::default();
+        assert!(limiter.try_take(&providers[0], "gpt-4", 0).is_ok());
+
+        let breakers = CircuitBreakers::default();
+        let breaker_config = BreakerConfig { failure_threshold: 1, ..BreakerConfig::default() };
+        breakers.record("p2", "gpt-4", &breaker_config, Outcome::Failed);
+
+        let availability = Availability {
+            open_circuits: breakers.open_circuits(),
+            rate_limited: limiter.exhausted(&providers),
+            ..Availability::default()
+        };
+        let profiles = vec![make_profile("auto", "auto", vec![Tier::Cheap])];
+        let config = make_config(providers, profiles, "auto");
+        let candidates = Router::route_request_with_profile(&config, "gpt-4", None, None, false, &availability, None);
+        let ids: Vec<&str> = candidates.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["p3", "p1", "p2"]);
+    }
+
+    #[test]
+    fn test_latency_ordering() {
+        let providers = vec![
+            make_provider("p1", "Cheap Slow", Tier::Cheap, 1.0, 1),
+            make_provider("p2", "Pricey Fast", Tier::Cheap, 2.0, 1),
+            make_provider("p3", "Unmeasured", Tier::Cheap, 3.0, 1),
+        ];
+        let mut profile = make_profile("fast", "fast", vec![Tier::Cheap]);
+        profile.ordering = CandidateOrder::Latency;
+        let config = make_config(providers, vec![profile], "fast");
+        let stats = |ms: f64| LatencyStats { latency_ms: ms, ttft_ms: None, samples: 5 };
+        let availability = Availability {
+            latency: Latencies::from([
+                (("p1".to_string(), "gpt-4".to_string()), stats(2000.0)),
+                (("p2".to_string(), "gpt-4".to_string()), stats(500.0)),
+            ]),
+            ..Availability::default()
+        };
+        let ids = |config: &Config| -> Vec<String> {
+            Router::route_request_with_profile(config, "gpt-4", None, None, false, &availability, None)
+                .into_iter()
+                .map(|p| p.id)
+                .collect()
+        };
+        // Unmeasured first so it gets sampled, then fastest
+        assert_eq!(ids(&config), vec!["p3", "p2", "p1"]);
+
+        // Weighted: p1 = 0.5*(1/3) + 0.5*1.0, p2 = 0.5*(2/3) + 0.5*0.25, p3 = 0.5*1.0 + 0
+        let mut weighted = config.clone();
+        weighted.profiles[0].ordering = CandidateOrder::Weighted;
+        assert_eq!(ids(&weighted), vec!["p2", "p3", "p1"]);
+        weighted.profiles[0].latency_weight = Some(0.0);
+        assert_eq!(ids(&weighted), vec!["p1", "p2", "p3"]);
+
+        // Cost ordering ignores latency
+        weighted.profiles[0].ordering = CandidateOrder::Cost;
+        weighted.profiles[0].latency_weight = None;
+        assert_eq!(ids(&weighted), vec!["p1", "p2", "p3"]);
+    }
+
+    #[test]
+    fn test_expected_cost_ordering() {
+        // p1 has the cheaper input, p2 the cheaper output
+        let mut p1 = make_provider("p1", "Cheap Input", Tier::Cheap, 1.0, 1);
+        p1.models[0].output_cost_per_1m = 10.0;
+        let mut p2 = make_provider("p2", "Cheap Output", Tier::Cheap, 2.0, 1);
+        p2.models[0].output_cost_per_1m = 3.0;
+        let profiles = vec![make_profile("auto", "auto", vec![Tier::Cheap])];
+        let config = make_config(vec![p1, p2], profiles, "auto");
+        let ids = |usage: Option<&ExpectedUsage>| -> Vec<String> {
+            Router::route_request_with_profile(&config, "gpt-4", None, None, false, &Availability::default(), usage)
+                .into_iter()
+                .map(|p| p.id)
+                .collect()
+        };
+        let usage = |prompt_tokens, output_tokens| ExpectedUsage {
+            prompt_tokens,
+            output_tokens,
+            output_source: OutputSource::Limit,
+        };
+
+        assert_eq!(ids(None), vec!["p1", "p2"]);
+        // 1k in + 1k out: p1 = 0.011, p2 = 0.005
+        assert_eq!(ids(Some(&usage(1000, 1000))), vec!["p2", "p1"]);
+        // 10k in + 100 out: p1 = 0.011, p2 = 0.0203
+        assert_eq!(ids(Some(&usage(10_000, 100))), vec!["p1", "p2"]);
+    }
+
+    #[test]
+    fn test_load_balancing_within_ties() {
+        let providers = vec![
+            make_provider("a1", "Account 1", Tier::Cheap, 1.0, 1),
+            make_provider("a2", "Account 2", Tier::Cheap, 1.0, 1),
+            make_provider("a3", "Account 3", Tier::Cheap, 1.0, 2),
+            make_provider("pricey", "Pricey", Tier::Cheap, 5.0, 9),
+        ];
+        let mut profile = make_profile("spread", "spread", vec![Tier::Cheap]);
+        profile.load_balancing = LoadBalancing::RoundRobin;
+        let config = make_config(providers, vec![profile], "spread");
+        let tracker = LoadTracker::default();
+
+        let mut firsts = Vec::new();
+        for _ in 0..3 {
+            let availability = Availability { load: tracker.snapshot(), ..Availability::default() };
+            let candidates = Router::route_request_with_profile(&config, "gpt-4", None, None, false, &availability, None);
+            // The pricier provider never joins the rotation
+            assert_eq!(candidates[3].id, "pricey");
+            firsts.push(candidates[0].id.clone());
+        }
+        assert_eq!(firsts, vec!["a3", "a1", "a2"]);
+    }
+
+    #[test]
+    fn test_down_provider_skipped() {
+        let providers = vec![
+            make_provider("p1", "Cheap 1", Tier::Cheap, 0.5, 1),
+            make_provider("p2", "Cheap 2", Tier::Cheap, 1.0, 1),
+        ];
+        let profiles = vec![make_profile("auto", "auto", vec![Tier::Cheap])];
+        let config = make_config(providers, profiles, "auto");
+
+        let availability = Availability { down: HashSet::from(["p1".to_string()]), ..Availability::default() };
+        let candidates = Router::route_request_with_profile(&config, "gpt-4", None, None, false, &availability, None);
+        let ids: Vec<&str> = candidates.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["p2"]);
+
+        // With every candidate down, they are still tried rather than failing outright
+        let availability = Availability { down: HashSet::from(["p1".to_string(), "p2".to_string()]), ..Availability::default() };
+        let candidates = Router::route_request_with_profile(&config, "gpt-4", None, None, false, &availability, None);
+        assert_eq!(candidates.len(), 2);
+    }
 }
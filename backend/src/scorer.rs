@@ -337,11 +337,9 @@ impl Scorer {
         let mut override_applied: Option<String> = None;
 
         // Override: token count > threshold → force Complex
-        if estimated_tokens > config.max_tokens_force_complex {
-            if (tier as u8) < (ComplexityTier::Complex as u8) {
-                tier = ComplexityTier::Complex;
-                override_applied = Some("token_count_force_complex".to_string());
-            }
+        if estimated_tokens > config.max_tokens_force_complex && (tier as u8) < (ComplexityTier::Complex as u8) {
+            tier = ComplexityTier::Complex;
+            override_applied = Some("token_count_force_complex".to_string());
         }
 
         // Override: structured output detected → minimum Medium
@@ -388,10 +386,11 @@ fn extract_text(messages: &[Value]) -> String {
                 Value::String(s) => parts.push(s.clone()),
                 Value::Array(arr) => {
                     for item in arr {
-                        if item.get("type").and_then(|t| t.as_str()) == Some("text") {
-                            if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                parts.push(text.to_string());
-                            }
+                        if item.get("type").and_then(|t| t.as_str()) != Some("text") {
+                            continue;
+                        }
+                        if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
+                            parts.push(text.to_string());
                         }
                     }
                 }
@@ -1,4 +1,16 @@
-use crate::config::Config;
+use crate::admin::AdminAuth;
+use crate::breaker::CircuitBreakers;
+use crate::budget::{self, SpendLedger};
+use crate::client::ClientPool;
+use crate::balance::LoadTracker;
+use crate::config::{Config, Provider};
+use crate::health::HealthRegistry;
+use crate::keys::KeyRing;
+use crate::ratelimit::RateLimiter;
+use crate::latency::LatencyTracker;
+use crate::router::Availability;
+use crate::virtual_keys::VirtualKeyTracker;
+use crate::error::{Attempt, ErrorClass};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -7,6 +19,7 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -24,22 +37,57 @@ pub struct RequestLog {
     pub model: String,
     pub effective_model: Option<String>,
     pub provider: Option<String>,
+    /// Id of the provider that served the request; `provider` is its name.
+    #[serde(default)]
+    pub provider_id: Option<String>,
     pub status: String,        // "success", "error", "no_provider"
     pub status_code: Option<u16>,
     pub duration_ms: u64,
+    /// Time until the first body chunk arrived (streaming requests only).
+    pub ttft_ms: Option<u64>,
     pub input_tokens: Option<u64>,
     pub output_tokens: Option<u64>,
     pub estimated_cost: Option<f64>,
+    /// Cost expected before sending, from the estimated prompt and output
+    /// tokens and the serving model's prices.
+    #[serde(default)]
+    pub expected_cost: Option<f64>,
+    /// Label of the pooled API key that served the request.
+    #[serde(default)]
+    pub api_key_label: Option<String>,
+    /// Id of the virtual key the client authenticated with.
+    #[serde(default)]
+    pub virtual_key: Option<String>,
+    /// Routing profile the request went through.
+    #[serde(default)]
+    pub profile: Option<String>,
     pub complexity_tier: Option<String>,
     pub complexity_score: Option<f64>,
     pub error_message: Option<String>,
+    /// Class of the failure that ended an unsuccessful request.
+    pub error_class: Option<ErrorClass>,
+    /// One entry per provider attempt; retries repeat the provider.
     pub providers_tried: Vec<String>,
+    /// Delay and outcome of each attempt, in the order they were made.
+    #[serde(default)]
+    pub attempts: Vec<AttemptLog>,
     pub cache_status: Option<String>,
     pub agentic_mode: Option<bool>,
     pub session_id: Option<String>,
     pub session_pinned: Option<bool>,
 }
 
+/// One attempt against a provider, as recorded in the request log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttemptLog {
+    pub provider: String,
+    /// Time waited before this attempt (0 for the first try on a provider).
+    pub delay_ms: u64,
+    pub status: Option<u16>,
+    /// "success", or the failure's error class.
+    pub outcome: String,
+}
+
 impl RequestLog {
     pub fn new(model: &str) -> Self {
         Self {
@@ -48,25 +96,70 @@ impl RequestLog {
             model: model.to_string(),
             effective_model: None,
             provider: None,
+            provider_id: None,
             status: "pending".to_string(),
             status_code: None,
             duration_ms: 0,
+            ttft_ms: None,
             input_tokens: None,
             output_tokens: None,
             estimated_cost: None,
+            expected_cost: None,
+            api_key_label: None,
+            virtual_key: None,
+            profile: None,
             complexity_tier: None,
             complexity_score: None,
             error_message: None,
+            error_class: None,
             providers_tried: Vec::new(),
+            attempts: Vec::new(),
             cache_status: None,
             agentic_mode: None,
             session_id: None,
             session_pinned: None,
         }
     }
+
+    /// Record an attempt that got a usable reply.
+    pub fn record_success(&mut self, provider: &str, delay: Duration, status: u16) {
+        self.attempts.push(AttemptLog {
+            provider: provider.to_string(),
+            delay_ms: delay.as_millis() as u64,
+            status: Some(status),
+            outcome: "success".to_string(),
+        });
+    }
+
+    /// Record a failed attempt.
+    pub fn record_failure(&mut self, attempt: &Attempt, delay: Duration) {
+        self.attempts.push(AttemptLog {
+            provider: attempt.provider.clone(),
+            delay_ms: delay.as_millis() as u64,
+            status: attempt.status,
+            outcome: attempt.class.as_str().to_string(),
+        });
+    }
+
+    /// Fill in `estimated_cost` from the recorded token usage and the model's pricing.
+    pub fn record_cost(&mut self, provider: &Provider, model_id: &str) {
+        let (Some(input_t), Some(output_t)) = (self.input_tokens, self.output_tokens) else { return };
+        let Some(model_cfg) = provider.models.iter().find(|m| m.id == model_id) else { return };
+        let cost = (input_t as f64 / 1_000_000.0) * model_cfg.input_cost_per_1m
+            + (output_t as f64 / 1_000_000.0) * model_cfg.output_cost_per_1m;
+        self.estimated_cost = Some(cost);
+    }
+}
+
+/// A stored Responses API result: the full chat history up to and including it.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub messages: Vec<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
 }
 
 const MAX_LOGS: usize = 1000;
+const MAX_STORED_RESPONSES: usize = 1000;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -74,10 +167,30 @@ pub struct AppState {
     pub config_path: PathBuf,
     pub logs: Arc<RwLock<Vec<RequestLog>>>,
     pub sessions: Arc<RwLock<HashMap<String, SessionEntry>>>,
+    pub responses: Arc<RwLock<HashMap<String, StoredResponse>>>,
+    /// Pooled HTTP clients for provider requests.
+    pub clients: ClientPool,
+    pub breakers: CircuitBreakers,
+    /// Results of the background provider health checks.
+    pub health: HealthRegistry,
+    /// Observed provider latency, from completed requests.
+    pub latency: LatencyTracker,
+    /// In-flight requests and recent errors per provider.
+    pub load: LoadTracker,
+    /// Rotation, limits and cooldowns of pooled API keys.
+    pub keys: KeyRing,
+    /// Request/token buckets of provider and model rate limits.
+    pub limits: RateLimiter,
+    /// Usage and rate limits of client virtual keys.
+    pub virtual_keys: VirtualKeyTracker,
+    /// Spend per budget scope and day.
+    pub spend: SpendLedger,
+    /// Tokens for the admin API.
+    pub admin: AdminAuth,
 }
 
 impl AppState {
-    pub async fn new(path: PathBuf) -> Self {
+    pub async fn new(path: PathBuf) -> Result<Self> {
         let config = if path.exists() {
             let content = fs::read_to_string(&path).unwrap_or_default();
             serde_json::from_str(&content).unwrap_or_default()
@@ -85,11 +198,28 @@ impl AppState {
             Config::default()
         };
 
+        // Spend is kept next to the config so budgets survive restarts
+        let spend = SpendLedger::load(path.with_file_name("spend.json"))?;
+        Ok(Self { spend, ..Self::from_config(config, path) })
+    }
+
+    pub fn from_config(config: Config, config_path: PathBuf) -> Self {
         Self {
             config: Arc::new(RwLock::new(config)),
-            config_path: path,
+            config_path,
             logs: Arc::new(RwLock::new(Vec::new())),
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            responses: Arc::new(RwLock::new(HashMap::new())),
+            clients: ClientPool::default(),
+            breakers: CircuitBreakers::default(),
+            health: HealthRegistry::default(),
+            latency: LatencyTracker::default(),
+            load: LoadTracker::default(),
+            keys: KeyRing::default(),
+            limits: RateLimiter::default(),
+            virtual_keys: VirtualKeyTracker::default(),
+            spend: SpendLedger::default(),
+            admin: AdminAuth::default(),
         }
     }
 
@@ -100,6 +230,18 @@ impl AppState {
         Ok(())
     }
 
+    /// Live provider state for routing: open circuits, providers that fail
+    /// their health checks, observed latency, current load and rate limits.
+    pub async fn availability(&self) -> Availability {
+        Availability {
+            open_circuits: self.breakers.open_circuits(),
+            down: self.health.down().await,
+            latency: self.latency.snapshot(),
+            load: self.load.snapshot(),
+            rate_limited: self.limits.exhausted(&self.get_config().await.providers),
+        }
+    }
+
     pub async fn get_config(&self) -> Config {
         let config = self.config.read().await;
         config.clone()
@@ -110,10 +252,18 @@ impl AppState {
             let mut config = self.config.write().await;
             *config = new_config;
         }
+        // Provider connection settings may have changed
+        self.clients.clear();
         self.save().await
     }
 
     pub async fn add_log(&self, log: RequestLog) {
+        self.latency.record(&log);
+        self.virtual_keys.record(&log);
+        if let Some(cost) = log.estimated_cost.filter(|c| *c > 0.0) {
+            let config = self.get_config().await;
+            self.spend.record(&config.budgets, &budget::scopes(&log), cost);
+        }
         let mut logs = self.logs.write().await;
         logs.push(log);
         // Keep only the most recent logs
@@ -123,6 +273,21 @@ impl AppState {
         }
     }
 
+    /// Average output tokens of successful requests in a complexity tier, or
+    /// of all successful requests when the tier has none.
+    pub async fn average_output_tokens(&self, complexity_tier: Option<&str>) -> Option<f64> {
+        let logs = self.logs.read().await;
+        let average = |tier: Option<&str>| {
+            let outputs: Vec<u64> = logs
+                .iter()
+                .filter(|l| l.status == "success" && tier.is_none_or(|t| l.complexity_tier.as_deref() == Some(t)))
+                .filter_map(|l| l.output_tokens)
+                .collect();
+            (!outputs.is_empty()).then(|| outputs.iter().sum::<u64>() as f64 / outputs.len() as f64)
+        };
+        complexity_tier.and_then(|t| average(Some(t))).or_else(|| average(None))
+    }
+
     pub async fn get_logs(&self) -> Vec<RequestLog> {
         let logs = self.logs.read().await;
         logs.clone()
@@ -171,4 +336,25 @@ impl AppState {
         let sessions = self.sessions.read().await;
         sessions.len()
     }
+
+    /// Store a Responses API result for `previous_response_id` lookups,
+    /// evicting the oldest entry once full.
+    pub async fn store_response(&self, id: String, messages: Vec<serde_json::Value>) {
+        let mut responses = self.responses.write().await;
+        let full = responses.len() >= MAX_STORED_RESPONSES && !responses.contains_key(&id);
+        let oldest = if full {
+            responses.iter().min_by_key(|(_, r)| r.created_at).map(|(k, _)| k.clone())
+        } else {
+            None
+        };
+        if let Some(oldest) = oldest {
+            responses.remove(&oldest);
+        }
+        responses.insert(id, StoredResponse { messages, created_at: Utc::now() });
+    }
+
+    pub async fn get_response(&self, id: &str) -> Option<StoredResponse> {
+        let responses = self.responses.read().await;
+        responses.get(id).cloned()
+    }
 }
@@ -0,0 +1,386 @@
+//! Server-sent event passthrough for `"stream": true` requests.
+//!
+//! Upstream chunks are relayed to the client as they arrive. A background task
+//! owns the `RequestLog`, watches the events for token usage and writes the log
+//! once the stream ends.
+
+use crate::anthropic;
+use crate::balance::InFlight;
+use crate::bedrock;
+use crate::completions;
+use crate::config::{Provider, ProviderType};
+use crate::gemini;
+use crate::handlers::ClientApi;
+use crate::responses;
+use crate::state::{AppState, RequestLog};
+use axum::{
+    body::{Body, Bytes},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::convert::Infallible;
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+/// An upstream streaming response whose first body chunk has already arrived.
+pub struct UpstreamStream {
+    pub status: StatusCode,
+    pub content_type: Option<String>,
+    pub first_chunk: Bytes,
+    pub first_chunk_at: Instant,
+    pub response: reqwest::Response,
+    pub translator: StreamTranslator,
+}
+
+impl UpstreamStream {
+    /// Wait for the first body chunk of a successful upstream response.
+    ///
+    /// Fails if the upstream errors or closes the body before sending anything,
+    /// which lets the caller fall back to the next candidate.
+    pub async fn open(mut response: reqwest::Response, translator: StreamTranslator) -> Result<Self, String> {
+        let status = StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::OK);
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+
+        match response.chunk().await {
+            Ok(Some(first_chunk)) => Ok(Self {
+                status,
+                content_type,
+                first_chunk,
+                first_chunk_at: Instant::now(),
+                response,
+                translator,
+            }),
+            Ok(None) => Err("stream closed before first byte".to_string()),
+            Err(e) => Err(format!("stream failed before first byte: {}", e)),
+        }
+    }
+}
+
+/// Splits an SSE byte stream into the payloads of its `data:` lines.
+#[derive(Default)]
+pub struct SseDecoder {
+    pending: Vec<u8>,
+}
+
+impl SseDecoder {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let mut payloads = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let Ok(line) = std::str::from_utf8(&line) else { continue };
+            if let Some(data) = line.trim().strip_prefix("data:") {
+                payloads.push(data.trim().to_string());
+            }
+        }
+        payloads
+    }
+}
+
+/// How the upstream stream is decoded into OpenAI chunk payloads.
+enum Source {
+    /// Upstream already speaks the client's API; bytes pass through unchanged
+    /// and are only scanned for usage (and, for Responses, the final response).
+    Passthrough(SseDecoder),
+    /// Upstream emits OpenAI chunks.
+    OpenAI(SseDecoder),
+    /// Upstream emits Anthropic events.
+    Anthropic(SseDecoder, anthropic::ChunkTranslator),
+    /// Upstream emits Gemini `alt=sse` chunks.
+    Gemini(SseDecoder, gemini::ChunkTranslator),
+    /// Upstream emits Bedrock `ConverseStream` event-stream frames.
+    Bedrock(bedrock::EventStreamDecoder, bedrock::ChunkTranslator),
+}
+
+/// How OpenAI chunk payloads are rendered for the client.
+enum Target {
+    /// `data:` lines of the chat completions API.
+    Chat,
+    /// Anthropic Messages events.
+    Anthropic(anthropic::EventTranslator),
+    /// Responses API events.
+    Responses(responses::EventTranslator),
+    /// Legacy `text_completion` chunks.
+    Completions(completions::ChunkTranslator),
+}
+
+/// Converts upstream stream bytes into the stream format the client asked
+/// for, tracking token usage along the way. Every translated stream goes
+/// through OpenAI chunks.
+pub struct StreamTranslator {
+    source: Source,
+    target: Target,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    anthropic_usage: anthropic::Usage,
+    /// Set for Responses clients; the finished response is stored against it.
+    conversation: Option<responses::Conversation>,
+    /// Final Response object seen on a native Responses passthrough.
+    completed: Option<Value>,
+}
+
+impl StreamTranslator {
+    /// Pick the translation for an upstream of `provider_type` answering a
+    /// client of `client`. `native` is set when the upstream was sent the
+    /// client's own request body and so answers in the client's format.
+    pub fn new(provider_type: &ProviderType, client: &ClientApi, model: &str, native: bool) -> Self {
+        let source = if native {
+            Source::Passthrough(SseDecoder::default())
+        } else {
+            match provider_type {
+                ProviderType::Anthropic => Source::Anthropic(SseDecoder::default(), anthropic::ChunkTranslator::new(model)),
+                ProviderType::Google => Source::Gemini(SseDecoder::default(), gemini::ChunkTranslator::new(model)),
+                ProviderType::Bedrock => {
+                    Source::Bedrock(bedrock::EventStreamDecoder::default(), bedrock::ChunkTranslator::new(model))
+                }
+                _ => Source::OpenAI(SseDecoder::default()),
+            }
+        };
+        let (target, conversation) = match client {
+            ClientApi::Chat => (Target::Chat, None),
+            ClientApi::Anthropic => (Target::Anthropic(anthropic::EventTranslator::new(model)), None),
+            ClientApi::Responses(conversation) => (
+                Target::Responses(responses::EventTranslator::new(model, conversation.previous_response_id.clone())),
+                Some(conversation.clone()),
+            ),
+            ClientApi::Completions(options) => {
+                (Target::Completions(completions::ChunkTranslator::new(model, options.clone())), None)
+            }
+        };
+        // OpenAI chunks rendered as OpenAI chunks need no translation at all
+        let source = match (source, &target) {
+            (Source::OpenAI(decoder), Target::Chat) => Source::Passthrough(decoder),
+            (source, _) => source,
+        };
+        Self {
+            source,
+            target,
+            input_tokens: None,
+            output_tokens: None,
+            anthropic_usage: anthropic::Usage::default(),
+            conversation,
+            completed: None,
+        }
+    }
+
+    /// Feed one upstream chunk. Returns the bytes to forward, if any.
+    pub fn feed(&mut self, chunk: Bytes) -> Option<Bytes> {
+        let payloads: Vec<String> = match &mut self.source {
+            Source::Passthrough(decoder) => {
+                for data in decoder.push(&chunk) {
+                    self.observe_native(&data);
+                }
+                return Some(chunk);
+            }
+            Source::OpenAI(decoder) => decoder.push(&chunk),
+            Source::Anthropic(decoder, translator) => {
+                decoder.push(&chunk).iter().flat_map(|data| translator.translate(data)).collect()
+            }
+            Source::Gemini(decoder, translator) => {
+                decoder.push(&chunk).iter().flat_map(|data| translator.translate(data)).collect()
+            }
+            Source::Bedrock(decoder, translator) => {
+                decoder.push(&chunk).iter().flat_map(|event| translator.translate(event)).collect()
+            }
+        };
+        self.render(payloads)
+    }
+
+    /// Called once the upstream ends cleanly. Returns any closing bytes the
+    /// client still needs, for upstreams that end without a terminal event.
+    pub fn finish(&mut self) -> Option<Bytes> {
+        let payloads = match &mut self.source {
+            Source::Passthrough(_) => return None,
+            Source::Gemini(_, translator) => translator.finish(),
+            Source::Bedrock(_, translator) => translator.finish(),
+            Source::OpenAI(_) | Source::Anthropic(..) => Vec::new(),
+        };
+        let mut out = self.render(payloads).map(|b| b.to_vec()).unwrap_or_default();
+        let tail = match &mut self.target {
+            Target::Chat | Target::Completions(_) => Vec::new(),
+            Target::Anthropic(events) => events.finish(),
+            Target::Responses(events) => events.finish(),
+        };
+        out.extend(tail.concat().into_bytes());
+        (!out.is_empty()).then(|| Bytes::from(out))
+    }
+
+    pub fn usage(&self) -> (Option<u64>, Option<u64>) {
+        (
+            self.input_tokens.or(self.anthropic_usage.input_tokens),
+            self.output_tokens.or(self.anthropic_usage.output_tokens),
+        )
+    }
+
+    /// The conversation and final Response of a Responses stream, once complete.
+    pub fn completed_response(&self) -> Option<(&responses::Conversation, &Value)> {
+        let response = match &self.target {
+            Target::Responses(events) => events.completed().or(self.completed.as_ref()),
+            _ => None,
+        }?;
+        Some((self.conversation.as_ref()?, response))
+    }
+
+    /// Whether the outgoing bytes differ from the upstream's own format.
+    fn translates(&self) -> bool {
+        !matches!(self.source, Source::Passthrough(_))
+    }
+
+    /// Record usage from one payload of an untranslated stream, whichever API it speaks.
+    fn observe_native(&mut self, data: &str) {
+        self.anthropic_usage.observe(data);
+        let Ok(event) = serde_json::from_str::<Value>(data) else { return };
+        self.observe_chunk(&event);
+        if matches!(event.get("type").and_then(|t| t.as_str()), Some("response.completed" | "response.incomplete")) {
+            (self.input_tokens, self.output_tokens) = responses::usage_tokens(&event["response"]["usage"]);
+            self.completed = Some(event["response"].clone());
+        }
+    }
+
+    /// Record usage from an OpenAI chunk.
+    fn observe_chunk(&mut self, chunk: &Value) {
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object()) {
+            if let Some(t) = usage.get("prompt_tokens").and_then(|v| v.as_u64()) {
+                self.input_tokens = Some(t);
+            }
+            if let Some(t) = usage.get("completion_tokens").and_then(|v| v.as_u64()) {
+                self.output_tokens = Some(t);
+            }
+        }
+    }
+
+    /// Render OpenAI chunk payloads in the client's format.
+    fn render(&mut self, payloads: Vec<String>) -> Option<Bytes> {
+        for data in &payloads {
+            if let Ok(chunk) = serde_json::from_str::<Value>(data) {
+                self.observe_chunk(&chunk);
+            }
+        }
+        let out: String = match &mut self.target {
+            Target::Chat => payloads.iter().map(|data| format!("data: {}\n\n", data)).collect(),
+            Target::Anthropic(events) => payloads.iter().flat_map(|data| events.translate(data)).collect(),
+            Target::Responses(events) => payloads.iter().flat_map(|data| events.translate(data)).collect(),
+            Target::Completions(chunks) => payloads.iter().flat_map(|data| chunks.translate(data)).collect(),
+        };
+        (!out.is_empty()).then(|| Bytes::from(out))
+    }
+}
+
+/// Relay an upstream stream to the client. The log entry is finalized and stored
+/// by the relay task when the upstream ends, errors, or the client disconnects;
+/// the request counts as in flight on the provider until then.
+pub fn relay(
+    state: AppState,
+    mut log_entry: RequestLog,
+    start: Instant,
+    upstream: UpstreamStream,
+    provider: Provider,
+    effective_model: String,
+    in_flight: InFlight,
+) -> Response {
+    let (tx, rx) = mpsc::channel::<Bytes>(32);
+    let status = upstream.status;
+    let content_type = match upstream.content_type.clone() {
+        Some(ct) if !upstream.translator.translates() => ct,
+        _ => "text/event-stream".to_string(),
+    };
+    log_entry.ttft_ms = Some(upstream.first_chunk_at.duration_since(start).as_millis() as u64);
+
+    tokio::spawn(async move {
+        let UpstreamStream { first_chunk, mut response, mut translator, .. } = upstream;
+        let mut next = Some(first_chunk);
+
+        loop {
+            let chunk = match next.take() {
+                Some(chunk) => chunk,
+                None => match response.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => {
+                        if let Some(tail) = translator.finish() {
+                            let _ = tx.send(tail).await;
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Provider {} stream error: {:?}", provider.name, e);
+                        log_entry.status = "error".to_string();
+                        log_entry.error_message = Some(format!("Upstream stream error: {}", e));
+                        break;
+                    }
+                },
+            };
+            let Some(out) = translator.feed(chunk) else { continue };
+            if tx.send(out).await.is_err() {
+                tracing::info!(provider = %provider.name, "Client disconnected mid-stream");
+                log_entry.error_message = Some("Client disconnected before stream ended".to_string());
+                break;
+            }
+        }
+
+        (log_entry.input_tokens, log_entry.output_tokens) = translator.usage();
+        if let Some((conversation, response)) = translator.completed_response() {
+            responses::remember(&state, conversation, response).await;
+        }
+        log_entry.record_cost(&provider, &effective_model);
+        log_entry.duration_ms = start.elapsed().as_millis() as u64;
+        state.add_log(log_entry).await;
+        drop(in_flight);
+        // Keep the sender alive until the log is stored so the client only sees
+        // end-of-stream once the request is fully accounted for.
+        drop(tx);
+    });
+
+    let body = Body::from_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (Ok::<_, Infallible>(chunk), rx))
+    }));
+
+    (
+        status,
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_usage_read_from_final_chunk() {
+        let mut t = StreamTranslator::new(&ProviderType::OpenAI, &ClientApi::Chat, "gpt-test", false);
+        t.feed(Bytes::from_static(b"data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n"));
+        t.feed(Bytes::from_static(b"data: {\"choices\":[],\"usage\":{\"prompt_tokens\":12,"));
+        t.feed(Bytes::from_static(b"\"completion_tokens\":3}}\n\ndata: [DONE]\n\n"));
+        assert_eq!(t.usage(), (Some(12), Some(3)));
+    }
+
+    #[test]
+    fn test_openai_null_usage_ignored() {
+        let mut t = StreamTranslator::new(&ProviderType::OpenAI, &ClientApi::Chat, "gpt-test", false);
+        let out = t.feed(Bytes::from_static(b"data: {\"choices\":[],\"usage\":null}\n\n"));
+        assert!(out.is_some());
+        assert_eq!(t.usage(), (None, None));
+    }
+
+    #[test]
+    fn test_anthropic_events_split_across_chunks() {
+        let mut t = StreamTranslator::new(&ProviderType::Anthropic, &ClientApi::Chat, "claude-test", false);
+        assert!(t.feed(Bytes::from_static(b"event: content_block_delta\ndata: {\"type\":\"content_block_")).is_none());
+        let out = t
+            .feed(Bytes::from_static(b"delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n"))
+            .unwrap();
+        let text = std::str::from_utf8(&out).unwrap();
+        assert!(text.starts_with("data: {"));
+        assert!(text.contains("chat.completion.chunk"));
+        assert!(text.contains("\"content\":\"Hi\""));
+    }
+}
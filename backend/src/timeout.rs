@@ -0,0 +1,108 @@
+//! Timeouts for provider requests and the client's overall deadline.
+//!
+//! Each provider has a connect, first-byte and total timeout. A client can
+//! also bound the whole request, across every candidate and retry, with the
+//! `x-request-timeout-ms` header; each attempt's timeouts are cut down to the
+//! time that is left.
+
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Request header carrying the client's deadline in milliseconds.
+pub const DEADLINE_HEADER: &str = "x-request-timeout-ms";
+
+/// Per-provider timeouts, in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeouts {
+    /// Establishing the connection.
+    pub connect_ms: u64,
+    /// Until the first byte of a streamed reply: the response headers and the
+    /// first body chunk. Other replies only arrive once the whole generation
+    /// is done, so they are bounded by `total_ms` alone.
+    pub first_byte_ms: u64,
+    /// The whole attempt, including reading the response body.
+    pub total_ms: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect_ms: 10_000,
+            first_byte_ms: 60_000,
+            total_ms: 600_000,
+        }
+    }
+}
+
+/// The timeouts for one attempt, after applying the request deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub first_byte: Duration,
+    pub total: Duration,
+}
+
+impl Timeouts {
+    /// Limits for an attempt starting now, capped by `deadline`. Only
+    /// `streaming` attempts get a first-byte limit shorter than the total.
+    pub fn limits(&self, deadline: Option<Instant>, streaming: bool) -> Limits {
+        let mut total = Duration::from_millis(self.total_ms);
+        if let Some(deadline) = deadline {
+            total = total.min(deadline.saturating_duration_since(Instant::now()));
+        }
+        let first_byte = if streaming { Duration::from_millis(self.first_byte_ms).min(total) } else { total };
+        Limits { first_byte, total }
+    }
+}
+
+/// The deadline a client set with [`DEADLINE_HEADER`], counted from `start`.
+pub fn deadline(headers: &HeaderMap, start: Instant) -> Option<Instant> {
+    let ms = headers.get(DEADLINE_HEADER)?.to_str().ok()?.trim().parse::<u64>().ok()?;
+    Some(start + Duration::from_millis(ms))
+}
+
+/// Whether the deadline, if any, has passed.
+pub fn expired(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|d| Instant::now() >= d)
+}
+
+/// Whether waiting `wait` still leaves time before the deadline.
+pub fn fits(deadline: Option<Instant>, wait: Duration) -> bool {
+    deadline.is_none_or(|d| Instant::now() + wait < d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_header() {
+        let start = Instant::now();
+        let mut headers = HeaderMap::new();
+        assert_eq!(deadline(&headers, start), None);
+        headers.insert(DEADLINE_HEADER, "1500".parse().unwrap());
+        assert_eq!(deadline(&headers, start), Some(start + Duration::from_millis(1500)));
+        headers.insert(DEADLINE_HEADER, "soon".parse().unwrap());
+        assert_eq!(deadline(&headers, start), None);
+    }
+
+    #[test]
+    fn test_limits_capped_by_deadline() {
+        let timeouts = Timeouts { connect_ms: 100, first_byte_ms: 2_000, total_ms: 5_000 };
+        let limits = timeouts.limits(None, true);
+        assert_eq!(limits, Limits { first_byte: Duration::from_secs(2), total: Duration::from_secs(5) });
+        let limits = timeouts.limits(None, false);
+        assert_eq!(limits, Limits { first_byte: Duration::from_secs(5), total: Duration::from_secs(5) });
+
+        let limits = timeouts.limits(Some(Instant::now() + Duration::from_secs(1)), true);
+        assert!(limits.total <= Duration::from_secs(1));
+        assert_eq!(limits.first_byte, limits.total);
+
+        let limits = timeouts.limits(Some(Instant::now() - Duration::from_secs(1)), true);
+        assert_eq!(limits.total, Duration::ZERO);
+        assert!(expired(Some(Instant::now() - Duration::from_millis(1))));
+        assert!(!fits(Some(Instant::now() + Duration::from_millis(10)), Duration::from_secs(1)));
+        assert!(fits(None, Duration::from_secs(1)));
+    }
+}
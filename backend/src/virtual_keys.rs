@@ -0,0 +1,292 @@
+//! Router-issued API keys for clients.
+//!
+//! Once any virtual key is configured, every `/v1` request must present one,
+//! as `Authorization: Bearer <key>` or, for Anthropic clients, `x-api-key`.
+//! Each key can be restricted to some profiles and models, capped at a total
+//! spend and rate limited, and carries team/owner metadata for reporting.
+//! A key's spend is kept in the persisted spend ledger, so the cap holds
+//! across restarts.
+//! Without virtual keys the endpoints stay open.
+
+use crate::admin;
+use crate::budget::{BudgetScope, SpendLedger};
+use crate::error;
+use crate::ratelimit::{RateLimit, RateLimiter};
+use crate::state::RequestLog;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualKey {
+    /// Stable id recorded in request logs; never the secret itself.
+    pub id: String,
+    /// The secret clients send.
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Profiles the key may route through; empty allows all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_profiles: Vec<String>,
+    /// Models the key may use, whether named by the client or picked by
+    /// routing; empty allows all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_models: Vec<String>,
+    /// Total spend allowed, in dollars of estimated cost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_budget: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl VirtualKey {
+    /// Check the key may route through `profile` and, when the client named
+    /// a model rather than a `router/<profile>`, request `model`. The model
+    /// routing settles on is checked again with [`VirtualKey::check_model`].
+    pub fn check_access(&self, profile: Option<&str>, model: Option<&str>) -> Result<(), Rejection> {
+        let allowed = |profile: &str| self.allowed_profiles.is_empty() || self.allowed_profiles.iter().any(|p| p == profile);
+        if let Some(profile) = profile.filter(|p| !allowed(p)) {
+            return Err(Rejection::ProfileNotAllowed(profile.to_string()));
+        }
+        match model {
+            Some(model) => self.check_model(model),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the key may use `model`.
+    pub fn check_model(&self, model: &str) -> Result<(), Rejection> {
+        if self.allowed_models.is_empty() || self.allowed_models.iter().any(|m| m == model) {
+            Ok(())
+        } else {
+            Err(Rejection::ModelNotAllowed(model.to_string()))
+        }
+    }
+}
+
+/// Why a client request was refused before routing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    /// No key, or one that is unknown or disabled.
+    Unauthorized,
+    ProfileNotAllowed(String),
+    ModelNotAllowed(String),
+    BudgetExceeded { spend: f64, budget: f64 },
+    /// Out of rate limit capacity for longer than the key may wait.
+    RateLimited(Duration),
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        match self {
+            Rejection::Unauthorized => error::json_error(
+                StatusCode::UNAUTHORIZED,
+                "invalid_request_error",
+                "invalid_api_key",
+                "Missing or invalid API key",
+                &[],
+            ),
+            Rejection::ProfileNotAllowed(profile) => error::json_error(
+                StatusCode::FORBIDDEN,
+                "permission_error",
+                "profile_not_allowed",
+                &format!("This API key may not use profile '{}'", profile),
+                &[],
+            ),
+            Rejection::ModelNotAllowed(model) => error::json_error(
+                StatusCode::FORBIDDEN,
+                "permission_error",
+                "model_not_allowed",
+                &format!("This API key may not use model '{}'", model),
+                &[],
+            ),
+            Rejection::BudgetExceeded { spend, budget } => error::json_error(
+                StatusCode::TOO_MANY_REQUESTS,
+                "insufficient_quota",
+                "budget_exceeded",
+                &format!("API key budget of ${:.2} exhausted (spent ${:.4})", budget, spend),
+                &[],
+            ),
+            Rejection::RateLimited(wait) => {
+                let mut response = error::json_error(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "rate_limit_error",
+                    "rate_limit_exceeded",
+                    "API key rate limit reached",
+                    &[],
+                );
+                let seconds = wait.as_secs_f64().ceil().max(1.0) as u64;
+                response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+                response
+            }
+        }
+    }
+}
+
+/// The key a client presented, if any.
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    bearer
+        .or_else(|| headers.get("x-api-key").and_then(|v| v.to_str().ok()))
+        .map(str::trim)
+}
+
+/// Find the client's key. `Ok(None)` when no virtual keys are configured.
+pub fn authenticate<'a>(keys: &'a [VirtualKey], headers: &HeaderMap) -> Result<Option<&'a VirtualKey>, Rejection> {
+    if keys.is_empty() {
+        return Ok(None);
+    }
+    let presented = presented_key(headers).ok_or(Rejection::Unauthorized)?;
+    keys.iter()
+        .find(|k| k.enabled && admin::constant_time_eq(&k.key, presented))
+        .map(Some)
+        .ok_or(Rejection::Unauthorized)
+}
+
+/// A key's usage alongside its metadata, as shown in `/api/stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyStatus {
+    pub id: String,
+    pub name: Option<String>,
+    pub team: Option<String>,
+    pub owner: Option<String>,
+    pub enabled: bool,
+    /// Requests since startup.
+    pub requests: u64,
+    /// All-time spend.
+    pub spend: f64,
+    pub max_budget: Option<f64>,
+}
+
+/// Per-key request counts and rate limits, keyed by key id.
+#[derive(Clone, Default)]
+pub struct VirtualKeyTracker {
+    requests: Arc<Mutex<HashMap<String, u64>>>,
+    limits: RateLimiter,
+}
+
+impl VirtualKeyTracker {
+    fn requests(&self, key_id: &str) -> u64 {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).get(key_id).copied().unwrap_or(0)
+    }
+
+    /// Count a completed request against its key.
+    pub fn record(&self, log: &RequestLog) {
+        let Some(ref key_id) = log.virtual_key else { return };
+        *self.requests.lock().unwrap_or_else(|e| e.into_inner()).entry(key_id.clone()).or_default() += 1;
+    }
+
+    /// Check the key's budget against its spend in `ledger`, then take rate
+    /// limit capacity for a request expected to use `tokens`.
+    pub fn check(&self, key: &VirtualKey, ledger: &SpendLedger, tokens: u64) -> Result<(), Rejection> {
+        if let Some(budget) = key.max_budget {
+            let spend = ledger.total(BudgetScope::VirtualKey, &key.id);
+            if spend >= budget {
+                return Err(Rejection::BudgetExceeded { spend, budget });
+            }
+        }
+        match key.rate_limit {
+            Some(ref limit) => self.limits.try_take_limit(&key.id, limit, tokens).map_err(Rejection::RateLimited),
+            None => Ok(()),
+        }
+    }
+
+    pub fn status(&self, keys: &[VirtualKey], ledger: &SpendLedger) -> Vec<KeyStatus> {
+        keys.iter()
+            .map(|key| KeyStatus {
+                id: key.id.clone(),
+                name: key.name.clone(),
+                team: key.team.clone(),
+                owner: key.owner.clone(),
+                enabled: key.enabled,
+                requests: self.requests(&key.id),
+                spend: ledger.total(BudgetScope::VirtualKey, &key.id),
+                max_budget: key.max_budget,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: &str) -> VirtualKey {
+        serde_json::from_value(serde_json::json!({"id": id, "key": format!("vk-{}", id)})).unwrap()
+    }
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let mut disabled = key("off");
+        disabled.enabled = false;
+        let keys = vec![key("a"), disabled];
+
+        let found = authenticate(&keys, &headers("authorization", "Bearer vk-a")).unwrap();
+        assert_eq!(found.map(|k| k.id.as_str()), Some("a"));
+        let found = authenticate(&keys, &headers("x-api-key", "vk-a")).unwrap();
+        assert_eq!(found.map(|k| k.id.as_str()), Some("a"));
+
+        assert_eq!(authenticate(&keys, &HeaderMap::new()).unwrap_err(), Rejection::Unauthorized);
+        assert_eq!(authenticate(&keys, &headers("authorization", "Bearer vk-b")).unwrap_err(), Rejection::Unauthorized);
+        assert_eq!(authenticate(&keys, &headers("authorization", "Bearer vk-off")).unwrap_err(), Rejection::Unauthorized);
+        assert!(matches!(authenticate(&[], &HeaderMap::new()), Ok(None)));
+    }
+
+    #[test]
+    fn test_access_rules() {
+        let mut key = key("a");
+        key.allowed_profiles = vec!["eco".to_string()];
+        key.allowed_models = vec!["gpt-4o-mini".to_string()];
+
+        assert_eq!(key.check_access(Some("eco"), Some("gpt-4o-mini")), Ok(()));
+        assert_eq!(key.check_access(Some("eco"), None), Ok(()));
+        assert_eq!(key.check_access(Some("premium"), None), Err(Rejection::ProfileNotAllowed("premium".to_string())));
+        assert_eq!(key.check_access(Some("eco"), Some("gpt-4o")), Err(Rejection::ModelNotAllowed("gpt-4o".to_string())));
+        assert_eq!(key.check_model("gpt-4o"), Err(Rejection::ModelNotAllowed("gpt-4o".to_string())));
+    }
+
+    #[test]
+    fn test_budget_and_rate_limit() {
+        let tracker = VirtualKeyTracker::default();
+        let ledger = SpendLedger::default();
+        let mut key = key("a");
+        key.max_budget = Some(0.01);
+        key.rate_limit = Some(RateLimit { rpm: Some(2), ..RateLimit::default() });
+
+        assert_eq!(tracker.check(&key, &ledger, 0), Ok(()));
+        let mut log = RequestLog::new("m");
+        log.virtual_key = Some("a".to_string());
+        tracker.record(&log);
+        ledger.record(&[], &["virtual_key:a".to_string()], 0.02);
+        assert!(matches!(tracker.check(&key, &ledger, 0), Err(Rejection::BudgetExceeded { .. })));
+
+        key.max_budget = None;
+        assert_eq!(tracker.check(&key, &ledger, 0), Ok(()));
+        assert!(matches!(tracker.check(&key, &ledger, 0), Err(Rejection::RateLimited(_))));
+
+        let status = tracker.status(std::slice::from_ref(&key), &ledger);
+        assert_eq!((status[0].requests, status[0].spend), (1, 0.02));
+    }
+}
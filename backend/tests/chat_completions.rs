@@ -1,13 +1,25 @@
+mod common;
+
+use backend::admin::AdminAuth;
+use backend::balance::LoadBalancing;
+use backend::breaker::BreakerConfig;
+use backend::budget::{Budget, BudgetAction, BudgetPeriod, BudgetScope};
 use backend::config::{
-    Config, Model, Provider, ProviderType, RoutingProfile, Tier,
+    CandidateOrder, Config, Model, Provider, ProviderType, RoutingProfile, Tier,
 };
+use backend::error::{ErrorAction, ErrorClass, ErrorPolicy};
+use backend::health::{self, HealthConfig};
+use backend::keys::ApiKey;
+use backend::ratelimit::RateLimit;
+use backend::retry::RetryPolicy;
+use backend::scorer::ScorerConfig;
+use backend::timeout::Timeouts;
+use backend::virtual_keys::VirtualKey;
+use common::{make_state, make_test_config, serve};
 use backend::state::AppState;
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{header, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 // ---------------------------------------------------------------------------
@@ -58,54 +70,6 @@ fn chat_request_with_extra(model: &str, extra: Value) -> Value {
     base
 }
 
-/// Create a test Config with one OpenAI-compatible provider pointed at the
-/// given mock endpoint.
-fn make_test_config(endpoint: &str, model_id: &str) -> Config {
-    Config {
-        providers: vec![Provider {
-            id: "mock-provider".to_string(),
-            name: "Mock Provider".to_string(),
-            provider_type: ProviderType::OpenAI,
-            api_key: Some("test-key-123".to_string()),
-            endpoint: Some(endpoint.to_string()),
-            tier: Tier::Cheap,
-            enabled: true,
-            priority: 1,
-            models: vec![Model {
-                id: model_id.to_string(),
-                name: model_id.to_string(),
-                input_cost_per_1m: 1.0,
-                output_cost_per_1m: 2.0,
-                context_window: 128000,
-                supports_vision: false,
-                supports_function_calling: true,
-            }],
-        }],
-        profiles: vec![RoutingProfile {
-            name: "auto".to_string(),
-            description: "test profile".to_string(),
-            allowed_tiers: vec![Tier::Subscription, Tier::Cheap, Tier::Free, Tier::PayPerRequest],
-            model_mapping: HashMap::new(),
-            agentic_model_mapping: HashMap::new(),
-        }],
-        active_profile: "auto".to_string(),
-        scorer: None,
-        cache: None,
-        agentic_mode: false,
-        session: None,
-    }
-}
-
-/// Create an AppState with the given config (no disk persistence needed).
-fn make_state(config: Config) -> AppState {
-    AppState {
-        config: Arc::new(RwLock::new(config)),
-        config_path: PathBuf::from("/dev/null"),
-        logs: Arc::new(RwLock::new(Vec::new())),
-        sessions: Arc::new(RwLock::new(HashMap::new())),
-    }
-}
-
 /// Build the axum Router from AppState without CORS (tests don't need it).
 fn test_app(state: AppState) -> axum::Router {
     backend::app(state)
@@ -179,8 +143,10 @@ async fn test_chat_completions_no_provider_for_model() {
         .unwrap();
 
     assert_eq!(resp.status(), 400);
-    let text = resp.text().await.unwrap();
-    assert!(text.contains("No provider found"));
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert_eq!(body["error"]["code"], "model_not_found");
+    assert!(body["error"]["message"].as_str().unwrap().contains("No provider found"));
 
     // Log entry records "no_provider"
     let logs = state.get_logs().await;
@@ -188,7 +154,8 @@ async fn test_chat_completions_no_provider_for_model() {
     assert_eq!(logs[0].status, "no_provider");
 }
 
-/// When the upstream provider returns an error, the handler returns 503.
+/// When the upstream provider returns an error, the handler returns 503 with
+/// an OpenAI-shaped error listing each attempt.
 #[tokio::test]
 async fn test_chat_completions_all_providers_fail() {
     let mock_server = MockServer::start().await;
@@ -216,15 +183,21 @@ async fn test_chat_completions_all_providers_fail() {
         .unwrap();
 
     assert_eq!(resp.status(), 503);
-    let text = resp.text().await.unwrap();
-    assert!(text.contains("All providers failed"));
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "api_error");
+    assert_eq!(body["error"]["code"], "all_providers_failed");
+    assert!(body["error"]["message"].as_str().unwrap().contains("All providers failed"));
+    assert_eq!(body["error"]["attempts"], json!([
+        {"provider": "Mock Provider", "status": 500, "class": "server_error", "message": "Internal Server Error"}
+    ]));
 
     let logs = state.get_logs().await;
     assert_eq!(logs.len(), 1);
     assert_eq!(logs[0].status, "error");
+    assert_eq!(logs[0].status_code, Some(503));
     assert_eq!(
         logs[0].error_message.as_deref(),
-        Some("All providers failed")
+        Some("All providers failed: Mock Provider: 500 Internal Server Error")
     );
     assert_eq!(logs[0].providers_tried, vec!["Mock Provider"]);
 }
@@ -254,7 +227,16 @@ async fn test_chat_completions_provider_fallback() {
                 name: "Failing Provider".to_string(),
                 provider_type: ProviderType::OpenAI,
                 api_key: Some("key1".to_string()),
+                api_keys: Vec::new(),
                 endpoint: Some(failing_server.uri()),
+                api_version: None,
+                aws: None,
+                retry: None,
+                timeouts: None,
+                proxy: None,
+                ca_cert: None,
+                rate_limit: None,
+                weight: None,
                 tier: Tier::Cheap,
                 enabled: true,
                 priority: 2, // higher priority → tried first
@@ -266,6 +248,9 @@ async fn test_chat_completions_provider_fallback() {
                     context_window: 128000,
                     supports_vision: false,
                     supports_function_calling: true,
+                    deployment: None,
+                    embedding: false,
+                    rate_limit: None,
                 }],
             },
             Provider {
@@ -273,7 +258,16 @@ async fn test_chat_completions_provider_fallback() {
                 name: "Good Provider".to_string(),
                 provider_type: ProviderType::OpenAI,
                 api_key: Some("key2".to_string()),
+                api_keys: Vec::new(),
                 endpoint: Some(succeeding_server.uri()),
+                api_version: None,
+                aws: None,
+                retry: None,
+                timeouts: None,
+                proxy: None,
+                ca_cert: None,
+                rate_limit: None,
+                weight: None,
                 tier: Tier::Cheap,
                 enabled: true,
                 priority: 1,
@@ -285,6 +279,9 @@ async fn test_chat_completions_provider_fallback() {
                     context_window: 128000,
                     supports_vision: false,
                     supports_function_calling: true,
+                    deployment: None,
+                    embedding: false,
+                    rate_limit: None,
                 }],
             },
         ],
@@ -294,12 +291,20 @@ async fn test_chat_completions_provider_fallback() {
             allowed_tiers: vec![Tier::Cheap],
             model_mapping: HashMap::new(),
             agentic_model_mapping: HashMap::new(),
+            ordering: CandidateOrder::Cost,
+            latency_weight: None,
+            load_balancing: LoadBalancing::None,
         }],
         active_profile: "auto".to_string(),
         scorer: None,
         cache: None,
         agentic_mode: false,
         session: None,
+        error_policy: None,
+        circuit_breaker: None,
+        health_check: None,
+        virtual_keys: Vec::new(),
+        budgets: Vec::new(),
     };
 
     let state = make_state(config);
@@ -745,7 +750,16 @@ async fn test_chat_completions_tier_filtering() {
             name: "Sub Only".to_string(),
             provider_type: ProviderType::OpenAI,
             api_key: Some("key".to_string()),
+            api_keys: Vec::new(),
             endpoint: Some(mock_server.uri()),
+            api_version: None,
+            aws: None,
+            retry: None,
+            timeouts: None,
+            proxy: None,
+            ca_cert: None,
+            rate_limit: None,
+            weight: None,
             tier: Tier::Subscription,
             enabled: true,
             priority: 1,
@@ -757,6 +771,9 @@ async fn test_chat_completions_tier_filtering() {
                 context_window: 128000,
                 supports_vision: false,
                 supports_function_calling: true,
+                deployment: None,
+                embedding: false,
+                rate_limit: None,
             }],
         }],
         profiles: vec![RoutingProfile {
@@ -765,12 +782,20 @@ async fn test_chat_completions_tier_filtering() {
             allowed_tiers: vec![Tier::Free],
             model_mapping: HashMap::new(),
             agentic_model_mapping: HashMap::new(),
+            ordering: CandidateOrder::Cost,
+            latency_weight: None,
+            load_balancing: LoadBalancing::None,
         }],
         active_profile: "free-only".to_string(),
         scorer: None,
         cache: None,
         agentic_mode: false,
         session: None,
+        error_policy: None,
+        circuit_breaker: None,
+        health_check: None,
+        virtual_keys: Vec::new(),
+        budgets: Vec::new(),
     };
 
     let state = make_state(config);
@@ -820,3 +845,1725 @@ async fn test_list_models() {
     assert!(ids.contains(&"router/auto"), "Missing router/auto in {:?}", ids);
     assert!(ids.contains(&"test-model"), "Missing test-model in {:?}", ids);
 }
+
+/// OpenAI-style SSE body with a trailing usage chunk.
+fn openai_stream_body() -> String {
+    [
+        r#"data: {"id":"chatcmpl-s1","object":"chat.completion.chunk","created":1700000000,"model":"test-model","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"},"finish_reason":null}]}"#,
+        r#"data: {"id":"chatcmpl-s1","object":"chat.completion.chunk","created":1700000000,"model":"test-model","choices":[{"index":0,"delta":{"content":"lo"},"finish_reason":"stop"}]}"#,
+        r#"data: {"id":"chatcmpl-s1","object":"chat.completion.chunk","created":1700000000,"model":"test-model","choices":[],"usage":{"prompt_tokens":8,"completion_tokens":2,"total_tokens":10}}"#,
+        "data: [DONE]",
+    ]
+    .iter()
+    .map(|line| format!("{}\n\n", line))
+    .collect()
+}
+
+/// Streaming requests are relayed as SSE and the log is finalized with usage.
+#[tokio::test]
+async fn test_chat_completions_streaming_passthrough() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(openai_stream_body(), "text/event-stream"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let config = make_test_config(&mock_server.uri(), "test-model");
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request_with_extra("test-model", json!({"stream": true})))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+    let text = resp.text().await.unwrap();
+    assert_eq!(text, openai_stream_body());
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].status, "success");
+    assert_eq!(logs[0].cache_status.as_deref(), Some("skip"));
+    assert_eq!(logs[0].input_tokens, Some(8));
+    assert_eq!(logs[0].output_tokens, Some(2));
+    assert!(logs[0].ttft_ms.is_some());
+    assert!(logs[0].estimated_cost.is_some());
+}
+
+/// A stream that closes before its first byte falls back to the next candidate.
+#[tokio::test]
+async fn test_chat_completions_streaming_fallback_before_first_byte() {
+    let empty_server = MockServer::start().await;
+    let good_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).insert_header("content-type", "text/event-stream"))
+        .mount(&empty_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(openai_stream_body(), "text/event-stream"),
+        )
+        .mount(&good_server)
+        .await;
+
+    let mut config = make_test_config(&empty_server.uri(), "test-model");
+    let mut second = config.providers[0].clone();
+    second.id = "p2".to_string();
+    second.name = "Good Provider".to_string();
+    second.endpoint = Some(good_server.uri());
+    second.priority = 0;
+    config.providers.push(second);
+
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request_with_extra("test-model", json!({"stream": true})))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), openai_stream_body());
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].provider.as_deref(), Some("Good Provider"));
+    assert_eq!(logs[0].providers_tried, vec!["Mock Provider", "Good Provider"]);
+}
+
+/// Anthropic SSE events are translated into OpenAI chat.completion.chunk events.
+#[tokio::test]
+async fn test_chat_completions_anthropic_stream_translated() {
+    let mock_server = MockServer::start().await;
+
+    let anthropic_stream = [
+        ("message_start", r#"{"type":"message_start","message":{"id":"msg_01","type":"message","role":"assistant","content":[],"model":"claude-test","stop_reason":null,"usage":{"input_tokens":21,"output_tokens":1}}}"#),
+        ("content_block_start", r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#),
+        ("ping", r#"{"type":"ping"}"#),
+        ("content_block_delta", r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#),
+        ("content_block_stop", r#"{"type":"content_block_stop","index":0}"#),
+        ("content_block_start", r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_01","name":"lookup","input":{}}}"#),
+        ("content_block_delta", r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"q\":\"x\"}"}}"#),
+        ("content_block_stop", r#"{"type":"content_block_stop","index":1}"#),
+        ("message_delta", r#"{"type":"message_delta","delta":{"stop_reason":"tool_use","stop_sequence":null},"usage":{"output_tokens":9}}"#),
+        ("message_stop", r#"{"type":"message_stop"}"#),
+    ]
+    .iter()
+    .map(|(event, data)| format!("event: {}\ndata: {}\n\n", event, data))
+    .collect::<String>();
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(wiremock::matchers::header("x-api-key", "test-key-123"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(anthropic_stream, "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "claude-test");
+    config.providers[0].provider_type = ProviderType::Anthropic;
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request_with_extra("claude-test", json!({"stream": true, "max_tokens": 64})))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+    let text = resp.text().await.unwrap();
+    assert!(text.ends_with("data: [DONE]\n\n"));
+
+    let chunks: Vec<Value> = text
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    assert!(chunks.iter().all(|c| c["object"] == "chat.completion.chunk"));
+    assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+    assert!(chunks.iter().any(|c| c["choices"][0]["delta"]["content"] == "Hello"));
+    assert!(chunks.iter().any(|c| c["choices"][0]["delta"]["tool_calls"][0]["function"]["name"] == "lookup"));
+    assert!(chunks.iter().any(|c| c["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"] == "{\"q\":\"x\"}"));
+    assert!(chunks.iter().any(|c| c["choices"][0]["finish_reason"] == "tool_calls"));
+    let usage = chunks.last().unwrap();
+    assert_eq!(usage["usage"]["prompt_tokens"], 21);
+    assert_eq!(usage["usage"]["completion_tokens"], 9);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].input_tokens, Some(21));
+    assert_eq!(logs[0].output_tokens, Some(9));
+}
+
+/// A Google provider is called through `generateContent` and the Gemini
+/// response comes back as an OpenAI chat completion.
+#[tokio::test]
+async fn test_chat_completions_gemini_provider() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-test:generateContent"))
+        .and(wiremock::matchers::header("x-goog-api-key", "test-key-123"))
+        .and(wiremock::matchers::body_partial_json(json!({
+            "contents": [{"role": "user", "parts": [{"text": "Hello"}]}],
+            "generationConfig": {"maxOutputTokens": 64}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "Hi there"}]},
+                "finishReason": "STOP",
+                "index": 0
+            }],
+            "usageMetadata": {"promptTokenCount": 7, "candidatesTokenCount": 3, "totalTokenCount": 10}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "gemini-test");
+    config.providers[0].provider_type = ProviderType::Google;
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request_with_extra("gemini-test", json!({"max_tokens": 64})))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "chat.completion");
+    assert_eq!(body["choices"][0]["message"]["content"], "Hi there");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    assert_eq!(body["usage"]["prompt_tokens"], 7);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(7));
+    assert_eq!(logs[0].output_tokens, Some(3));
+}
+
+/// Gemini `alt=sse` chunks are translated into OpenAI chunks, closed with a
+/// usage chunk and `[DONE]`.
+#[tokio::test]
+async fn test_chat_completions_gemini_stream_translated() {
+    let mock_server = MockServer::start().await;
+
+    let gemini_stream = [
+        r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Hel"}]},"index":0}]}"#,
+        r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"lo"}]},"finishReason":"STOP","index":0}],"usageMetadata":{"promptTokenCount":9,"candidatesTokenCount":2}}"#,
+    ]
+    .iter()
+    .map(|data| format!("data: {}\r\n\r\n", data))
+    .collect::<String>();
+
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-test:streamGenerateContent"))
+        .and(wiremock::matchers::query_param("alt", "sse"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(gemini_stream, "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "gemini-test");
+    config.providers[0].provider_type = ProviderType::Google;
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request_with_extra("gemini-test", json!({"stream": true})))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let text = resp.text().await.unwrap();
+    assert!(text.ends_with("data: [DONE]\n\n"));
+    let chunks: Vec<Value> = text
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    let content: String = chunks
+        .iter()
+        .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+        .collect();
+    assert_eq!(content, "Hello");
+    assert_eq!(chunks.last().unwrap()["usage"]["completion_tokens"], 2);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(9));
+    assert_eq!(logs[0].output_tokens, Some(2));
+}
+
+/// Models of a local Ollama server are discovered from `/api/tags`, priced at
+/// zero, and routed to through its OpenAI-compatible chat endpoint.
+#[tokio::test]
+async fn test_ollama_models_discovered_and_routed() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/tags"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "models": [{"name": "llama3.2:3b", "model": "llama3.2:3b", "size": 2019393189}]
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(wiremock::matchers::body_partial_json(json!({"model": "llama3.2:3b"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "unused");
+    config.providers[0].provider_type = ProviderType::Ollama;
+    config.providers[0].tier = Tier::Free;
+    config.providers[0].models.clear();
+    let mut state = make_state(config);
+    state.admin = AdminAuth::new(Some("admin-token".to_string()), None);
+    let addr = serve(state.clone()).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("http://{}/api/providers/discover", addr))
+        .bearer_auth("admin-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["mock-provider"]["models"], json!(["llama3.2:3b"]));
+
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("llama3.2:3b"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].status, "success");
+    assert_eq!(logs[0].estimated_cost, Some(0.0));
+}
+
+/// Azure OpenAI providers call the model's deployment URL with the
+/// `api-version` query parameter and the key in the `api-key` header.
+#[tokio::test]
+async fn test_chat_completions_azure_deployment() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/prod-gpt4o/chat/completions"))
+        .and(wiremock::matchers::query_param("api-version", "2025-01-01-preview"))
+        .and(wiremock::matchers::header("api-key", "test-key-123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&format!("{}/", mock_server.uri()), "gpt-4o");
+    config.providers[0].provider_type = ProviderType::AzureOpenAI;
+    config.providers[0].api_version = Some("2025-01-01-preview".to_string());
+    config.providers[0].models[0].deployment = Some("prod-gpt4o".to_string());
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .header("Authorization", "Bearer client-token")
+        .json(&chat_request("gpt-4o"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let requests = mock_server.received_requests().await.unwrap();
+    assert!(!requests[0].headers.iter().any(|(name, _)| name.as_str().eq_ignore_ascii_case("authorization")));
+}
+
+fn bedrock_config(endpoint: &str) -> Config {
+    let mut config = make_test_config(endpoint, "claude-haiku");
+    config.providers[0].provider_type = ProviderType::Bedrock;
+    config.providers[0].api_key = None;
+    config.providers[0].aws = Some(backend::config::AwsConfig {
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "secret".to_string(),
+        session_token: Some("session-token".to_string()),
+        region: "eu-west-1".to_string(),
+    });
+    config.providers[0].models[0].deployment = Some("anthropic.claude-3-haiku-20240307-v1:0".to_string());
+    config
+}
+
+/// Bedrock providers get a SigV4-signed Converse request and the reply is
+/// converted into an OpenAI chat completion.
+#[tokio::test]
+async fn test_chat_completions_bedrock_converse() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/model/anthropic.claude-3-haiku-20240307-v1%3A0/converse"))
+        .and(wiremock::matchers::header("x-amz-security-token", "session-token"))
+        .and(wiremock::matchers::body_partial_json(json!({
+            "messages": [{"role": "user", "content": [{"text": "Hello"}]}]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "output": {"message": {"role": "assistant", "content": [{"text": "Hi from Bedrock"}]}},
+            "stopReason": "end_turn",
+            "usage": {"inputTokens": 10, "outputTokens": 4, "totalTokens": 14}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(bedrock_config(&mock_server.uri()));
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("claude-haiku"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "Hi from Bedrock");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let authorization = requests[0]
+        .headers
+        .iter()
+        .find(|(name, _)| name.as_str() == "authorization")
+        .map(|(_, values)| values.iter().map(|v| v.as_str()).collect::<Vec<_>>().join(", "))
+        .unwrap();
+    assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+    assert!(authorization.contains("/eu-west-1/bedrock/aws4_request"));
+    assert!(authorization.contains("SignedHeaders=host;x-amz-date;x-amz-security-token"));
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(10));
+    assert_eq!(logs[0].output_tokens, Some(4));
+}
+
+/// ConverseStream event-stream frames are translated into OpenAI SSE chunks.
+#[tokio::test]
+async fn test_chat_completions_bedrock_stream_translated() {
+    let mock_server = MockServer::start().await;
+
+    let events = [
+        ("messageStart", json!({"role": "assistant"})),
+        ("contentBlockDelta", json!({"contentBlockIndex": 0, "delta": {"text": "Hel"}})),
+        ("contentBlockDelta", json!({"contentBlockIndex": 0, "delta": {"text": "lo"}})),
+        ("contentBlockStop", json!({"contentBlockIndex": 0})),
+        ("messageStop", json!({"stopReason": "max_tokens"})),
+        ("metadata", json!({"usage": {"inputTokens": 6, "outputTokens": 2, "totalTokens": 8}, "metrics": {"latencyMs": 100}})),
+    ];
+    let body: Vec<u8> = events
+        .iter()
+        .flat_map(|(event_type, payload)| backend::bedrock::encode_event(event_type, payload))
+        .collect();
+
+    Mock::given(method("POST"))
+        .and(path("/model/anthropic.claude-3-haiku-20240307-v1%3A0/converse-stream"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/vnd.amazon.eventstream"))
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(bedrock_config(&mock_server.uri()));
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request_with_extra("claude-haiku", json!({"stream": true})))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"].to_str().unwrap(), "text/event-stream");
+    let text = resp.text().await.unwrap();
+    assert!(text.ends_with("data: [DONE]\n\n"));
+    let chunks: Vec<Value> = text
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    let content: String = chunks
+        .iter()
+        .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+        .collect();
+    assert_eq!(content, "Hello");
+    assert!(chunks.iter().any(|c| c["choices"][0]["finish_reason"] == "length"));
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(6));
+    assert_eq!(logs[0].output_tokens, Some(2));
+}
+
+/// Client errors (400 invalid request, 413 too large) reach the client
+/// unchanged and are not retried on other providers.
+#[tokio::test]
+async fn test_chat_completions_client_error_not_retried() {
+    let rejecting_server = MockServer::start().await;
+    let backup_server = MockServer::start().await;
+    let upstream_error = json!({
+        "error": {
+            "message": "This model's maximum context length is 8192 tokens.",
+            "type": "invalid_request_error",
+            "code": "context_length_exceeded"
+        }
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(413).set_body_json(upstream_error.clone()))
+        .expect(1)
+        .mount(&rejecting_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(0)
+        .mount(&backup_server)
+        .await;
+
+    let mut config = make_test_config(&rejecting_server.uri(), "test-model");
+    config.providers[0].priority = 2;
+    let mut backup = config.providers[0].clone();
+    backup.id = "backup".to_string();
+    backup.name = "Backup Provider".to_string();
+    backup.endpoint = Some(backup_server.uri());
+    backup.priority = 1;
+    config.providers.push(backup);
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 413);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body, upstream_error);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].status, "error");
+    assert_eq!(logs[0].status_code, Some(413));
+    assert_eq!(logs[0].error_class, Some(ErrorClass::ContextOverflow));
+    assert_eq!(logs[0].providers_tried, vec!["Mock Provider"]);
+}
+
+/// A class configured as `retry` retries the same provider before anything
+/// else is tried; the final failure's class is logged.
+#[tokio::test]
+async fn test_chat_completions_error_policy_retry() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("upstream overloaded"))
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "test-model");
+    config.error_policy = Some(ErrorPolicy {
+        server_error: ErrorAction::Retry,
+        max_retries: 2,
+        ..ErrorPolicy::default()
+    });
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 503);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["attempts"].as_array().unwrap().len(), 3);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].error_class, Some(ErrorClass::ServerError));
+}
+
+/// A class configured as `abort` stops at the first provider even for
+/// errors that would normally fall through.
+#[tokio::test]
+async fn test_chat_completions_error_policy_abort() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({"error": {"message": "Invalid API key"}})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "test-model");
+    let mut backup = config.providers[0].clone();
+    backup.id = "backup".to_string();
+    backup.name = "Backup Provider".to_string();
+    backup.endpoint = Some("http://127.0.0.1:9".to_string());
+    backup.priority = 0;
+    config.providers.push(backup);
+    config.error_policy = Some(ErrorPolicy { auth: ErrorAction::Abort, ..ErrorPolicy::default() });
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 401);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].providers_tried, vec!["Mock Provider"]);
+    assert_eq!(logs[0].error_class, Some(ErrorClass::Auth));
+}
+
+/// A 429 with `Retry-After` is retried on the same provider after the
+/// requested wait; every attempt is logged with its delay and outcome.
+#[tokio::test]
+async fn test_chat_completions_retry_after_honored() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after-ms", "50")
+                .set_body_json(json!({"error": {"message": "Rate limit reached"}})),
+        )
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "test-model");
+    config.providers[0].retry = Some(RetryPolicy::default());
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].status, "success");
+    assert_eq!(logs[0].providers_tried, vec!["Mock Provider", "Mock Provider"]);
+    let attempts = &logs[0].attempts;
+    assert_eq!(attempts.len(), 2);
+    assert_eq!((attempts[0].delay_ms, attempts[0].status, attempts[0].outcome.as_str()), (0, Some(429), "rate_limited"));
+    assert_eq!((attempts[1].delay_ms, attempts[1].status, attempts[1].outcome.as_str()), (50, Some(200), "success"));
+}
+
+/// When the upstream asks for a wait longer than `max_delay_ms`, the router
+/// moves on to the next candidate instead of sleeping.
+#[tokio::test]
+async fn test_chat_completions_long_retry_after_falls_through() {
+    let limited_server = MockServer::start().await;
+    let backup_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("x-ratelimit-reset-requests", "6m0s")
+                .set_body_json(json!({"error": {"message": "Rate limit reached"}})),
+        )
+        .expect(1)
+        .mount(&limited_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&backup_server)
+        .await;
+
+    let mut config = make_test_config(&limited_server.uri(), "test-model");
+    config.providers[0].priority = 2;
+    config.providers[0].retry = Some(RetryPolicy { max_attempts: 5, ..RetryPolicy::default() });
+    let mut backup = config.providers[0].clone();
+    backup.id = "backup".to_string();
+    backup.name = "Backup Provider".to_string();
+    backup.endpoint = Some(backup_server.uri());
+    backup.priority = 1;
+    config.providers.push(backup);
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].provider.as_deref(), Some("Backup Provider"));
+    assert_eq!(logs[0].providers_tried, vec!["Mock Provider", "Backup Provider"]);
+    assert_eq!(logs[0].attempts[0].outcome, "rate_limited");
+}
+
+/// A provider that sends nothing of a stream before its first-byte timeout is
+/// abandoned for the next candidate.
+#[tokio::test]
+async fn test_chat_completions_first_byte_timeout_falls_through() {
+    let hung_server = MockServer::start().await;
+    let backup_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(openai_stream_body(), "text/event-stream")
+                .set_delay(std::time::Duration::from_secs(5)),
+        )
+        .mount(&hung_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(openai_stream_body(), "text/event-stream"))
+        .expect(1)
+        .mount(&backup_server)
+        .await;
+
+    let mut config = make_test_config(&hung_server.uri(), "test-model");
+    config.providers[0].priority = 2;
+    config.providers[0].timeouts = Some(Timeouts { first_byte_ms: 100, ..Timeouts::default() });
+    let mut backup = config.providers[0].clone();
+    backup.id = "backup".to_string();
+    backup.name = "Backup Provider".to_string();
+    backup.endpoint = Some(backup_server.uri());
+    backup.priority = 1;
+    config.providers.push(backup);
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let started = std::time::Instant::now();
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request_with_extra("test-model", json!({"stream": true})))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert!(resp.text().await.unwrap().contains("[DONE]"));
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].provider.as_deref(), Some("Backup Provider"));
+    assert_eq!(logs[0].attempts[0].outcome, "timeout");
+}
+
+/// Non-streaming replies only arrive once generation is done, so the
+/// first-byte timeout does not apply to them; only the total does.
+#[tokio::test]
+async fn test_chat_completions_slow_non_streaming_not_cut_off() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(openai_success_body())
+                .set_delay(std::time::Duration::from_millis(500)),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "test-model");
+    config.providers[0].timeouts = Some(Timeouts { first_byte_ms: 100, total_ms: 5_000, ..Timeouts::default() });
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].providers_tried, vec!["Mock Provider"]);
+}
+
+/// `x-request-timeout-ms` bounds the whole request; once it passes the
+/// client gets a 504 instead of waiting on the provider.
+#[tokio::test]
+async fn test_chat_completions_request_deadline() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(openai_success_body())
+                .set_delay(std::time::Duration::from_secs(5)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), "test-model"));
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let started = std::time::Instant::now();
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .header("x-request-timeout-ms", "200")
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 504);
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "deadline_exceeded");
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].error_class, Some(ErrorClass::Timeout));
+    assert_eq!(logs[0].status_code, Some(504));
+}
+
+/// Requests to a provider with a `proxy` go through that proxy.
+#[tokio::test]
+async fn test_chat_completions_via_provider_proxy() {
+    let proxy_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("host", "provider.invalid"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(2)
+        .mount(&proxy_server)
+        .await;
+
+    let mut config = make_test_config("http://provider.invalid/v1/chat/completions", "test-model");
+    config.providers[0].proxy = Some(proxy_server.uri());
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    for _ in 0..2 {
+        let resp = reqwest::Client::new()
+            .post(format!("http://{}/v1/chat/completions", addr))
+            .json(&chat_request("test-model"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    // Both requests used the same pooled client
+    assert_eq!(state.clients.len(), 1);
+}
+
+/// After enough consecutive failures a provider's circuit opens: later
+/// requests go straight to the next candidate and the state shows in stats.
+#[tokio::test]
+async fn test_chat_completions_circuit_breaker_opens() {
+    let failing_server = MockServer::start().await;
+    let backup_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+        .expect(2)
+        .mount(&failing_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(3)
+        .mount(&backup_server)
+        .await;
+
+    let mut config = make_test_config(&failing_server.uri(), "test-model");
+    config.providers[0].priority = 2;
+    let mut backup = config.providers[0].clone();
+    backup.id = "backup".to_string();
+    backup.name = "Backup Provider".to_string();
+    backup.endpoint = Some(backup_server.uri());
+    backup.priority = 1;
+    config.providers.push(backup);
+    config.circuit_breaker = Some(BreakerConfig { failure_threshold: 2, ..BreakerConfig::default() });
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    for _ in 0..3 {
+        let resp = client
+            .post(format!("http://{}/v1/chat/completions", addr))
+            .json(&chat_request("test-model"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[2].providers_tried, vec!["Backup Provider"]);
+
+    let stats: Value = client
+        .get(format!("http://{}/api/stats", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let circuits = stats["circuits"].as_array().unwrap();
+    let failing = circuits.iter().find(|c| c["provider_id"] == "mock-provider").unwrap();
+    assert_eq!(failing["state"], "open");
+    assert_eq!(failing["model"], "test-model");
+    let backup = circuits.iter().find(|c| c["provider_id"] == "backup").unwrap();
+    assert_eq!(backup["state"], "closed");
+}
+
+/// A half-open circuit's probe that is stopped by the rate limit before
+/// anything is sent is given back, so the next request can probe.
+#[tokio::test]
+async fn test_chat_completions_rate_limited_probe_released() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "test-model");
+    config.providers[0].rate_limit = Some(RateLimit { rpm: Some(1), ..RateLimit::default() });
+    let breaker_config = BreakerConfig { failure_threshold: 1, cooldown_seconds: 1, ..BreakerConfig::default() };
+    config.circuit_breaker = Some(breaker_config.clone());
+    let state = make_state(config);
+    let addr = serve(state.clone()).await;
+
+    // The failure opens the circuit and uses the one request a minute
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+    assert!(!resp.status().is_success());
+
+    // Once cooled down this request is the probe, but the rate limit stops it
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+    assert!(!resp.status().is_success());
+    let logs = state.get_logs().await;
+    assert!(logs[1].providers_tried.is_empty());
+
+    assert!(state.breakers.allow("mock-provider", "test-model", &breaker_config));
+}
+
+#[tokio::test]
+async fn test_chat_completions_down_provider_skipped() {
+    let down_server = MockServer::start().await;
+    let up_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+        .expect(2)
+        .mount(&down_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(0)
+        .mount(&down_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .and(header("authorization", "Bearer test-key-123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"object": "list", "data": []})))
+        .expect(2)
+        .mount(&up_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&up_server)
+        .await;
+
+    let mut config = make_test_config(&down_server.uri(), "test-model");
+    config.providers[0].priority = 2;
+    let mut up = config.providers[0].clone();
+    up.id = "up".to_string();
+    up.name = "Up Provider".to_string();
+    up.endpoint = Some(up_server.uri());
+    up.priority = 1;
+    config.providers.push(up);
+    let health_config = HealthConfig { failure_threshold: 2, ..HealthConfig::default() };
+    config.health_check = Some(health_config.clone());
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    for _ in 0..2 {
+        health::check_all(&state, &health_config).await;
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    let health: Value = client
+        .get(format!("http://{}/api/health/providers", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let providers = health["providers"].as_array().unwrap();
+    assert_eq!(providers[0]["provider_id"], "mock-provider");
+    assert_eq!(providers[0]["status"], "down");
+    assert_eq!(providers[0]["consecutive_failures"], 2);
+    assert!(providers[0]["last_error"].as_str().unwrap().contains("500"));
+    assert_eq!(providers[1]["provider_id"], "up");
+    assert_eq!(providers[1]["status"], "up");
+    assert!(providers[1]["latency_ms"].is_u64());
+
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].providers_tried, vec!["Up Provider"]);
+}
+
+#[tokio::test]
+async fn test_chat_completions_latency_ordering() {
+    let slow_server = MockServer::start().await;
+    let fast_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(openai_success_body())
+                .set_delay(std::time::Duration::from_millis(300)),
+        )
+        .expect(1)
+        .mount(&slow_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(2)
+        .mount(&fast_server)
+        .await;
+
+    // The slow provider is cheaper, so cost ordering would always pick it
+    let mut config = make_test_config(&slow_server.uri(), "test-model");
+    let mut fast = config.providers[0].clone();
+    fast.id = "fast".to_string();
+    fast.name = "Fast Provider".to_string();
+    fast.endpoint = Some(fast_server.uri());
+    fast.models[0].input_cost_per_1m = 5.0;
+    config.providers.push(fast);
+    config.profiles[0].ordering = CandidateOrder::Latency;
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    for _ in 0..3 {
+        let resp = client
+            .post(format!("http://{}/v1/chat/completions", addr))
+            .json(&chat_request("test-model"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    // Unmeasured providers are tried first, cheapest first; then the fastest wins
+    let providers: Vec<String> = state.get_logs().await.into_iter().filter_map(|l| l.provider).collect();
+    assert_eq!(providers, vec!["Mock Provider", "Fast Provider", "Fast Provider"]);
+
+    let stats: Value = client
+        .get(format!("http://{}/api/stats", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let latency = stats["latency"].as_array().unwrap();
+    assert_eq!(latency.len(), 2);
+    let slow = latency.iter().find(|l| l["provider"] == "Mock Provider").unwrap();
+    assert!(slow["latency_ms"].as_u64().unwrap() >= 300);
+    assert_eq!(slow["samples"], 1);
+}
+
+#[tokio::test]
+async fn test_chat_completions_expected_cost_ordering() {
+    let input_server = MockServer::start().await;
+    let output_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(0)
+        .mount(&input_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&output_server)
+        .await;
+
+    // Cheaper input but much pricier output than the second provider
+    let mut config = make_test_config(&input_server.uri(), "test-model");
+    config.providers[0].models[0].output_cost_per_1m = 20.0;
+    let mut output = config.providers[0].clone();
+    output.id = "cheap-output".to_string();
+    output.name = "Cheap Output".to_string();
+    output.endpoint = Some(output_server.uri());
+    output.models[0].input_cost_per_1m = 2.0;
+    output.models[0].output_cost_per_1m = 4.0;
+    config.providers.push(output);
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .json(&chat_request_with_extra("test-model", json!({"max_tokens": 1000})))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].provider.as_deref(), Some("Cheap Output"));
+    // ~10 prompt tokens at $2/1M plus 1000 output tokens at $4/1M
+    let expected = logs[0].expected_cost.unwrap();
+    assert!(expected > 0.004 && expected < 0.0041, "expected cost {}", expected);
+}
+
+#[tokio::test]
+async fn test_chat_completions_round_robin_accounts() {
+    let first_server = MockServer::start().await;
+    let second_server = MockServer::start().await;
+
+    for server in [&first_server, &second_server] {
+        Mock::given(method("POST"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+            .expect(2)
+            .mount(server)
+            .await;
+    }
+
+    // Two accounts with identical pricing
+    let mut config = make_test_config(&first_server.uri(), "test-model");
+    let mut second = config.providers[0].clone();
+    second.id = "second-account".to_string();
+    second.name = "Second Account".to_string();
+    second.endpoint = Some(second_server.uri());
+    config.providers.push(second);
+    config.profiles[0].load_balancing = LoadBalancing::RoundRobin;
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    for _ in 0..4 {
+        let resp = client
+            .post(format!("http://{}/v1/chat/completions", addr))
+            .json(&chat_request("test-model"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    let providers: Vec<String> = state.get_logs().await.into_iter().filter_map(|l| l.provider).collect();
+    assert_eq!(providers, vec!["Mock Provider", "Second Account", "Mock Provider", "Second Account"]);
+    assert!(state.load.snapshot().in_flight.values().all(|n| *n == 0));
+}
+
+#[tokio::test]
+async fn test_chat_completions_api_key_rotation() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("authorization", "Bearer sk-team-a"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "30"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("authorization", "Bearer sk-team-b"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "test-model");
+    config.providers[0].api_key = None;
+    config.providers[0].api_keys = ["team-a", "team-b"]
+        .iter()
+        .map(|label| ApiKey {
+            key: format!("sk-{}", label),
+            label: Some(label.to_string()),
+            rpm: None,
+            tpm: None,
+            weight: None,
+        })
+        .collect();
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    for _ in 0..2 {
+        let resp = client
+            .post(format!("http://{}/v1/chat/completions", addr))
+            .json(&chat_request("test-model"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    // The rate-limited key is retried at once with the other key and then
+    // stays on cooldown; the provider itself is not penalized
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].providers_tried, vec!["Mock Provider", "Mock Provider"]);
+    assert_eq!(logs[0].attempts[0].outcome, "rate_limited");
+    assert_eq!(logs[0].attempts[1].delay_ms, 0);
+    assert_eq!(logs[0].api_key_label.as_deref(), Some("team-b"));
+    assert_eq!(logs[1].providers_tried, vec!["Mock Provider"]);
+    assert_eq!(logs[1].api_key_label.as_deref(), Some("team-b"));
+    let circuits = state.breakers.status(&BreakerConfig::default());
+    assert!(circuits.iter().all(|c| c.consecutive_failures == 0));
+    assert!(state.load.snapshot().recent_errors.is_empty());
+}
+
+#[tokio::test]
+async fn test_chat_completions_rate_limited_provider_skipped() {
+    let limited_server = MockServer::start().await;
+    let backup_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&limited_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&backup_server)
+        .await;
+
+    let mut config = make_test_config(&limited_server.uri(), "test-model");
+    config.providers[0].priority = 2;
+    config.providers[0].rate_limit = Some(RateLimit { rpm: Some(1), ..RateLimit::default() });
+    let mut backup = config.providers[0].clone();
+    backup.id = "backup".to_string();
+    backup.name = "Backup Provider".to_string();
+    backup.endpoint = Some(backup_server.uri());
+    backup.rate_limit = None;
+    backup.priority = 1;
+    config.providers.push(backup);
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    for _ in 0..2 {
+        let resp = client
+            .post(format!("http://{}/v1/chat/completions", addr))
+            .json(&chat_request("test-model"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    // The preferred provider used its one request a minute; the next request
+    // goes to the backup without trying it
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].providers_tried, vec!["Mock Provider"]);
+    assert_eq!(logs[1].providers_tried, vec!["Backup Provider"]);
+
+    let stats: Value = client
+        .get(format!("http://{}/api/stats", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let limits = stats["rate_limits"].as_array().unwrap();
+    assert_eq!(limits.len(), 1);
+    assert_eq!(limits[0]["provider_id"], "mock-provider");
+    assert_eq!(limits[0]["rpm"], 1);
+    assert_eq!(limits[0]["remaining_requests"], 0);
+}
+
+#[tokio::test]
+async fn test_chat_completions_virtual_keys() {
+    let mock_server = MockServer::start().await;
+
+    // Upstream sees the provider's key, never the client's virtual key
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("authorization", "Bearer test-key-123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = make_test_config(&mock_server.uri(), "test-model");
+    let virtual_key = |id: &str, extra: Value| -> VirtualKey {
+        let mut key = json!({"id": id, "key": format!("vk-{}", id), "team": "research"});
+        key.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(key).unwrap()
+    };
+    config.virtual_keys = vec![
+        virtual_key("alice", json!({"owner": "alice"})),
+        virtual_key("restricted", json!({"allowed_models": ["other-model"]})),
+        virtual_key("broke", json!({"max_budget": 0.0})),
+    ];
+    let state = make_state(config);
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    let send = |key: Option<&'static str>| {
+        let mut request = client
+            .post(format!("http://{}/v1/chat/completions", addr))
+            .json(&chat_request("test-model"));
+        if let Some(key) = key {
+            request = request.bearer_auth(key);
+        }
+        request.send()
+    };
+
+    let resp = send(None).await.unwrap();
+    assert_eq!(resp.status(), 401);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "invalid_api_key");
+    assert_eq!(send(Some("vk-unknown")).await.unwrap().status(), 401);
+
+    let resp = send(Some("vk-restricted")).await.unwrap();
+    assert_eq!(resp.status(), 403);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "model_not_allowed");
+
+    // Naming a profile instead of a model doesn't get around the model list
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .bearer_auth("vk-restricted")
+        .json(&chat_request("router/auto"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "model_not_allowed");
+
+    let resp = send(Some("vk-broke")).await.unwrap();
+    assert_eq!(resp.status(), 429);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "budget_exceeded");
+
+    assert_eq!(send(Some("vk-alice")).await.unwrap().status(), 200);
+    let resp = client
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .header("x-api-key", "vk-alice")
+        .json(&chat_request("test-model"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs.len(), 2);
+    assert!(logs.iter().all(|l| l.virtual_key.as_deref() == Some("alice")));
+
+    let stats: Value = client
+        .get(format!("http://{}/api/stats", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let alice = &stats["virtual_keys"][0];
+    assert_eq!(alice["id"], "alice");
+    assert_eq!(alice["team"], "research");
+    assert_eq!(alice["requests"], 2);
+    assert!(alice["spend"].as_f64().unwrap() > 0.0);
+    assert!(alice.get("key").is_none());
+
+    // The model list only shows what the key may use
+    let models: Value = client
+        .get(format!("http://{}/v1/models", addr))
+        .bearer_auth("vk-restricted")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let ids: Vec<&str> = models["data"].as_array().unwrap().iter().map(|m| m["id"].as_str().unwrap()).collect();
+    assert!(!ids.contains(&"test-model"));
+    assert!(ids.iter().all(|id| id.starts_with("router/")));
+}
+
+#[tokio::test]
+async fn test_chat_completions_budgets() {
+    let premium_server = MockServer::start().await;
+    let cheap_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&premium_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_success_body()))
+        .expect(1)
+        .mount(&cheap_server)
+        .await;
+
+    let mut config = make_test_config(&premium_server.uri(), "test-model");
+    // Without scoring every tier is allowed, subscription first
+    config.scorer = Some(ScorerConfig { enabled: false, ..ScorerConfig::default() });
+    config.providers[0].tier = Tier::Subscription;
+    let mut cheap = config.providers[0].clone();
+    cheap.id = "cheap".to_string();
+    cheap.name = "Cheap Provider".to_string();
+    cheap.endpoint = Some(cheap_server.uri());
+    cheap.tier = Tier::Cheap;
+    config.providers.push(cheap);
+    config.budgets = vec![Budget {
+        scope: BudgetScope::Profile,
+        id: Some("auto".to_string()),
+        period: BudgetPeriod::Daily,
+        limit: 0.000001,
+        action: BudgetAction::Downgrade,
+        warn_at: None,
+    }];
+    let state = make_state(config.clone());
+    let app = test_app(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = reqwest::Client::new();
+    let send = || {
+        client
+            .post(format!("http://{}/v1/chat/completions", addr))
+            .json(&chat_request("test-model"))
+            .send()
+    };
+
+    // The first request uses up the profile's budget; the next is downgraded
+    assert_eq!(send().await.unwrap().status(), 200);
+    assert_eq!(send().await.unwrap().status(), 200);
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].provider.as_deref(), Some("Mock Provider"));
+    assert_eq!(logs[0].profile.as_deref(), Some("auto"));
+    assert_eq!(logs[1].provider.as_deref(), Some("Cheap Provider"));
+    assert_eq!(logs[1].provider_id.as_deref(), Some("cheap"));
+
+    // A global cap that rejects
+    config.budgets.push(Budget {
+        scope: BudgetScope::Global,
+        id: None,
+        period: BudgetPeriod::Monthly,
+        limit: 0.000001,
+        action: BudgetAction::Reject,
+        warn_at: None,
+    });
+    state.update_config(config).await.unwrap();
+    let resp = send().await.unwrap();
+    assert_eq!(resp.status(), 429);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "budget_exceeded");
+
+    let stats: Value = client
+        .get(format!("http://{}/api/stats", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let budgets = stats["budgets"].as_array().unwrap();
+    assert_eq!(budgets.len(), 2);
+    assert_eq!(budgets[0]["scope"], "profile");
+    assert_eq!(budgets[0]["action"], "downgrade");
+    assert_eq!(budgets[0]["exceeded"], true);
+    // Spend counted before the global budget existed still applies to it
+    assert!(budgets[0]["spend"].as_f64().unwrap() > 0.0);
+    assert_eq!(budgets[1]["spend"], budgets[0]["spend"]);
+    assert_eq!(budgets[1]["exceeded"], true);
+}
+
+#[tokio::test]
+async fn test_admin_api_auth() {
+    let config = make_test_config("http://127.0.0.1:1", "test-model");
+    let mut state = make_state(config.clone());
+    state.admin = AdminAuth::new(Some("admin-token".to_string()), Some("viewer-token".to_string()));
+    let addr = serve(state.clone()).await;
+
+    let client = reqwest::Client::new();
+    let url = |path: &str| format!("http://{}{}", addr, path);
+
+    let auth: Value = client.get(url("/api/auth")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(auth, json!({"required": true, "role": null}));
+    assert_eq!(client.get(url("/api/stats")).send().await.unwrap().status(), 401);
+    assert_eq!(client.post(url("/api/config")).json(&config).send().await.unwrap().status(), 401);
+
+    // Login checks the token and reports its role
+    let resp = client.post(url("/api/auth/login")).json(&json!({"token": "guess"})).send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+    let login: Value = client
+        .post(url("/api/auth/login"))
+        .json(&json!({"token": "viewer-token"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(login["role"], "read_only");
+
+    // Read-only: secrets redacted, no changes
+    let resp = client.get(url("/api/config")).bearer_auth("viewer-token").send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["providers"][0]["api_key"], "[redacted]");
+    assert_eq!(client.get(url("/api/logs")).bearer_auth("viewer-token").send().await.unwrap().status(), 200);
+    let resp = client.post(url("/api/config")).bearer_auth("viewer-token").json(&config).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+
+    // Admin: full config, changes allowed
+    let body: Value = client
+        .get(url("/api/config"))
+        .bearer_auth("admin-token")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["providers"][0]["api_key"], "test-key-123");
+    let resp = client.post(url("/api/config")).bearer_auth("admin-token").json(&config).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let auth: Value = client.get(url("/api/auth")).bearer_auth("admin-token").send().await.unwrap().json().await.unwrap();
+    assert_eq!(auth["role"], "admin");
+
+    // Repeated wrong tokens get throttled, but the right one still works.
+    // The guess above was the first of five.
+    for _ in 0..4 {
+        let resp = client.post(url("/api/auth/login")).json(&json!({"token": "guess"})).send().await.unwrap();
+        assert_eq!(resp.status(), 401);
+    }
+    let resp = client.post(url("/api/auth/login")).json(&json!({"token": "guess"})).send().await.unwrap();
+    assert_eq!(resp.status(), 429);
+    assert!(resp.headers().contains_key("retry-after"));
+    assert_eq!(client.get(url("/api/stats")).bearer_auth("guess").send().await.unwrap().status(), 429);
+    let resp = client.post(url("/api/auth/login")).json(&json!({"token": "admin-token"})).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(client.get(url("/api/stats")).bearer_auth("admin-token").send().await.unwrap().status(), 200);
+}
+
+/// Without any admin token configured the admin API only reads, with
+/// secrets redacted.
+#[tokio::test]
+async fn test_admin_api_read_only_without_token() {
+    let config = make_test_config("http://127.0.0.1:1", "test-model");
+    let addr = serve(make_state(config.clone())).await;
+    let client = reqwest::Client::new();
+    let url = |path: &str| format!("http://{}{}", addr, path);
+
+    let auth: Value = client.get(url("/api/auth")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(auth, json!({"required": false, "role": "read_only"}));
+    let body: Value = client.get(url("/api/config")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(body["providers"][0]["api_key"], "[redacted]");
+    let resp = client.post(url("/api/config")).json(&config).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "read_only");
+}
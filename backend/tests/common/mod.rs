@@ -0,0 +1,97 @@
+//! Helpers shared by the integration tests.
+
+// Each test binary compiles this module and uses only some of it
+#![allow(dead_code)]
+
+use backend::balance::LoadBalancing;
+use backend::config::{CandidateOrder, Config, Model, Provider, ProviderType, RoutingProfile, Tier};
+use backend::state::AppState;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A `Cheap` tier provider with one chat model, pointed at the given mock
+/// endpoint.
+pub fn make_provider(endpoint: &str, model_id: &str, provider_type: ProviderType) -> Provider {
+    Provider {
+        id: "mock-provider".to_string(),
+        name: "Mock Provider".to_string(),
+        provider_type,
+        api_key: Some("test-key-123".to_string()),
+        api_keys: Vec::new(),
+        endpoint: Some(endpoint.to_string()),
+        api_version: None,
+        aws: None,
+        retry: None,
+        timeouts: None,
+        proxy: None,
+        ca_cert: None,
+        rate_limit: None,
+        weight: None,
+        tier: Tier::Cheap,
+        enabled: true,
+        priority: 1,
+        models: vec![Model {
+            id: model_id.to_string(),
+            name: model_id.to_string(),
+            input_cost_per_1m: 1.0,
+            output_cost_per_1m: 2.0,
+            context_window: 128000,
+            supports_vision: false,
+            supports_function_calling: true,
+            deployment: None,
+            embedding: false,
+            rate_limit: None,
+        }],
+    }
+}
+
+/// A config routing through an "auto" profile that allows every tier.
+pub fn make_config(providers: Vec<Provider>) -> Config {
+    Config {
+        providers,
+        profiles: vec![RoutingProfile {
+            name: "auto".to_string(),
+            description: "test profile".to_string(),
+            allowed_tiers: vec![Tier::Subscription, Tier::Cheap, Tier::Free, Tier::PayPerRequest],
+            model_mapping: HashMap::new(),
+            agentic_model_mapping: HashMap::new(),
+            ordering: CandidateOrder::Cost,
+            latency_weight: None,
+            load_balancing: LoadBalancing::None,
+        }],
+        active_profile: "auto".to_string(),
+        scorer: None,
+        cache: None,
+        agentic_mode: false,
+        session: None,
+        error_policy: None,
+        circuit_breaker: None,
+        health_check: None,
+        virtual_keys: Vec::new(),
+        budgets: Vec::new(),
+    }
+}
+
+/// Create a test Config with one OpenAI-compatible provider pointed at the
+/// given mock endpoint.
+pub fn make_test_config(endpoint: &str, model_id: &str) -> Config {
+    make_config(vec![make_provider(endpoint, model_id, ProviderType::OpenAI)])
+}
+
+/// Create an AppState with the given config (no disk persistence needed).
+pub fn make_state(config: Config) -> AppState {
+    AppState::from_config(config, PathBuf::from("/dev/null"))
+}
+
+/// Serve the app on a free local port.
+pub async fn serve(state: AppState) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = backend::app(state);
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+            .await
+            .unwrap()
+    });
+    addr
+}
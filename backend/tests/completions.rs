@@ -0,0 +1,138 @@
+mod common;
+
+use backend::config::{Config, ProviderType};
+use common::{make_config, make_provider, make_state, serve};
+use serde_json::{json, Value};
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+fn make_test_config(endpoint: &str, provider_type: ProviderType) -> Config {
+    make_config(vec![make_provider(&format!("{}/v1/chat/completions", endpoint), "gpt-test", provider_type)])
+}
+
+async fn post_completions(addr: std::net::SocketAddr, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{}/v1/completions", addr))
+        .json(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+/// The prompt is sent as a user message and every chat choice comes back as a
+/// completion choice, with echo and legacy logprobs applied.
+#[tokio::test]
+async fn test_completions_via_chat() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({
+            "model": "gpt-test",
+            "messages": [{"role": "user", "content": "Once upon"}],
+            "n": 2,
+            "logprobs": true,
+            "top_logprobs": 1,
+            "max_tokens": 16
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-test",
+            "choices": [
+                {"index": 0, "message": {"role": "assistant", "content": " a time"}, "finish_reason": "stop",
+                 "logprobs": {"content": [{"token": " a time", "logprob": -0.5, "top_logprobs": [{"token": " a time", "logprob": -0.5}]}]}},
+                {"index": 1, "message": {"role": "assistant", "content": " midnight"}, "finish_reason": "length",
+                 "logprobs": {"content": []}}
+            ],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 4, "total_tokens": 7}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), ProviderType::OpenAI));
+    let addr = serve(state.clone()).await;
+
+    let resp = post_completions(addr, &json!({
+        "model": "gpt-test",
+        "prompt": "Once upon",
+        "max_tokens": 16,
+        "n": 2,
+        "logprobs": 1,
+        "echo": true
+    }))
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "text_completion");
+    assert_eq!(body["choices"][0]["text"], "Once upon a time");
+    assert_eq!(body["choices"][0]["logprobs"]["token_logprobs"], json!([-0.5]));
+    assert_eq!(body["choices"][0]["logprobs"]["text_offset"], json!([9]));
+    assert_eq!(body["choices"][1]["text"], "Once upon midnight");
+    assert_eq!(body["choices"][1]["finish_reason"], "length");
+    assert_eq!(body["usage"]["total_tokens"], 7);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(3));
+    assert_eq!(logs[0].output_tokens, Some(4));
+}
+
+/// Streamed chat chunks become `text_completion` chunks; usage is recorded
+/// but not forwarded unless asked for.
+#[tokio::test]
+async fn test_completions_stream() {
+    let mock_server = MockServer::start().await;
+    let sse = concat!(
+        "data: {\"id\":\"c1\",\"model\":\"gpt-test\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hel\"}}]}\n\n",
+        "data: {\"id\":\"c1\",\"model\":\"gpt-test\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"stop\"}]}\n\n",
+        "data: {\"id\":\"c1\",\"model\":\"gpt-test\",\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":2}}\n\n",
+        "data: [DONE]\n\n",
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"stream": true, "messages": [{"role": "user", "content": "Say"}]})))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), ProviderType::OpenAI));
+    let addr = serve(state.clone()).await;
+
+    let resp = post_completions(addr, &json!({"model": "gpt-test", "prompt": "Say", "stream": true})).await;
+    assert_eq!(resp.status(), 200);
+    let text = resp.text().await.unwrap();
+
+    let data: Vec<&str> = text.lines().filter_map(|line| line.strip_prefix("data: ")).collect();
+    assert_eq!(data.last(), Some(&"[DONE]"));
+    let chunks: Vec<Value> = data[..data.len() - 1].iter().map(|d| serde_json::from_str(d).unwrap()).collect();
+    assert_eq!(chunks.len(), 2);
+    assert!(chunks.iter().all(|c| c["object"] == "text_completion"));
+    let streamed: String = chunks.iter().filter_map(|c| c["choices"][0]["text"].as_str()).collect();
+    assert_eq!(streamed, "Hello");
+    assert_eq!(chunks[1]["choices"][0]["finish_reason"], "stop");
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].output_tokens, Some(2));
+}
+
+/// Several prompts in one request are rejected.
+#[tokio::test]
+async fn test_completions_multiple_prompts_rejected() {
+    let state = make_state(make_test_config("http://127.0.0.1:9", ProviderType::OpenAI));
+    let addr = serve(state).await;
+
+    let resp = post_completions(addr, &json!({"model": "gpt-test", "prompt": ["a", "b"]})).await;
+    assert_eq!(resp.status(), 400);
+}
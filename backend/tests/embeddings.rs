@@ -0,0 +1,144 @@
+mod common;
+
+use backend::cache::CacheConfig;
+use backend::config::{Config, Provider, ProviderType, Tier};
+use common::{make_config, make_provider, make_state, serve};
+use serde_json::{json, Value};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+fn embedding_provider(id: &str, endpoint: &str, tier: Tier, cost: f64) -> Provider {
+    let endpoint = format!("{}/v1/chat/completions", endpoint);
+    let mut provider = make_provider(&endpoint, "text-embedding-3-small", ProviderType::OpenAI);
+    provider.id = id.to_string();
+    provider.name = id.to_string();
+    provider.tier = tier;
+    let model = &mut provider.models[0];
+    model.name = "Embedding 3 Small".to_string();
+    model.input_cost_per_1m = cost;
+    model.output_cost_per_1m = 0.0;
+    model.context_window = 8191;
+    model.supports_function_calling = false;
+    model.embedding = true;
+    provider
+}
+
+fn embeddings_body() -> Value {
+    json!({
+        "object": "list",
+        "data": [{"object": "embedding", "index": 0, "embedding": [0.1, 0.2, 0.3]}],
+        "model": "text-embedding-3-small",
+        "usage": {"prompt_tokens": 500000, "total_tokens": 500000}
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+/// Embeddings are sent to the provider's `/embeddings` URL, logged with usage
+/// and cost, and served from the cache on repeat.
+#[tokio::test]
+async fn test_embeddings_routed_logged_and_cached() {
+    let mock_server = MockServer::start().await;
+    let cache_dir = tempfile::tempdir().unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .and(header("Authorization", "Bearer test-key-123"))
+        .and(body_partial_json(json!({"model": "text-embedding-3-small", "input": "hello", "dimensions": 3})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(embeddings_body()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let cache = CacheConfig {
+        enabled: true,
+        ttl_seconds: 60,
+        cache_dir: cache_dir.path().to_string_lossy().to_string(),
+    };
+    let provider = embedding_provider("openai", &mock_server.uri(), Tier::Cheap, 0.02);
+    let state = make_state(Config { cache: Some(cache), ..make_config(vec![provider]) });
+    let addr = serve(state.clone()).await;
+    let client = reqwest::Client::new();
+    let request = json!({"model": "text-embedding-3-small", "input": "hello", "dimensions": 3});
+
+    for _ in 0..2 {
+        let resp = client
+            .post(format!("http://{}/v1/embeddings", addr))
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["data"][0]["embedding"], json!([0.1, 0.2, 0.3]));
+    }
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[0].cache_status.as_deref(), Some("miss"));
+    assert_eq!(logs[0].input_tokens, Some(500000));
+    assert_eq!(logs[0].estimated_cost, Some(0.01));
+    assert_eq!(logs[1].cache_status.as_deref(), Some("hit"));
+}
+
+/// Providers are tried in tier/cost order and a failing one falls through.
+#[tokio::test]
+async fn test_embeddings_fallback_in_cost_order() {
+    let cheap_server = MockServer::start().await;
+    let backup_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&cheap_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(embeddings_body()))
+        .expect(1)
+        .mount(&backup_server)
+        .await;
+
+    let providers = vec![
+        embedding_provider("backup", &backup_server.uri(), Tier::Cheap, 0.10),
+        embedding_provider("cheap", &cheap_server.uri(), Tier::Cheap, 0.02),
+    ];
+    let state = make_state(make_config(providers));
+    let addr = serve(state.clone()).await;
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/embeddings", addr))
+        .json(&json!({"model": "text-embedding-3-small", "input": ["a", "b"]}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].providers_tried, vec!["cheap", "backup"]);
+    assert_eq!(logs[0].provider.as_deref(), Some("backup"));
+}
+
+/// Chat-only models are not offered for embeddings.
+#[tokio::test]
+async fn test_embeddings_no_provider_for_chat_model() {
+    let mut provider = embedding_provider("openai", "http://127.0.0.1:9", Tier::Cheap, 0.02);
+    provider.models[0].embedding = false;
+    let state = make_state(make_config(vec![provider]));
+    let addr = serve(state).await;
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/embeddings", addr))
+        .json(&json!({"model": "text-embedding-3-small", "input": "hello"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
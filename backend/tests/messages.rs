@@ -0,0 +1,206 @@
+mod common;
+
+use backend::config::{Config, ProviderType};
+use common::{make_config, make_provider, make_state, serve};
+use serde_json::{json, Value};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+fn make_test_config(endpoint: &str, model_id: &str, provider_type: ProviderType) -> Config {
+    make_config(vec![make_provider(endpoint, model_id, provider_type)])
+}
+
+fn messages_request(model: &str) -> Value {
+    json!({
+        "model": model,
+        "max_tokens": 128,
+        "system": "You are terse.",
+        "messages": [{"role": "user", "content": "Hello"}]
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+/// An OpenAI-compatible provider receives a chat request and the client gets
+/// an Anthropic Messages response back.
+#[tokio::test]
+async fn test_messages_via_openai_provider() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("Authorization", "Bearer test-key-123"))
+        .and(body_partial_json(json!({
+            "model": "gpt-test",
+            "max_tokens": 128,
+            "messages": [
+                {"role": "system", "content": "You are terse."},
+                {"role": "user", "content": "Hello"}
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-test",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi."},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 2, "total_tokens": 14}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), "gpt-test", ProviderType::OpenAI));
+    let addr = serve(state.clone()).await;
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/messages", addr))
+        .json(&messages_request("gpt-test"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["type"], "message");
+    assert_eq!(body["role"], "assistant");
+    assert_eq!(body["content"][0], json!({"type": "text", "text": "Hi."}));
+    assert_eq!(body["stop_reason"], "end_turn");
+    assert_eq!(body["usage"], json!({"input_tokens": 12, "output_tokens": 2}));
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].status, "success");
+    assert_eq!(logs[0].input_tokens, Some(12));
+}
+
+/// An Anthropic provider receives the native request body unchanged.
+#[tokio::test]
+async fn test_messages_native_anthropic_passthrough() {
+    let mock_server = MockServer::start().await;
+
+    let anthropic_response = json!({
+        "id": "msg_01",
+        "type": "message",
+        "role": "assistant",
+        "model": "claude-test",
+        "content": [{"type": "text", "text": "Hi."}],
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 9, "output_tokens": 3}
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("x-api-key", "test-key-123"))
+        .and(body_partial_json(json!({"system": "You are terse.", "max_tokens": 128})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(anthropic_response.clone()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), "claude-test", ProviderType::Anthropic));
+    let addr = serve(state.clone()).await;
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/messages", addr))
+        .json(&messages_request("claude-test"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body, anthropic_response);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(9));
+    assert_eq!(logs[0].output_tokens, Some(3));
+}
+
+/// Streaming through an OpenAI provider yields Anthropic stream events.
+#[tokio::test]
+async fn test_messages_streaming_via_openai_provider() {
+    let mock_server = MockServer::start().await;
+
+    let openai_stream: String = [
+        r#"data: {"id":"c1","object":"chat.completion.chunk","created":1,"model":"gpt-test","choices":[{"index":0,"delta":{"role":"assistant","content":"Hi"},"finish_reason":null}]}"#,
+        r#"data: {"id":"c1","object":"chat.completion.chunk","created":1,"model":"gpt-test","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+        r#"data: {"id":"c1","object":"chat.completion.chunk","created":1,"model":"gpt-test","choices":[],"usage":{"prompt_tokens":7,"completion_tokens":1,"total_tokens":8}}"#,
+        "data: [DONE]",
+    ]
+    .iter()
+    .map(|line| format!("{}\n\n", line))
+    .collect();
+
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(body_partial_json(json!({"stream": true, "stream_options": {"include_usage": true}})))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(openai_stream, "text/event-stream"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), "gpt-test", ProviderType::OpenAI));
+    let addr = serve(state.clone()).await;
+
+    let mut request = messages_request("gpt-test");
+    request["stream"] = json!(true);
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/messages", addr))
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let text = resp.text().await.unwrap();
+    let events: Vec<&str> = text
+        .split("\n\n")
+        .filter_map(|frame| frame.lines().next())
+        .filter_map(|line| line.strip_prefix("event: "))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            "message_start",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_stop",
+            "message_delta",
+            "message_stop"
+        ]
+    );
+    assert!(text.contains(r#""text":"Hi""#));
+    assert!(text.contains(r#""output_tokens":1"#));
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(7));
+    assert_eq!(logs[0].output_tokens, Some(1));
+}
+
+/// Missing required fields are rejected before routing.
+#[tokio::test]
+async fn test_messages_missing_messages() {
+    let state = make_state(make_test_config("http://127.0.0.1:1", "gpt-test", ProviderType::OpenAI));
+    let addr = serve(state).await;
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/v1/messages", addr))
+        .json(&json!({"model": "gpt-test", "max_tokens": 10}))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 422);
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use backend::config::{Config, ProviderType};
+use common::{make_config, make_provider, make_state, serve};
+use serde_json::{json, Value};
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+fn make_test_config(endpoint: &str, provider_type: ProviderType) -> Config {
+    make_config(vec![make_provider(&format!("{}/v1/chat/completions", endpoint), "gpt-test", provider_type)])
+}
+
+fn chat_completion(content: &str) -> Value {
+    json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-test",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 12, "completion_tokens": 2, "total_tokens": 14}
+    })
+}
+
+async fn post_responses(addr: std::net::SocketAddr, body: &Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{}/v1/responses", addr))
+        .json(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+/// A chat-only provider receives a chat request and the client gets a
+/// Response object back.
+#[tokio::test]
+async fn test_responses_via_chat_provider() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("Authorization", "Bearer test-key-123"))
+        .and(body_partial_json(json!({
+            "model": "gpt-test",
+            "max_tokens": 64,
+            "messages": [
+                {"role": "system", "content": "You are terse."},
+                {"role": "user", "content": "Hello"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "lookup", "parameters": {"type": "object"}}}]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_completion("Hi.")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), ProviderType::CustomOpenAI));
+    let addr = serve(state.clone()).await;
+
+    let resp = post_responses(addr, &json!({
+        "model": "gpt-test",
+        "instructions": "You are terse.",
+        "input": "Hello",
+        "max_output_tokens": 64,
+        "tools": [{"type": "function", "name": "lookup", "parameters": {"type": "object"}}]
+    }))
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "response");
+    assert_eq!(body["status"], "completed");
+    assert_eq!(body["output"][0]["type"], "message");
+    assert_eq!(body["output"][0]["content"][0]["text"], "Hi.");
+    assert_eq!(body["usage"]["input_tokens"], 12);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(12));
+    assert_eq!(logs[0].output_tokens, Some(2));
+}
+
+/// OpenAI providers serve the Responses API themselves; the body is forwarded
+/// unchanged to their `/responses` URL.
+#[tokio::test]
+async fn test_responses_native_passthrough() {
+    let mock_server = MockServer::start().await;
+    let native = json!({
+        "id": "resp_upstream",
+        "object": "response",
+        "status": "completed",
+        "model": "gpt-test",
+        "output": [{"type": "web_search_call", "id": "ws_1", "status": "completed"}],
+        "usage": {"input_tokens": 30, "output_tokens": 5, "total_tokens": 35}
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(body_partial_json(json!({
+            "model": "gpt-test",
+            "input": [{"role": "user", "content": [{"type": "input_text", "text": "News?"}]}],
+            "tools": [{"type": "web_search_preview"}]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(native.clone()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), ProviderType::OpenAI));
+    let addr = serve(state.clone()).await;
+
+    let resp = post_responses(addr, &json!({
+        "model": "gpt-test",
+        "input": [{"role": "user", "content": [{"type": "input_text", "text": "News?"}]}],
+        "tools": [{"type": "web_search_preview"}]
+    }))
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body, native);
+
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(30));
+    assert_eq!(logs[0].output_tokens, Some(5));
+}
+
+/// `previous_response_id` is resolved from the router's store, so the
+/// follow-up carries the whole conversation to a chat-only provider.
+#[tokio::test]
+async fn test_previous_response_id_chained() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({
+            "messages": [
+                {"role": "user", "content": "My name is Ada."},
+                {"role": "assistant", "content": "Hello Ada."},
+                {"role": "user", "content": "What's my name?"}
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_completion("Ada.")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"messages": [{"role": "user", "content": "My name is Ada."}]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_completion("Hello Ada.")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), ProviderType::CustomOpenAI));
+    let addr = serve(state.clone()).await;
+
+    let first: Value = post_responses(addr, &json!({"model": "gpt-test", "input": "My name is Ada."}))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let first_id = first["id"].as_str().unwrap();
+    assert!(first_id.starts_with("resp_"));
+
+    let resp = post_responses(addr, &json!({
+        "model": "gpt-test",
+        "previous_response_id": first_id,
+        "input": "What's my name?"
+    }))
+    .await;
+    assert_eq!(resp.status(), 200);
+    let second: Value = resp.json().await.unwrap();
+    assert_eq!(second["output"][0]["content"][0]["text"], "Ada.");
+    assert_eq!(second["previous_response_id"], first_id);
+}
+
+/// Chat chunks from the provider are streamed back as Responses events and
+/// the completed response is stored for follow-ups.
+#[tokio::test]
+async fn test_responses_stream_via_chat_provider() {
+    let mock_server = MockServer::start().await;
+    let sse = concat!(
+        "data: {\"id\":\"c1\",\"model\":\"gpt-test\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hel\"}}]}\n\n",
+        "data: {\"id\":\"c1\",\"model\":\"gpt-test\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"stop\"}]}\n\n",
+        "data: {\"id\":\"c1\",\"model\":\"gpt-test\",\"choices\":[],\"usage\":{\"prompt_tokens\":8,\"completion_tokens\":2}}\n\n",
+        "data: [DONE]\n\n",
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"stream": true, "stream_options": {"include_usage": true}})))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let state = make_state(make_test_config(&mock_server.uri(), ProviderType::CustomOpenAI));
+    let addr = serve(state.clone()).await;
+
+    let resp = post_responses(addr, &json!({"model": "gpt-test", "input": "Hi", "stream": true})).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "text/event-stream");
+    let text = resp.text().await.unwrap();
+
+    let events: Vec<Value> = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    assert_eq!(events.first().unwrap()["type"], "response.created");
+    let completed = events.last().unwrap();
+    assert_eq!(completed["type"], "response.completed");
+    assert_eq!(completed["response"]["output"][0]["content"][0]["text"], "Hello");
+    assert_eq!(completed["response"]["usage"]["output_tokens"], 2);
+
+    let stored = state.get_response(completed["response"]["id"].as_str().unwrap()).await.unwrap();
+    assert_eq!(stored.messages, vec![
+        json!({"role": "user", "content": "Hi"}),
+        json!({"role": "assistant", "content": "Hello"}),
+    ]);
+    let logs = state.get_logs().await;
+    assert_eq!(logs[0].input_tokens, Some(8));
+}
@@ -1,10 +1,43 @@
+import { useEffect, useState } from "react";
 import { BrowserRouter as Router, Routes, Route, Link } from "react-router-dom";
+import api, { clearToken } from "./api";
 import { Dashboard } from "./components/Dashboard";
 import { Settings } from "./components/Settings";
 import { Logs } from "./components/Logs";
-import { LayoutDashboard, Settings as SettingsIcon, FileText } from "lucide-react";
+import { Login, type Role } from "./components/Login";
+import { LayoutDashboard, Settings as SettingsIcon, FileText, LogOut } from "lucide-react";
+
+interface AuthState {
+  required: boolean;
+  role: Role | null;
+}
 
 function App() {
+  const [auth, setAuth] = useState<AuthState | null>(null);
+
+  useEffect(() => {
+    api
+      .get("/api/auth")
+      .then((res) => setAuth(res.data))
+      // Show the dashboard anyway; its own requests will report the problem
+      .catch(() => setAuth({ required: false, role: null }));
+    const onLogout = () => setAuth((a) => (a ? { ...a, role: null } : a));
+    window.addEventListener("admin-logout", onLogout);
+    return () => window.removeEventListener("admin-logout", onLogout);
+  }, []);
+
+  const logout = () => {
+    clearToken();
+    setAuth((a) => (a ? { ...a, role: null } : a));
+  };
+
+  if (!auth) {
+    return null;
+  }
+  if (auth.required && !auth.role) {
+    return <Login onLogin={(role) => setAuth({ ...auth, role })} />;
+  }
+
   return (
     <Router>
       <div className="flex h-screen bg-gray-100">
@@ -21,6 +54,23 @@ function App() {
               <FileText className="h-4 w-4" /> Logs
             </Link>
           </nav>
+          {(auth.required || auth.role === "read_only") && (
+            <div className="mt-6 border-t pt-4 px-4 space-y-2">
+              {auth.role === "read_only" && (
+                <span
+                  title={auth.required ? undefined : "Set ADMIN_TOKEN to allow changes"}
+                  className="inline-block rounded-full bg-yellow-100 px-2 py-0.5 text-xs font-medium text-yellow-800"
+                >
+                  Read-only
+                </span>
+              )}
+              {auth.required && (
+                <button onClick={logout} className="flex items-center gap-2 text-sm text-gray-500 hover:text-gray-700">
+                  <LogOut className="h-4 w-4" /> Log out
+                </button>
+              )}
+            </div>
+          )}
         </aside>
         <main className="flex-1 p-8 overflow-auto">
           <Routes>
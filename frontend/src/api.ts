@@ -1,7 +1,34 @@
 import axios from 'axios';
 
+const TOKEN_KEY = 'adminToken';
+
 const api = axios.create({
   baseURL: '',
 });
 
+export const getToken = () => localStorage.getItem(TOKEN_KEY);
+export const setToken = (token: string) => localStorage.setItem(TOKEN_KEY, token);
+export const clearToken = () => localStorage.removeItem(TOKEN_KEY);
+
+// Send the admin token with every request
+api.interceptors.request.use((config) => {
+  const token = getToken();
+  if (token) {
+    config.headers.Authorization = `Bearer ${token}`;
+  }
+  return config;
+});
+
+// A rejected token means the session is over; send the user back to login
+api.interceptors.response.use(
+  (response) => response,
+  (error) => {
+    if (error.response?.status === 401 && !error.config?.url?.startsWith('/api/auth')) {
+      clearToken();
+      window.dispatchEvent(new Event('admin-logout'));
+    }
+    return Promise.reject(error);
+  },
+);
+
 export default api;
@@ -48,6 +48,85 @@ interface RequestLog {
   complexity_tier: string | null;
 }
 
+interface CircuitStatus {
+  provider_id: string;
+  model: string;
+  state: "closed" | "open" | "half_open";
+  consecutive_failures: number;
+  error_rate: number;
+  window_requests: number;
+  retry_in_seconds: number | null;
+}
+
+const CIRCUIT_COLORS: Record<CircuitStatus["state"], string> = {
+  closed: "text-green-600",
+  open: "text-red-600",
+  half_open: "text-amber-600",
+};
+
+interface ProviderHealth {
+  provider_id: string;
+  name: string;
+  status: "unknown" | "up" | "down";
+  latency_ms: number | null;
+  last_error: string | null;
+  last_checked: string | null;
+  last_up: string | null;
+  consecutive_failures: number;
+}
+
+interface HealthReport {
+  enabled: boolean;
+  interval_seconds: number;
+  providers: ProviderHealth[];
+}
+
+const HEALTH_COLORS: Record<ProviderHealth["status"], string> = {
+  unknown: "text-gray-500",
+  up: "text-green-600",
+  down: "text-red-600",
+};
+
+interface LatencyStats {
+  provider: string;
+  provider_id: string;
+  model: string;
+  latency_ms: number;
+  ttft_ms: number | null;
+  samples: number;
+}
+
+interface LimitStatus {
+  provider_id: string;
+  model: string | null;
+  rpm: number | null;
+  tpm: number | null;
+  remaining_requests: number | null;
+  remaining_tokens: number | null;
+}
+
+interface VirtualKeyStatus {
+  id: string;
+  name: string | null;
+  team: string | null;
+  owner: string | null;
+  enabled: boolean;
+  requests: number;
+  spend: number;
+  max_budget: number | null;
+}
+
+interface BudgetStatus {
+  scope: string;
+  id?: string;
+  period: string;
+  limit: number;
+  action: string;
+  spend: number;
+  remaining: number;
+  exceeded: boolean;
+}
+
 interface Stats {
   requests: number;
   successful: number;
@@ -59,6 +138,11 @@ interface Stats {
   models: Record<string, ModelStats>;
   complexity_tiers: Record<string, number>;
   recent_requests: RequestLog[];
+  circuits?: CircuitStatus[];
+  latency?: LatencyStats[];
+  rate_limits?: LimitStatus[];
+  virtual_keys?: VirtualKeyStatus[];
+  budgets?: BudgetStatus[];
 }
 
 const TIER_COLORS: Record<string, string> = {
@@ -80,6 +164,7 @@ const PROVIDER_COLORS = [
 
 export function Dashboard() {
   const [stats, setStats] = useState<Stats | null>(null);
+  const [health, setHealth] = useState<HealthReport | null>(null);
   const [autoRefresh, setAutoRefresh] = useState(false);
 
   const fetchStats = useCallback(async () => {
@@ -89,6 +174,12 @@ export function Dashboard() {
     } catch (e) {
       console.error("Failed to fetch stats", e);
     }
+    try {
+      const res = await api.get("/api/health/providers");
+      setHealth(res.data);
+    } catch (e) {
+      console.error("Failed to fetch provider health", e);
+    }
   }, []);
 
   useEffect(() => {
@@ -132,6 +223,7 @@ export function Dashboard() {
       case "error":
         return <XCircle className="h-3.5 w-3.5 text-red-500" />;
       case "no_provider":
+      case "budget_exceeded":
         return <AlertCircle className="h-3.5 w-3.5 text-yellow-500" />;
       default:
         return <Clock className="h-3.5 w-3.5 text-gray-400" />;
@@ -392,6 +484,269 @@ export function Dashboard() {
         </Card>
       )}
 
+      {/* Provider Health */}
+      {health && health.enabled && health.providers.length > 0 && (
+        <Card>
+          <CardHeader className="pb-2">
+            <CardTitle className="text-sm font-medium">
+              Provider Health
+              <span className="ml-2 text-xs font-normal text-gray-500">
+                checked every {health.interval_seconds}s
+              </span>
+            </CardTitle>
+          </CardHeader>
+          <CardContent>
+            <div className="overflow-x-auto">
+              <table className="w-full text-sm">
+                <thead>
+                  <tr className="border-b text-left text-gray-500">
+                    <th className="py-2 pr-4 font-medium">Provider</th>
+                    <th className="py-2 pr-4 font-medium">Status</th>
+                    <th className="py-2 pr-4 font-medium text-right">Latency</th>
+                    <th className="py-2 pr-4 font-medium">Last Checked</th>
+                    <th className="py-2 font-medium">Last Error</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {health.providers.map((h) => (
+                    <tr key={h.provider_id} className="border-b last:border-0">
+                      <td className="py-2 pr-4 font-medium">{h.name}</td>
+                      <td className={`py-2 pr-4 text-xs ${HEALTH_COLORS[h.status]}`}>
+                        {h.status}
+                        {h.status !== "up" &&
+                          h.consecutive_failures > 0 &&
+                          ` (${h.consecutive_failures} failed)`}
+                      </td>
+                      <td className="py-2 pr-4 text-right font-mono text-xs">
+                        {h.latency_ms !== null ? `${h.latency_ms}ms` : "-"}
+                      </td>
+                      <td className="py-2 pr-4 text-xs text-gray-500">
+                        {h.last_checked
+                          ? new Date(h.last_checked).toLocaleTimeString()
+                          : "-"}
+                      </td>
+                      <td
+                        className="py-2 max-w-xs truncate text-xs text-gray-500"
+                        title={h.last_error ?? undefined}
+                      >
+                        {h.last_error ?? "-"}
+                      </td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
+      {/* Latency */}
+      {(stats.latency || []).length > 0 && (
+        <Card>
+          <CardHeader className="pb-2">
+            <CardTitle className="text-sm font-medium">Latency (moving average)</CardTitle>
+          </CardHeader>
+          <CardContent>
+            <div className="overflow-x-auto">
+              <table className="w-full text-sm">
+                <thead>
+                  <tr className="border-b text-left text-gray-500">
+                    <th className="py-2 pr-4 font-medium">Provider</th>
+                    <th className="py-2 pr-4 font-medium">Model</th>
+                    <th className="py-2 pr-4 font-medium text-right">Latency</th>
+                    <th className="py-2 pr-4 font-medium text-right">TTFT</th>
+                    <th className="py-2 font-medium text-right">Samples</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {(stats.latency || []).map((l) => (
+                    <tr
+                      key={`${l.provider_id}/${l.model}`}
+                      className="border-b last:border-0"
+                    >
+                      <td className="py-2 pr-4 font-medium">{l.provider}</td>
+                      <td className="py-2 pr-4 font-mono text-xs">{l.model}</td>
+                      <td className="py-2 pr-4 text-right font-mono text-xs">
+                        {l.latency_ms}ms
+                      </td>
+                      <td className="py-2 pr-4 text-right font-mono text-xs">
+                        {l.ttft_ms !== null ? `${l.ttft_ms}ms` : "-"}
+                      </td>
+                      <td className="py-2 text-right font-mono text-xs">
+                        {l.samples}
+                      </td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
+      {/* Budgets */}
+      {(stats.budgets || []).length > 0 && (
+        <Card>
+          <CardHeader className="pb-2">
+            <CardTitle className="text-sm font-medium">Budgets</CardTitle>
+          </CardHeader>
+          <CardContent>
+            <div className="space-y-3">
+              {(stats.budgets || []).map((b, i) => {
+                const used = b.limit > 0 ? Math.min(b.spend / b.limit, 1) : 1;
+                return (
+                  <div key={i} className="space-y-1">
+                    <div className="flex justify-between text-sm">
+                      <span className="font-medium">
+                        {b.scope === "global" ? "Global" : `${b.scope.replace("_", " ")}: ${b.id}`}
+                        <span className="ml-2 text-xs text-gray-500">{b.period}, {b.action}</span>
+                      </span>
+                      <span className={`font-mono text-xs ${b.exceeded ? "text-red-600" : ""}`}>
+                        ${b.spend.toFixed(4)} / ${b.limit.toFixed(2)}
+                      </span>
+                    </div>
+                    <div className="h-2 w-full rounded-full bg-gray-100">
+                      <div
+                        className={`h-2 rounded-full ${b.exceeded ? "bg-red-500" : used >= 0.8 ? "bg-yellow-500" : "bg-green-500"}`}
+                        style={{ width: `${used * 100}%` }}
+                      />
+                    </div>
+                  </div>
+                );
+              })}
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
+      {/* Virtual Keys */}
+      {(stats.virtual_keys || []).length > 0 && (
+        <Card>
+          <CardHeader className="pb-2">
+            <CardTitle className="text-sm font-medium">Virtual Keys</CardTitle>
+          </CardHeader>
+          <CardContent>
+            <div className="overflow-x-auto">
+              <table className="w-full text-sm">
+                <thead>
+                  <tr className="border-b text-left text-gray-500">
+                    <th className="py-2 pr-4 font-medium">Key</th>
+                    <th className="py-2 pr-4 font-medium">Team</th>
+                    <th className="py-2 pr-4 font-medium">Owner</th>
+                    <th className="py-2 pr-4 font-medium text-right">Requests</th>
+                    <th className="py-2 font-medium text-right">Spend / Budget</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {(stats.virtual_keys || []).map((k) => (
+                    <tr key={k.id} className="border-b last:border-0">
+                      <td className={`py-2 pr-4 font-medium ${k.enabled ? "" : "text-gray-400 line-through"}`}>
+                        {k.name ?? k.id}
+                      </td>
+                      <td className="py-2 pr-4">{k.team ?? "-"}</td>
+                      <td className="py-2 pr-4">{k.owner ?? "-"}</td>
+                      <td className="py-2 pr-4 text-right font-mono text-xs">{k.requests}</td>
+                      <td className="py-2 text-right font-mono text-xs">
+                        ${k.spend.toFixed(4)}
+                        {k.max_budget !== null ? ` / $${k.max_budget.toFixed(2)}` : ""}
+                      </td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
+      {/* Rate Limits */}
+      {(stats.rate_limits || []).length > 0 && (
+        <Card>
+          <CardHeader className="pb-2">
+            <CardTitle className="text-sm font-medium">Rate Limits</CardTitle>
+          </CardHeader>
+          <CardContent>
+            <div className="overflow-x-auto">
+              <table className="w-full text-sm">
+                <thead>
+                  <tr className="border-b text-left text-gray-500">
+                    <th className="py-2 pr-4 font-medium">Provider</th>
+                    <th className="py-2 pr-4 font-medium">Model</th>
+                    <th className="py-2 pr-4 font-medium text-right">Requests Left</th>
+                    <th className="py-2 font-medium text-right">Tokens Left</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {(stats.rate_limits || []).map((l) => (
+                    <tr
+                      key={`${l.provider_id}/${l.model ?? ""}`}
+                      className="border-b last:border-0"
+                    >
+                      <td className="py-2 pr-4 font-medium">{l.provider_id}</td>
+                      <td className="py-2 pr-4 font-mono text-xs">{l.model ?? "all"}</td>
+                      <td className="py-2 pr-4 text-right font-mono text-xs">
+                        {l.rpm !== null ? `${l.remaining_requests} / ${l.rpm}` : "-"}
+                      </td>
+                      <td className="py-2 text-right font-mono text-xs">
+                        {l.tpm !== null ? `${l.remaining_tokens} / ${l.tpm}` : "-"}
+                      </td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
+      {/* Circuit Breakers */}
+      {(stats.circuits || []).length > 0 && (
+        <Card>
+          <CardHeader className="pb-2">
+            <CardTitle className="text-sm font-medium">Circuit Breakers</CardTitle>
+          </CardHeader>
+          <CardContent>
+            <div className="overflow-x-auto">
+              <table className="w-full text-sm">
+                <thead>
+                  <tr className="border-b text-left text-gray-500">
+                    <th className="py-2 pr-4 font-medium">Provider</th>
+                    <th className="py-2 pr-4 font-medium">Model</th>
+                    <th className="py-2 pr-4 font-medium">State</th>
+                    <th className="py-2 pr-4 font-medium text-right">
+                      Consecutive Failures
+                    </th>
+                    <th className="py-2 font-medium text-right">Error Rate</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {(stats.circuits || []).map((c) => (
+                    <tr
+                      key={`${c.provider_id}/${c.model}`}
+                      className="border-b last:border-0"
+                    >
+                      <td className="py-2 pr-4 font-medium">{c.provider_id}</td>
+                      <td className="py-2 pr-4 font-mono text-xs">{c.model}</td>
+                      <td className={`py-2 pr-4 text-xs ${CIRCUIT_COLORS[c.state]}`}>
+                        {c.state.replace("_", "-")}
+                        {c.retry_in_seconds !== null &&
+                          ` (probe in ${c.retry_in_seconds}s)`}
+                      </td>
+                      <td className="py-2 pr-4 text-right font-mono text-xs">
+                        {c.consecutive_failures}
+                      </td>
+                      <td className="py-2 text-right font-mono text-xs">
+                        {(c.error_rate * 100).toFixed(0)}% of {c.window_requests}
+                      </td>
+                    </tr>
+                  ))}
+                </tbody>
+              </table>
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
       {/* Recent Activity */}
       <Card>
         <CardHeader className="pb-2">
@@ -0,0 +1,52 @@
+import { type FormEvent, useState } from "react";
+import api, { setToken } from "../api";
+import { Button } from "./ui/Button";
+import { Input } from "./ui/Input";
+import { Card, CardContent, CardHeader, CardTitle } from "./ui/Card";
+
+export type Role = "admin" | "read_only";
+
+export function Login({ onLogin }: { onLogin: (role: Role) => void }) {
+  const [token, setTokenInput] = useState("");
+  const [error, setError] = useState<string | null>(null);
+
+  const submit = async (e: FormEvent) => {
+    e.preventDefault();
+    try {
+      const res = await api.post("/api/auth/login", { token });
+      setToken(token.trim());
+      setError(null);
+      onLogin(res.data.role);
+    } catch (err: any) {
+      setError(
+        err.response?.status === 429 ? "Too many failed attempts; try again in a minute" : "Invalid admin token",
+      );
+    }
+  };
+
+  return (
+    <div className="flex h-screen items-center justify-center bg-gray-100">
+      <Card className="w-96">
+        <CardHeader>
+          <CardTitle>Claw9Router Admin</CardTitle>
+          <p className="text-sm text-gray-500">Enter the admin token configured on the server.</p>
+        </CardHeader>
+        <CardContent>
+          <form onSubmit={submit} className="space-y-4">
+            <Input
+              type="password"
+              value={token}
+              onChange={(e) => setTokenInput(e.target.value)}
+              placeholder="Admin token"
+              autoFocus
+            />
+            {error && <p className="text-sm text-red-600">{error}</p>}
+            <Button type="submit" className="w-full" disabled={!token.trim()}>
+              Log in
+            </Button>
+          </form>
+        </CardContent>
+      </Card>
+    </div>
+  );
+}
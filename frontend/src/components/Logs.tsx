@@ -26,10 +26,28 @@ interface RequestLog {
   input_tokens: number | null;
   output_tokens: number | null;
   estimated_cost: number | null;
+  expected_cost?: number | null;
+  api_key_label?: string | null;
+  virtual_key?: string | null;
+  profile?: string | null;
   complexity_tier: string | null;
   complexity_score: number | null;
   error_message: string | null;
+  error_class: string | null;
   providers_tried: string[];
+  attempts?: AttemptLog[];
+}
+
+interface AttemptLog {
+  provider: string;
+  delay_ms: number;
+  status: number | null;
+  outcome: string;
+}
+
+function formatAttempt(a: AttemptLog): string {
+  const wait = a.delay_ms > 0 ? ` after ${a.delay_ms}ms` : "";
+  return `${a.provider} (${a.outcome}${wait})`;
 }
 
 interface LogsResponse {
@@ -90,6 +108,7 @@ export function Logs() {
       case "error":
         return <XCircle className="h-4 w-4 text-red-500" />;
       case "no_provider":
+      case "budget_exceeded":
         return <AlertCircle className="h-4 w-4 text-yellow-500" />;
       default:
         return <Clock className="h-4 w-4 text-gray-400" />;
@@ -101,6 +120,7 @@ export function Logs() {
       success: "bg-green-100 text-green-800",
       error: "bg-red-100 text-red-800",
       no_provider: "bg-yellow-100 text-yellow-800",
+      budget_exceeded: "bg-yellow-100 text-yellow-800",
     };
     return (
       <span
@@ -169,6 +189,7 @@ export function Logs() {
                 <option value="success">Success</option>
                 <option value="error">Error</option>
                 <option value="no_provider">No provider</option>
+                <option value="budget_exceeded">Over budget</option>
               </select>
             </div>
             <div className="flex-1 min-w-[140px]">
@@ -267,7 +288,13 @@ export function Logs() {
                                 </div>
                                 <div>
                                   <span className="text-gray-500 block">Providers Tried</span>
-                                  <span>{log.providers_tried.length > 0 ? log.providers_tried.join(" -> ") : "-"}</span>
+                                  <span>
+                                    {log.attempts && log.attempts.length > 0
+                                      ? log.attempts.map(formatAttempt).join(" -> ")
+                                      : log.providers_tried.length > 0
+                                        ? log.providers_tried.join(" -> ")
+                                        : "-"}
+                                  </span>
                                 </div>
                                 <div>
                                   <span className="text-gray-500 block">Input Tokens</span>
@@ -277,9 +304,27 @@ export function Logs() {
                                   <span className="text-gray-500 block">Output Tokens</span>
                                   <span>{log.output_tokens ?? "-"}</span>
                                 </div>
+                                <div>
+                                  <span className="text-gray-500 block">API Key</span>
+                                  <span>{log.api_key_label ?? "-"}</span>
+                                </div>
+                                <div>
+                                  <span className="text-gray-500 block">Virtual Key</span>
+                                  <span>{log.virtual_key ?? "-"}</span>
+                                </div>
+                                <div>
+                                  <span className="text-gray-500 block">Profile</span>
+                                  <span>{log.profile ?? "-"}</span>
+                                </div>
+                                <div>
+                                  <span className="text-gray-500 block">Expected Cost</span>
+                                  <span>{formatCost(log.expected_cost ?? null)}</span>
+                                </div>
                                 {log.error_message && (
                                   <div className="col-span-2 md:col-span-4">
-                                    <span className="text-gray-500 block">Error</span>
+                                    <span className="text-gray-500 block">
+                                      Error{log.error_class ? ` (${log.error_class.replace(/_/g, " ")})` : ""}
+                                    </span>
                                     <span className="text-red-600">{log.error_message}</span>
                                   </div>
                                 )}
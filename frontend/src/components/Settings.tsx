@@ -5,7 +5,7 @@ import { Input } from "./ui/Input";
 import { Card, CardContent, CardHeader, CardTitle } from "./ui/Card";
 import { Plus, Trash2, ChevronDown, ChevronUp, RotateCcw } from "lucide-react";
 
-const PROVIDER_TYPES = ["OpenAI", "Anthropic", "Google", "DeepSeek", "XAI", "CustomOpenAI"];
+const PROVIDER_TYPES = ["OpenAI", "Anthropic", "Google", "DeepSeek", "XAI", "CustomOpenAI", "Ollama", "AzureOpenAI", "Bedrock"];
 const TIERS = ["Subscription", "Cheap", "Free", "PayPerRequest"];
 const COMPLEXITY_TIERS = ["simple", "medium", "complex", "reasoning"];
 const COMPLEXITY_LABELS: Record<string, string> = {
@@ -113,8 +113,8 @@ export function Settings() {
     try {
       await api.post("/api/config", config);
       alert("Config saved!");
-    } catch (e) {
-      alert("Failed to save config");
+    } catch (e: any) {
+      alert(e.response?.status === 403 ? "Read-only access: config cannot be changed" : "Failed to save config");
     }
   };
 
@@ -177,7 +177,52 @@ export function Settings() {
               newProfiles[profileIdx] = { ...profile, model_mapping: newMapping };
               setConfig({ ...config, profiles: newProfiles });
             };
+            const updateProfile = (changes: Record<string, any>) => {
+              const newProfiles = [...config.profiles];
+              newProfiles[profileIdx] = { ...profile, ...changes };
+              setConfig({ ...config, profiles: newProfiles });
+            };
             return (
+              <>
+              <div className="mt-4 border-t pt-4">
+                <h3 className="text-sm font-semibold mb-3">Provider Ordering</h3>
+                <p className="text-xs text-gray-400 mb-3">
+                  How providers within the same tier are ordered: by price, by observed latency, or by a blend of both. Providers that tie share traffic by the load balancing strategy.
+                </p>
+                <div className="grid gap-2 md:grid-cols-3 items-center">
+                  <select
+                    value={profile.ordering || "cost"}
+                    onChange={(e) => updateProfile({ ordering: e.target.value })}
+                    className="flex h-10 w-full rounded-md border border-gray-300 bg-transparent px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-blue-500 focus:ring-offset-2"
+                  >
+                    <option value="cost">Cost</option>
+                    <option value="latency">Latency</option>
+                    <option value="weighted">Weighted cost/latency</option>
+                  </select>
+                  <select
+                    value={profile.load_balancing || "none"}
+                    onChange={(e) => updateProfile({ load_balancing: e.target.value })}
+                    className="flex h-10 w-full rounded-md border border-gray-300 bg-transparent px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-blue-500 focus:ring-offset-2"
+                  >
+                    <option value="none">No load balancing</option>
+                    <option value="weighted_random">Weighted random</option>
+                    <option value="round_robin">Round robin</option>
+                    <option value="least_in_flight">Least in-flight requests</option>
+                    <option value="least_errors">Least recent errors</option>
+                  </select>
+                  {profile.ordering === "weighted" && (
+                    <Input
+                      type="number"
+                      min={0}
+                      max={1}
+                      step={0.1}
+                      value={profile.latency_weight ?? 0.5}
+                      onChange={(e) => updateProfile({ latency_weight: parseFloat(e.target.value) })}
+                      placeholder="Latency weight (0-1)"
+                    />
+                  )}
+                </div>
+              </div>
               <div className="mt-4 border-t pt-4">
                 <h3 className="text-sm font-semibold mb-3">Model Mapping by Complexity Tier</h3>
                 <p className="text-xs text-gray-400 mb-3">
@@ -201,6 +246,7 @@ export function Settings() {
                   ))}
                 </div>
               </div>
+              </>
             );
           })()}
         </CardContent>
@@ -534,6 +580,20 @@ export function Settings() {
                               }}
                             />
                           </div>
+                          <div className="space-y-1">
+                            <label className="text-xs text-gray-500">Load Balancing Weight</label>
+                            <Input
+                              type="number"
+                              min={0}
+                              value={provider.weight ?? 1}
+                              onChange={(e) => {
+                                const newProviders = [...config.providers];
+                                const weight = parseInt(e.target.value);
+                                newProviders[idx] = { ...newProviders[idx], weight: isNaN(weight) ? undefined : weight };
+                                setConfig({ ...config, providers: newProviders });
+                              }}
+                            />
+                          </div>
                         </div>
                         <div className="grid gap-2 md:grid-cols-2">
                           <div className="space-y-1">
@@ -569,6 +629,136 @@ export function Settings() {
                             </select>
                           </div>
                         </div>
+                        <div className="space-y-1">
+                          <label className="text-xs text-gray-500">Rate Limit (RPM / TPM / max wait ms)</label>
+                          {(() => {
+                            const updateLimit = (field: string, value: string) => {
+                              const newProviders = [...config.providers];
+                              const n = parseInt(value);
+                              const limit = { ...newProviders[idx].rate_limit, [field]: isNaN(n) ? undefined : n };
+                              const empty = limit.rpm === undefined && limit.tpm === undefined;
+                              newProviders[idx] = { ...newProviders[idx], rate_limit: empty ? undefined : limit };
+                              setConfig({ ...config, providers: newProviders });
+                            };
+                            return (
+                              <div className="grid gap-2 md:grid-cols-3">
+                                <Input
+                                  type="number"
+                                  min={1}
+                                  value={provider.rate_limit?.rpm ?? ""}
+                                  onChange={(e) => updateLimit("rpm", e.target.value)}
+                                  placeholder="RPM"
+                                />
+                                <Input
+                                  type="number"
+                                  min={1}
+                                  value={provider.rate_limit?.tpm ?? ""}
+                                  onChange={(e) => updateLimit("tpm", e.target.value)}
+                                  placeholder="TPM"
+                                />
+                                <Input
+                                  type="number"
+                                  min={0}
+                                  value={provider.rate_limit?.max_wait_ms ?? ""}
+                                  onChange={(e) => updateLimit("max_wait_ms", e.target.value)}
+                                  placeholder="Max wait (ms)"
+                                  disabled={!provider.rate_limit}
+                                />
+                              </div>
+                            );
+                          })()}
+                        </div>
+                        <div className="space-y-2">
+                          <div className="flex items-center justify-between">
+                            <label className="text-xs text-gray-500 font-semibold">
+                              Key Pool ({provider.api_keys?.length || 0})
+                            </label>
+                            <Button
+                              variant="ghost"
+                              className="text-xs"
+                              onClick={() => {
+                                const newProviders = [...config.providers];
+                                newProviders[idx] = {
+                                  ...newProviders[idx],
+                                  api_keys: [...(newProviders[idx].api_keys || []), { key: "" }],
+                                };
+                                setConfig({ ...config, providers: newProviders });
+                              }}
+                            >
+                              <Plus className="h-3 w-3 mr-1" /> Add Key
+                            </Button>
+                          </div>
+                          {(provider.api_keys || []).length > 0 && (
+                            <p className="text-xs text-gray-400">
+                              Keys are rotated per request; a rate-limited or rejected key cools down while the others are used.
+                            </p>
+                          )}
+                          {(provider.api_keys || []).map((key: any, keyIdx: number) => {
+                            const updateKey = (changes: Record<string, any>) => {
+                              const newProviders = [...config.providers];
+                              const newKeys = [...newProviders[idx].api_keys];
+                              newKeys[keyIdx] = { ...newKeys[keyIdx], ...changes };
+                              newProviders[idx] = { ...newProviders[idx], api_keys: newKeys };
+                              setConfig({ ...config, providers: newProviders });
+                            };
+                            const optionalNumber = (value: string) => {
+                              const n = parseInt(value);
+                              return isNaN(n) ? undefined : n;
+                            };
+                            return (
+                              <div key={keyIdx} className="grid gap-2 md:grid-cols-6 items-center">
+                                <Input
+                                  value={key.label || ""}
+                                  onChange={(e) => updateKey({ label: e.target.value || undefined })}
+                                  placeholder={`key-${keyIdx + 1}`}
+                                />
+                                <Input
+                                  type="password"
+                                  className="md:col-span-2"
+                                  value={key.key || ""}
+                                  onChange={(e) => updateKey({ key: e.target.value })}
+                                  placeholder="sk-..."
+                                />
+                                <Input
+                                  type="number"
+                                  min={1}
+                                  value={key.rpm ?? ""}
+                                  onChange={(e) => updateKey({ rpm: optionalNumber(e.target.value) })}
+                                  placeholder="RPM"
+                                />
+                                <Input
+                                  type="number"
+                                  min={1}
+                                  value={key.tpm ?? ""}
+                                  onChange={(e) => updateKey({ tpm: optionalNumber(e.target.value) })}
+                                  placeholder="TPM"
+                                />
+                                <div className="flex items-center gap-2">
+                                  <Input
+                                    type="number"
+                                    min={0}
+                                    value={key.weight ?? ""}
+                                    onChange={(e) => updateKey({ weight: optionalNumber(e.target.value) })}
+                                    placeholder="Weight"
+                                  />
+                                  <button
+                                    onClick={() => {
+                                      const newProviders = [...config.providers];
+                                      newProviders[idx] = {
+                                        ...newProviders[idx],
+                                        api_keys: newProviders[idx].api_keys.filter((_: any, i: number) => i !== keyIdx),
+                                      };
+                                      setConfig({ ...config, providers: newProviders });
+                                    }}
+                                    className="text-red-400 hover:text-red-600"
+                                  >
+                                    <Trash2 className="h-4 w-4" />
+                                  </button>
+                                </div>
+                              </div>
+                            );
+                          })}
+                        </div>
                         <div className="space-y-2">
                           <div className="flex items-center justify-between">
                             <label className="text-xs text-gray-500 font-semibold">Models ({provider.models?.length || 0})</label>
@@ -716,6 +906,207 @@ export function Settings() {
         ))}
       </div>
 
+      <Card>
+        <CardHeader>
+          <CardTitle className="flex items-center justify-between">
+            <span>Virtual Keys ({config.virtual_keys?.length || 0})</span>
+            <Button
+              variant="ghost"
+              className="text-sm font-normal"
+              onClick={() =>
+                setConfig({
+                  ...config,
+                  virtual_keys: [
+                    ...(config.virtual_keys || []),
+                    { id: "", key: `vk-${crypto.randomUUID().replace(/-/g, "")}`, enabled: true },
+                  ],
+                })
+              }
+            >
+              <Plus className="h-4 w-4 mr-1" /> Add Key
+            </Button>
+          </CardTitle>
+          <p className="text-sm text-gray-500">
+            Keys clients send as a bearer token. Once any key exists, requests without a valid one are rejected with 401. Empty model and profile lists allow everything.
+          </p>
+        </CardHeader>
+        <CardContent className="space-y-4">
+          {(config.virtual_keys || []).map((vk: any, keyIdx: number) => {
+            const updateVirtualKey = (changes: Record<string, any>) => {
+              const newKeys = [...config.virtual_keys];
+              newKeys[keyIdx] = { ...newKeys[keyIdx], ...changes };
+              setConfig({ ...config, virtual_keys: newKeys });
+            };
+            const list = (value: string) => value.split(",").map((v) => v.trim()).filter(Boolean);
+            return (
+              <div key={keyIdx} className="space-y-2 border-b pb-4 last:border-0 last:pb-0">
+                <div className="grid gap-2 md:grid-cols-4 items-center">
+                  <Input
+                    value={vk.id}
+                    onChange={(e) => updateVirtualKey({ id: e.target.value })}
+                    placeholder="Key ID"
+                  />
+                  <Input
+                    type="password"
+                    className="md:col-span-2"
+                    value={vk.key}
+                    onChange={(e) => updateVirtualKey({ key: e.target.value })}
+                    placeholder="vk-..."
+                  />
+                  <div className="flex items-center gap-2">
+                    <label className="flex items-center gap-2 text-sm">
+                      <input
+                        type="checkbox"
+                        checked={vk.enabled ?? true}
+                        onChange={(e) => updateVirtualKey({ enabled: e.target.checked })}
+                        className="h-4 w-4"
+                      />
+                      Enabled
+                    </label>
+                    <Button
+                      variant="ghost"
+                      onClick={() =>
+                        setConfig({
+                          ...config,
+                          virtual_keys: config.virtual_keys.filter((_: any, i: number) => i !== keyIdx),
+                        })
+                      }
+                    >
+                      <Trash2 className="h-4 w-4 text-red-500" />
+                    </Button>
+                  </div>
+                </div>
+                <div className="grid gap-2 md:grid-cols-4">
+                  <Input
+                    value={vk.team || ""}
+                    onChange={(e) => updateVirtualKey({ team: e.target.value || undefined })}
+                    placeholder="Team"
+                  />
+                  <Input
+                    value={vk.owner || ""}
+                    onChange={(e) => updateVirtualKey({ owner: e.target.value || undefined })}
+                    placeholder="Owner"
+                  />
+                  <Input
+                    type="number"
+                    min={0}
+                    step={1}
+                    value={vk.max_budget ?? ""}
+                    onChange={(e) => {
+                      const budget = parseFloat(e.target.value);
+                      updateVirtualKey({ max_budget: isNaN(budget) ? undefined : budget });
+                    }}
+                    placeholder="Budget ($)"
+                  />
+                  <Input
+                    type="number"
+                    min={1}
+                    value={vk.rate_limit?.rpm ?? ""}
+                    onChange={(e) => {
+                      const rpm = parseInt(e.target.value);
+                      updateVirtualKey({ rate_limit: isNaN(rpm) ? undefined : { ...vk.rate_limit, rpm } });
+                    }}
+                    placeholder="RPM"
+                  />
+                </div>
+                <div className="grid gap-2 md:grid-cols-2">
+                  <Input
+                    value={(vk.allowed_models || []).join(", ")}
+                    onChange={(e) => updateVirtualKey({ allowed_models: list(e.target.value) })}
+                    placeholder="Allowed models (comma-separated)"
+                  />
+                  <Input
+                    value={(vk.allowed_profiles || []).join(", ")}
+                    onChange={(e) => updateVirtualKey({ allowed_profiles: list(e.target.value) })}
+                    placeholder="Allowed profiles (comma-separated)"
+                  />
+                </div>
+              </div>
+            );
+          })}
+        </CardContent>
+      </Card>
+
+      <Card>
+        <CardHeader>
+          <CardTitle className="flex items-center justify-between">
+            <span>Budgets ({config.budgets?.length || 0})</span>
+            <Button
+              variant="ghost"
+              className="text-sm font-normal"
+              onClick={() =>
+                setConfig({
+                  ...config,
+                  budgets: [...(config.budgets || []), { scope: "global", period: "monthly", limit: 100, action: "warn" }],
+                })
+              }
+            >
+              <Plus className="h-4 w-4 mr-1" /> Add Budget
+            </Button>
+          </CardTitle>
+          <p className="text-sm text-gray-500">
+            Spend caps per day, week or month. Once a budget is used up, requests are rejected, routed to Free and Cheap providers only, or just logged.
+          </p>
+        </CardHeader>
+        <CardContent className="space-y-2">
+          {(config.budgets || []).map((b: any, budgetIdx: number) => {
+            const updateBudget = (changes: Record<string, any>) => {
+              const newBudgets = [...config.budgets];
+              newBudgets[budgetIdx] = { ...newBudgets[budgetIdx], ...changes };
+              setConfig({ ...config, budgets: newBudgets });
+            };
+            const selectClass =
+              "flex h-10 w-full rounded-md border border-gray-300 bg-transparent px-3 py-2 text-sm focus:outline-none focus:ring-2 focus:ring-blue-500 focus:ring-offset-2";
+            return (
+              <div key={budgetIdx} className="grid gap-2 md:grid-cols-6 items-center">
+                <select
+                  value={b.scope}
+                  onChange={(e) => updateBudget({ scope: e.target.value, id: e.target.value === "global" ? undefined : b.id })}
+                  className={selectClass}
+                >
+                  <option value="global">Global</option>
+                  <option value="provider">Provider</option>
+                  <option value="profile">Profile</option>
+                  <option value="virtual_key">Virtual key</option>
+                </select>
+                <Input
+                  value={b.id || ""}
+                  onChange={(e) => updateBudget({ id: e.target.value || undefined })}
+                  placeholder={b.scope === "global" ? "-" : "ID / name"}
+                  disabled={b.scope === "global"}
+                />
+                <select value={b.period} onChange={(e) => updateBudget({ period: e.target.value })} className={selectClass}>
+                  <option value="daily">Daily</option>
+                  <option value="weekly">Weekly</option>
+                  <option value="monthly">Monthly</option>
+                </select>
+                <Input
+                  type="number"
+                  min={0}
+                  step={1}
+                  value={b.limit}
+                  onChange={(e) => updateBudget({ limit: parseFloat(e.target.value) || 0 })}
+                  placeholder="Limit ($)"
+                />
+                <select value={b.action ?? "reject"} onChange={(e) => updateBudget({ action: e.target.value })} className={selectClass}>
+                  <option value="reject">Reject</option>
+                  <option value="downgrade">Downgrade</option>
+                  <option value="warn">Warn</option>
+                </select>
+                <Button
+                  variant="ghost"
+                  onClick={() =>
+                    setConfig({ ...config, budgets: config.budgets.filter((_: any, i: number) => i !== budgetIdx) })
+                  }
+                >
+                  <Trash2 className="h-4 w-4 text-red-500" />
+                </Button>
+              </div>
+            );
+          })}
+        </CardContent>
+      </Card>
+
       <Card>
         <CardHeader>
           <CardTitle className="flex items-center justify-between">